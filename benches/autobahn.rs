@@ -0,0 +1,123 @@
+//! Banc d'essai de la détection de spam d'Autobahn
+//!
+//! Rejoue des flux de messages synthétiques contre la détection des messages répétés,
+//! puis affiche le débit et l'occupation de l'historique.
+//!
+//! ```sh
+//! cargo bench --bench autobahn
+//! ```
+
+#[allow(dead_code)]
+#[path = "../src/components/autobahn/similarity.rs"]
+mod similarity;
+#[allow(dead_code)]
+#[path = "../src/components/autobahn/history.rs"]
+mod history;
+#[allow(dead_code)]
+#[path = "../src/components/autobahn/detection.rs"]
+mod detection;
+
+use std::time::Instant;
+use detection::{MessageHistory, MessageInfo, Thresholds};
+use serenity::model::id::{ChannelId, GuildId, MessageId, UserId};
+use similarity::Fingerprint;
+
+/// Seuils par défaut d'Autobahn
+const THRESHOLDS: Thresholds = Thresholds { max_messages: 4, window: 20, similarity: 80, max_channels: 3 };
+/// Nombre de salons de chaque serveur
+const CHANNELS: u64 = 5;
+
+/// Générateur pseudo-aléatoire déterministe (xorshift)
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+const WORDS: &[&str] = &[
+    "salut", "tout", "le", "monde", "quelqu'un", "sait", "comment", "compiler", "ce", "projet",
+    "rust", "erreur", "borrow", "checker", "merci", "pour", "aide", "lien", "code", "question",
+];
+
+/// Scénario de flux de messages
+struct Scenario {
+    name: &'static str,
+    guilds: u64,
+    users: u64,
+    /// Proportion de spammeurs, en pourcents
+    spammers: u64,
+    /// Nombre de messages par seconde
+    rate: u64,
+    messages: u64,
+}
+
+fn message(rng: &mut Rng, spammer: bool) -> String {
+    if spammer {
+        // Même message avec une variation pour contourner une comparaison exacte
+        format!("Free nitro sur discord-gift.example {}", "!".repeat(rng.below(4) as usize))
+    } else {
+        (0..3 + rng.below(10))
+            .map(|_| WORDS[rng.below(WORDS.len() as u64) as usize])
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+fn run(scenario: &Scenario) {
+    let mut rng = Rng(0x2545F4914F6CDD1D);
+    let mut history = MessageHistory::default();
+    let mut detections = 0u64;
+    let mut peak_entries = 0usize;
+    let stream = (0..scenario.messages)
+        .map(|i| {
+            let user = rng.below(scenario.users);
+            let guild = user % scenario.guilds;
+            let spammer = user % 100 < scenario.spammers;
+            // Les spammeurs restent dans leur salon pour ne pas être détectés sur plusieurs salons
+            let channel = if spammer { user % CHANNELS } else { rng.below(CHANNELS) };
+            let time = i as i64 / scenario.rate as i64;
+            (time, (GuildId(guild + 1), UserId(user + 1)), (ChannelId(guild * CHANNELS + channel + 1), MessageId(i + 1)), message(&mut rng, spammer))
+        })
+        .collect::<Vec<_>>();
+    let start = Instant::now();
+    for (i, (time, key, id, content)) in stream.into_iter().enumerate() {
+        let msg_info = MessageInfo { time, id, attachments: 0, fingerprint: Fingerprint::new(&content) };
+        // Même traitement de l'historique que la réception d'un message par Autobahn
+        if detection::detect(&history, &key, &msg_info, &THRESHOLDS).is_some() {
+            detections += 1;
+            history.remove(&key);
+        } else {
+            history.push(key, msg_info, THRESHOLDS.window, history::capacity(THRESHOLDS.max_messages.max(THRESHOLDS.max_channels)));
+        }
+        if i % 10_000 == 0 {
+            history.prune(time);
+            peak_entries = peak_entries.max(history.stats(time).entries);
+        }
+    }
+    let elapsed = start.elapsed();
+    let end = (scenario.messages / scenario.rate) as i64;
+    let stats = history.stats(end);
+    println!("{}", scenario.name);
+    println!("  {} messages en {:.2?} ({:.0} ns/message)", scenario.messages, elapsed, elapsed.as_nanos() as f64 / scenario.messages as f64);
+    println!("  {} détections", detections);
+    println!("  historique: {} membres, {} messages (pic {}), {} remplacés, {} oubliés", stats.users, stats.entries, peak_entries, stats.dropped, stats.evicted);
+}
+
+fn main() {
+    let scenarios = [
+        Scenario { name: "Serveur calme", guilds: 1, users: 200, spammers: 0, rate: 5, messages: 50_000 },
+        Scenario { name: "Vague de spam", guilds: 1, users: 500, spammers: 20, rate: 50, messages: 200_000 },
+        Scenario { name: "Trafic multi-serveurs", guilds: 50, users: 100_000, spammers: 2, rate: 500, messages: 1_000_000 },
+    ];
+    for scenario in &scenarios {
+        run(scenario);
+    }
+}
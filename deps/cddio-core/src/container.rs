@@ -1,39 +1,115 @@
-use std::sync::Arc;
+use std::{collections::{HashMap, HashSet}, sync::Arc, time::Duration};
 use futures_locks::RwLock;
-use crate::{Components, event::ComponentEventDispatcher, Component};
+use serenity::model::{event::Event, id::GuildId};
+use crate::{event, lifecycle::Health, Components, Component};
 
 /// # The component container
-/// 
+///
 /// The component container stores components to dispatch them into the client or other components.
+/// Components can be disabled per guild: see [`Self::set_enabled`].
 #[derive(Clone)]
-pub struct ComponentContainer(Components);
+pub struct ComponentContainer {
+    components: Components,
+    /// Names of the components disabled on each guild
+    disabled: HashMap<GuildId, HashSet<String>>,
+}
 pub type RefContainer = RwLock<ComponentContainer>;
 
 impl ComponentContainer {
     pub fn new() -> ComponentContainer {
-        ComponentContainer(Vec::new())
-    }
-    /// Create a [`ComponentEventDispatcher`] from the components in the container.
-    /// Note that if new components are added to the container afterward, the dispatcher will not included these.
-    pub fn get_event_dispatcher(&self) -> ComponentEventDispatcher {
-        ComponentEventDispatcher::new(self.0.clone())
+        ComponentContainer {
+            components: Vec::new(),
+            disabled: HashMap::new(),
+        }
     }
-    /// Add a component to the container. 
+    /// Add a component to the container.
     /// The component is embedded to an Arc pointer to be async compatible.
     pub fn add_component<T: 'static + Component>(&mut self, comp: T) -> Arc<T> {
         let arc = Arc::new(comp);
         let v = Arc::clone(&arc);
-        self.0.push (v);
+        self.components.push (v);
         arc
     }
+    /// Add a component already embedded in an Arc pointer.
+    /// Useful when the component keeps a weak reference to itself (see [`Arc::new_cyclic`]).
+    pub fn add_shared_component<T: 'static + Component>(&mut self, comp: Arc<T>) -> Arc<T> {
+        self.components.push(Arc::clone(&comp) as Arc<dyn Component>);
+        comp
+    }
+    /// Find a component by its name (see [`Component::name`]).
+    pub fn find(&self, name: &str) -> Option<&Arc<dyn Component>> {
+        self.components.iter().find(|comp| comp.name() == name)
+    }
+    /// Check if the component `name` is enabled on the guild.
+    pub fn is_enabled(&self, guild_id: GuildId, name: &str) -> bool {
+        self.disabled.get(&guild_id).is_none_or(|disabled| !disabled.contains(name))
+    }
+    /// Enable or disable the component `name` on the guild.
+    ///
+    /// Returns `false` if the component was already in this state.
+    pub fn set_enabled(&mut self, guild_id: GuildId, name: &str, enabled: bool) -> bool {
+        let disabled = self.disabled.entry(guild_id).or_default();
+        let changed = match enabled {
+            true => disabled.remove(name),
+            false => disabled.insert(name.to_string()),
+        };
+        if disabled.is_empty() {
+            self.disabled.remove(&guild_id);
+        }
+        changed
+    }
+    /// Components enabled on the guild.
+    pub fn enabled_components(&self, guild_id: GuildId) -> impl Iterator<Item = &Arc<dyn Component>> {
+        self.components.iter().filter(move |comp| self.is_enabled(guild_id, comp.name()))
+    }
+    /// Components receiving the event: the components enabled on the guild of the event,
+    /// or every component if the event is not related to a guild.
+    pub fn event_components(&self, event: &Event) -> Components {
+        match event::guild_id(event) {
+            Some(guild_id) => self.enabled_components(guild_id).cloned().collect(),
+            None => self.components.clone(),
+        }
+    }
+    /// Call [`ComponentLifecycle::init`] on every component, in the order they were added.
+    ///
+    /// Stops at the first error.
+    ///
+    /// [`ComponentLifecycle::init`]: crate::ComponentLifecycle::init
+    pub async fn init_components(&self) -> Result<(), String> {
+        for comp in &self.components {
+            comp.init().await.map_err(|e| format!("{}: {}", comp.name(), e))?;
+        }
+        Ok(())
+    }
+    /// Call [`ComponentLifecycle::on_shutdown`] on every component, in the reverse order they were added
+    /// so that components stop before the components they depend on.
+    ///
+    /// A component taking more than `timeout` to stop is skipped.
+    ///
+    /// [`ComponentLifecycle::on_shutdown`]: crate::ComponentLifecycle::on_shutdown
+    pub async fn shutdown_components(&self, timeout: Duration) {
+        for comp in self.components.iter().rev() {
+            if tokio::time::timeout(timeout, comp.on_shutdown()).await.is_err() {
+                eprintln!("Component {} did not stop within {:?}", comp.name(), timeout);
+            }
+        }
+    }
+    /// Health of every component, in the order they were added.
+    pub async fn health(&self) -> Vec<(&'static str, Health)> {
+        let mut health = Vec::with_capacity(self.components.len());
+        for comp in &self.components {
+            health.push((comp.name(), comp.health().await));
+        }
+        health
+    }
 }
 impl AsRef<Components> for ComponentContainer {
     fn as_ref(&self) -> &Components {
-        &self.0
+        &self.components
     }
 }
 impl AsMut<Components> for ComponentContainer {
     fn as_mut(&mut self) -> &mut Components {
-        &mut self.0
+        &mut self.components
     }
-}
\ No newline at end of file
+}
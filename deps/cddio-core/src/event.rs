@@ -1,9 +1,14 @@
-use serenity::{model::event::Event, client::Context, async_trait};
+use std::sync::Arc;
+use serenity::{
+    model::{event::Event, id::GuildId, application::interaction::Interaction},
+    client::Context,
+    async_trait
+};
 pub use serenity::prelude::RawEventHandler;
-use crate::Components;
+use crate::container::RefContainer;
 
-/// # The component event trait. 
-/// 
+/// # The component event trait.
+///
 /// Every component must implement this trait to receive gateway event from ComponentEventDispatcher.
 #[async_trait]
 pub trait ComponentEvent: Sync + Send{
@@ -11,29 +16,83 @@ pub trait ComponentEvent: Sync + Send{
 }
 
 /// # The component event dispatcher.
-/// 
+///
 /// This dispatcher is responsible for dispatching events to the components.
 /// Add it to the client to receive events.
-/// 
+///
+/// The dispatcher reads the container on every event: components added afterward receive
+/// the events too, and components disabled on a guild do not receive the events of this guild.
+///
 /// See [`serenity::client::ClientBuilder::raw_event_handler()`] for more information.
 pub struct ComponentEventDispatcher {
-    components: Components
+    container: RefContainer,
+    pending: PendingEvents,
 }
 
 impl ComponentEventDispatcher {
-    pub(crate) fn new(components: Components) -> Self { 
-        Self { components } 
+    pub fn new(container: RefContainer) -> Self {
+        Self { container, pending: PendingEvents::default() }
+    }
+    /// Handle to wait for the events being handled by the components.
+    pub fn pending_events(&self) -> PendingEvents {
+        self.pending.clone()
+    }
+}
+
+/// # Events being handled
+///
+/// Each event dispatched holds a read lock until every component handled it.
+#[derive(Clone, Default)]
+pub struct PendingEvents(Arc<tokio::sync::RwLock<()>>);
+
+impl PendingEvents {
+    /// Wait until every event being handled is finished.
+    pub async fn wait(&self) {
+        let _ = self.0.write().await;
     }
 }
 
 #[async_trait]
 impl RawEventHandler for ComponentEventDispatcher {
     async fn raw_event(&self, ctx: Context, ev: Event) {
-        let components = self.components.clone();
+        let components = self.container.read().await.event_components(&ev);
+        let pending = Arc::clone(&self.pending.0).read_owned().await;
         tokio::spawn(async move {
             for component in components.into_iter() {
                 component.event(&ctx, &ev).await;
             }
+            drop(pending);
         });
     }
-}
\ No newline at end of file
+}
+
+/// Guild the event is related to, if any.
+///
+/// Only the events handled by guild specific features are supported,
+/// other events are considered global and return `None`.
+pub fn guild_id(event: &Event) -> Option<GuildId> {
+    match event {
+        Event::InteractionCreate(ev) => match &ev.interaction {
+            Interaction::ApplicationCommand(interaction) => interaction.guild_id,
+            Interaction::MessageComponent(interaction) => interaction.guild_id,
+            Interaction::Autocomplete(interaction) => interaction.guild_id,
+            Interaction::ModalSubmit(interaction) => interaction.guild_id,
+            _ => None,
+        },
+        Event::MessageCreate(ev) => ev.message.guild_id,
+        Event::MessageUpdate(ev) => ev.guild_id,
+        Event::MessageDelete(ev) => ev.guild_id,
+        Event::MessageDeleteBulk(ev) => ev.guild_id,
+        Event::ReactionAdd(ev) => ev.reaction.guild_id,
+        Event::ReactionRemove(ev) => ev.reaction.guild_id,
+        Event::ReactionRemoveAll(ev) => ev.guild_id,
+        Event::GuildMemberAdd(ev) => Some(ev.member.guild_id),
+        Event::GuildMemberRemove(ev) => Some(ev.guild_id),
+        Event::GuildMemberUpdate(ev) => Some(ev.guild_id),
+        Event::GuildBanAdd(ev) => Some(ev.guild_id),
+        Event::GuildBanRemove(ev) => Some(ev.guild_id),
+        Event::TypingStart(ev) => ev.guild_id,
+        Event::VoiceStateUpdate(ev) => ev.voice_state.guild_id,
+        _ => None,
+    }
+}
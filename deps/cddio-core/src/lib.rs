@@ -8,10 +8,11 @@
 //! and can be used to interact with the client or other components.
 //! 
 //! Each component must implement the [`Component`] trait to handle event and application command.
-//! The [`Component`] trait is composed of two traits: 
+//! The [`Component`] trait is composed of three traits: 
 //! - [`ComponentEvent`] which manage Discord gateway events.
 //! - [`ComponentDeclarative`] which manage applications 
 //!     commands declatation (groups, command names, arguments, description...)
+//! - [`ComponentLifecycle`] which manage the start and the stop of the bot.
 //! 
 //! ## Simplify serenity
 //! 
@@ -30,15 +31,27 @@
 
 pub mod declarative;
 pub mod event;
+pub mod lifecycle;
 pub mod container;
 pub mod embed;
 pub mod message;
+pub mod modal;
+#[cfg(feature = "testing")]
+pub mod testing;
 use std::sync::Arc;
 
 pub use declarative::ComponentDeclarative;
 pub use event::ComponentEvent;
+pub use lifecycle::ComponentLifecycle;
 pub use container::ComponentContainer;
 pub use embed::ApplicationCommandEmbed;
 
-pub trait Component: ComponentDeclarative + ComponentEvent {}
+pub trait Component: ComponentDeclarative + ComponentEvent + ComponentLifecycle {
+    /// Name of the component, used to enable or disable it on a guild.
+    ///
+    /// The [`cddio-macros`] crate names a component after its structure, in lowercase.
+    ///
+    /// [`cddio-macros`]: ../cddio_macros/index.html
+    fn name(&self) -> &'static str;
+}
 pub type Components = Vec<Arc<dyn Component>>;
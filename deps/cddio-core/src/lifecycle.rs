@@ -0,0 +1,58 @@
+use std::fmt;
+use serenity::async_trait;
+
+/// Health of a component, reported by [`ComponentLifecycle::health`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Health {
+    /// The component works normally.
+    Healthy,
+    /// The component works, with limited features.
+    Degraded(String),
+    /// The component cannot work.
+    Unhealthy(String),
+}
+
+impl fmt::Display for Health {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Health::Healthy => write!(f, "OK"),
+            Health::Degraded(reason) => write!(f, "Dégradé : {}", reason),
+            Health::Unhealthy(reason) => write!(f, "Hors service : {}", reason),
+        }
+    }
+}
+
+/// # The component lifecycle trait.
+///
+/// Hooks called by the bot when it starts and stops. Every hook has a default implementation
+/// doing nothing, so a component only implements the hooks it needs:
+///
+/// ```
+/// use cddio_core::ComponentLifecycle;
+///
+/// struct MyComponent;
+///
+/// impl ComponentLifecycle for MyComponent {}
+/// ```
+///
+/// See [`ComponentContainer::init_components`] and [`ComponentContainer::shutdown_components`].
+///
+/// [`ComponentContainer::init_components`]: crate::ComponentContainer::init_components
+/// [`ComponentContainer::shutdown_components`]: crate::ComponentContainer::shutdown_components
+#[async_trait]
+pub trait ComponentLifecycle: Sync + Send {
+    /// Called once before the client connects to Discord.
+    ///
+    /// An error prevents the bot from starting.
+    async fn init(&self) -> Result<(), String> {
+        Ok(())
+    }
+    /// Called once when the bot stops, after the events being handled are finished.
+    ///
+    /// The component must save its data and finish its pending work.
+    async fn on_shutdown(&self) {}
+    /// Current health of the component.
+    async fn health(&self) -> Health {
+        Health::Healthy
+    }
+}
@@ -0,0 +1,98 @@
+//! Fenêtres modales (modals) Discord
+//!
+//! Une fenêtre modale est affichée en réponse à une interaction (commande, bouton, menu...).
+//! Sa validation déclenche une interaction de type *modal submit*, récupérable avec
+//! l'attribut `#[modal_submit]` de `cddio-macros`.
+
+use serenity::builder::CreateInteractionResponse;
+use serenity::model::application::component::{ActionRowComponent, InputTextStyle};
+use serenity::model::application::interaction::modal::ModalSubmitInteractionData;
+
+/// Nombre maximum de champs dans une fenêtre modale.
+pub const MAX_FIELDS: usize = 5;
+/// Taille maximale du libellé d'un champ.
+pub const MAX_LABEL_LEN: usize = 45;
+
+/// Champ de saisie d'une fenêtre modale
+#[derive(Debug, Clone)]
+pub struct ModalField {
+    /// Identifiant du champ, retrouvé dans la réponse
+    pub custom_id: String,
+    /// Libellé du champ
+    pub label: String,
+    /// Saisie sur plusieurs lignes
+    pub long: bool,
+    /// Champ obligatoire
+    pub required: bool,
+}
+
+/// Interface de création de fenêtre modale
+#[derive(Debug, Clone)]
+pub struct Modal {
+    pub custom_id: String,
+    pub title: String,
+    pub fields: Vec<ModalField>,
+}
+
+impl Modal {
+    pub fn new<S1: ToString, S2: ToString>(custom_id: S1, title: S2) -> Self {
+        Modal {
+            custom_id: custom_id.to_string(),
+            title: title.to_string(),
+            fields: Vec::new(),
+        }
+    }
+    /// Ajoute un champ de saisie à la fenêtre.
+    ///
+    /// Les champs au-delà de [`MAX_FIELDS`] sont ignorés par Discord.
+    pub fn add_field<S1: ToString, S2: ToString>(&mut self, custom_id: S1, label: S2, long: bool, required: bool) -> &mut Self {
+        self.fields.push(ModalField {
+            custom_id: custom_id.to_string(),
+            label: label.to_string(),
+            long,
+            required,
+        });
+        self
+    }
+}
+
+impl From<Modal> for CreateInteractionResponse<'_> {
+    fn from(modal: Modal) -> Self {
+        use serenity::model::application::interaction::InteractionResponseType;
+        let mut response = CreateInteractionResponse::default();
+        response.kind(InteractionResponseType::Modal);
+        response.interaction_response_data(|data| {
+            data
+                .custom_id(modal.custom_id)
+                .title(modal.title)
+                .components(|cmps| {
+                    for field in modal.fields.into_iter().take(MAX_FIELDS) {
+                        cmps.create_action_row(|row| {
+                            row.create_input_text(|input| {
+                                input
+                                    .custom_id(field.custom_id)
+                                    .label(field.label.chars().take(MAX_LABEL_LEN).collect::<String>())
+                                    .style(if field.long { InputTextStyle::Paragraph } else { InputTextStyle::Short })
+                                    .required(field.required)
+                            })
+                        });
+                    }
+                    cmps
+                })
+        });
+        response
+    }
+}
+
+/// Retourne les valeurs saisies dans une fenêtre modale,
+/// sous la forme `(custom_id, valeur)` dans l'ordre des champs.
+pub fn values(data: &ModalSubmitInteractionData) -> Vec<(String, String)> {
+    data.components
+        .iter()
+        .flat_map(|row| row.components.iter())
+        .filter_map(|component| match component {
+            ActionRowComponent::InputText(input) => Some((input.custom_id.clone(), input.value.clone())),
+            _ => None
+        })
+        .collect()
+}
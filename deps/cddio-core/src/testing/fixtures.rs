@@ -0,0 +1,321 @@
+//! Synthetic Discord payloads.
+//!
+//! Most serenity models are `#[non_exhaustive]`: the fixtures are built as gateway JSON payloads
+//! and deserialized like the events received by the bot.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use serde_json::{json, Map, Value};
+use serenity::model::{
+    application::interaction::application_command::ApplicationCommandInteraction,
+    event::{deserialize_event_with_type, Event, EventType},
+    permissions::Permissions,
+    Timestamp,
+};
+
+/// Id of the application of the mocked bot.
+pub const APPLICATION_ID: u64 = 100_000_000_000_000_001;
+/// Id of the mocked bot user.
+pub const BOT_ID: u64 = 100_000_000_000_000_002;
+/// Default guild of the fixtures.
+pub const GUILD_ID: u64 = 200_000_000_000_000_001;
+/// Default channel of the fixtures.
+pub const CHANNEL_ID: u64 = 200_000_000_000_000_002;
+/// Default author of the fixtures.
+pub const USER_ID: u64 = 300_000_000_000_000_001;
+
+/// Generate a new snowflake, unique for the whole test binary.
+pub fn next_id() -> u64 {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(900_000_000_000_000_000);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Build an event from its gateway payload.
+///
+/// # Panics
+///
+/// Panics if the payload does not match the event type.
+pub fn event(kind: EventType, payload: Value) -> Event {
+    let name = format!("{:?}", kind);
+    deserialize_event_with_type(kind, payload)
+        .unwrap_or_else(|e| panic!("Invalid {} payload: {}", name, e))
+}
+
+pub fn user(id: u64) -> Value {
+    json!({
+        "id": id.to_string(),
+        "username": format!("user{}", id),
+        "discriminator": "0001",
+        "avatar": null,
+        "bot": id == BOT_ID,
+    })
+}
+
+/// Guild member who joined the guild now.
+pub fn member(id: u64, roles: &[u64]) -> Value {
+    json!({
+        "user": user(id),
+        "roles": roles.iter().map(u64::to_string).collect::<Vec<_>>(),
+        "joined_at": Timestamp::now().to_string(),
+        "deaf": false,
+        "mute": false,
+    })
+}
+
+pub fn role(id: u64, name: &str) -> Value {
+    json!({
+        "id": id.to_string(),
+        "name": name,
+        "color": 0,
+        "hoist": false,
+        "managed": false,
+        "mentionable": true,
+        "permissions": "0",
+        "position": 1,
+    })
+}
+
+/// Guild with its roles, as sent in a `GUILD_CREATE` event. The `@everyone` role is added to `roles`.
+pub fn guild(id: u64, roles: &[Value]) -> Value {
+    let mut everyone = role(id, "@everyone");
+    everyone["position"] = json!(0);
+    json!({
+        "id": id.to_string(),
+        "name": format!("guild{}", id),
+        "icon": null,
+        "splash": null,
+        "owner_id": USER_ID.to_string(),
+        "afk_timeout": 300,
+        "verification_level": 0,
+        "default_message_notifications": 0,
+        "explicit_content_filter": 0,
+        "system_channel_flags": 0,
+        "roles": std::iter::once(everyone).chain(roles.iter().cloned()).collect::<Vec<_>>(),
+        "emojis": [],
+        "features": [],
+        "mfa_level": 0,
+        "joined_at": Timestamp::now().to_string(),
+        "large": false,
+        "member_count": 0,
+        "members": [],
+        "channels": [],
+        "presences": [],
+        "voice_states": [],
+        "premium_tier": 0,
+        "nsfw_level": 0,
+        "stickers": [],
+        "threads": [],
+        "stage_instances": [],
+        "guild_scheduled_events": [],
+        "preferred_locale": "fr",
+    })
+}
+
+/// Text channel of a guild, without permission overwrites.
+pub fn channel(id: u64, guild_id: u64, name: &str) -> Value {
+    json!({
+        "id": id.to_string(),
+        "guild_id": guild_id.to_string(),
+        "name": name,
+        "type": 0,
+        "position": 0,
+        "permission_overwrites": [],
+        "nsfw": false,
+        "topic": null,
+        "parent_id": null,
+        "last_message_id": null,
+    })
+}
+
+/// Message sent now.
+pub fn message(id: u64, channel_id: u64, author_id: u64, content: &str) -> Value {
+    json!({
+        "id": id.to_string(),
+        "channel_id": channel_id.to_string(),
+        "author": user(author_id),
+        "content": content,
+        "timestamp": Timestamp::now().to_string(),
+        "edited_timestamp": null,
+        "tts": false,
+        "mention_everyone": false,
+        "mentions": [],
+        "mention_roles": [],
+        "attachments": [],
+        "embeds": [],
+        "pinned": false,
+        "type": 0,
+    })
+}
+
+/// Message sent by `author_id` in a guild channel.
+pub fn message_create(guild_id: u64, channel_id: u64, author_id: u64, content: &str) -> Event {
+    let mut payload = message(next_id(), channel_id, author_id, content);
+    payload["guild_id"] = json!(guild_id.to_string());
+    payload["member"] = member(author_id, &[]);
+    event(EventType::MessageCreate, payload)
+}
+
+/// Message returned by the mock when the bot sends or edits a message.
+pub(crate) fn reply_message(id: u64, channel_id: u64, request: &Value) -> Value {
+    let mut payload = message(id, channel_id, BOT_ID, request["content"].as_str().unwrap_or_default());
+    if let Some(embeds) = request["embeds"].as_array() {
+        payload["embeds"] = Value::Array(embeds.clone());
+    }
+    payload
+}
+
+/// # Synthetic application command
+///
+/// Build an [`ApplicationCommandInteraction`] as received from the gateway.
+/// Options are added to the last subcommand declared.
+///
+/// ```
+/// use cddio_core::testing::fixtures::CommandBuilder;
+///
+/// let interaction = CommandBuilder::new("autobahn")
+///     .subcommand("seuils")
+///     .integer("messages", 5)
+///     .build();
+/// assert_eq!(interaction.data.name, "autobahn");
+/// ```
+#[derive(Debug, Clone)]
+pub struct CommandBuilder {
+    name: String,
+    /// Subcommand groups and subcommand, with their option type.
+    path: Vec<(String, u8)>,
+    options: Vec<Value>,
+    resolved: Map<String, Value>,
+    guild_id: Option<u64>,
+    channel_id: u64,
+    user_id: u64,
+    roles: Vec<u64>,
+    permissions: u64,
+}
+
+impl CommandBuilder {
+    /// Command `name` sent by [`USER_ID`] in [`CHANNEL_ID`] of [`GUILD_ID`] with every permission.
+    pub fn new(name: &str) -> Self {
+        CommandBuilder {
+            name: name.to_string(),
+            path: Vec::new(),
+            options: Vec::new(),
+            resolved: Map::new(),
+            guild_id: Some(GUILD_ID),
+            channel_id: CHANNEL_ID,
+            user_id: USER_ID,
+            roles: Vec::new(),
+            permissions: Permissions::all().bits(),
+        }
+    }
+    pub fn group(mut self, name: &str) -> Self {
+        self.path.push((name.to_string(), 2));
+        self
+    }
+    pub fn subcommand(mut self, name: &str) -> Self {
+        self.path.push((name.to_string(), 1));
+        self
+    }
+    pub fn string(self, name: &str, value: &str) -> Self {
+        self.option(name, 3, json!(value))
+    }
+    pub fn integer(self, name: &str, value: i64) -> Self {
+        self.option(name, 4, json!(value))
+    }
+    pub fn boolean(self, name: &str, value: bool) -> Self {
+        self.option(name, 5, json!(value))
+    }
+    pub fn number(self, name: &str, value: f64) -> Self {
+        self.option(name, 10, json!(value))
+    }
+    pub fn user(mut self, name: &str, id: u64) -> Self {
+        self.resolve("users", id, user(id));
+        self.option(name, 6, json!(id.to_string()))
+    }
+    pub fn channel(mut self, name: &str, id: u64) -> Self {
+        self.resolve("channels", id, json!({
+            "id": id.to_string(),
+            "name": format!("channel{}", id),
+            "type": 0,
+            "permissions": "0",
+        }));
+        self.option(name, 7, json!(id.to_string()))
+    }
+    pub fn role(mut self, name: &str, id: u64) -> Self {
+        self.resolve("roles", id, role(id, &format!("role{}", id)));
+        self.option(name, 8, json!(id.to_string()))
+    }
+    /// Guild of the command. `None` for a command sent in direct messages.
+    pub fn guild(mut self, guild_id: Option<u64>) -> Self {
+        self.guild_id = guild_id;
+        self
+    }
+    pub fn channel_id(mut self, channel_id: u64) -> Self {
+        self.channel_id = channel_id;
+        self
+    }
+    /// Author of the command and their roles.
+    pub fn author(mut self, user_id: u64, roles: &[u64]) -> Self {
+        self.user_id = user_id;
+        self.roles = roles.to_vec();
+        self
+    }
+    /// Permissions of the author in the channel.
+    pub fn permissions(mut self, permissions: u64) -> Self {
+        self.permissions = permissions;
+        self
+    }
+    fn option(mut self, name: &str, kind: u8, value: Value) -> Self {
+        self.options.push(json!({ "name": name, "type": kind, "value": value }));
+        self
+    }
+    fn resolve(&mut self, kind: &str, id: u64, value: Value) {
+        if let Some(resolved) = self.resolved
+            .entry(kind)
+            .or_insert_with(|| Value::Object(Map::new()))
+            .as_object_mut()
+        {
+            resolved.insert(id.to_string(), value);
+        }
+    }
+    /// Gateway payload of the interaction.
+    pub fn payload(&self) -> Value {
+        let options = self.path.iter().rev().fold(self.options.clone(), |options, (name, kind)| {
+            vec![json!({ "name": name, "type": kind, "options": options })]
+        });
+        let mut payload = json!({
+            "id": next_id().to_string(),
+            "application_id": APPLICATION_ID.to_string(),
+            "type": 2,
+            "data": {
+                "id": next_id().to_string(),
+                "name": self.name,
+                "type": 1,
+                "options": options,
+                "resolved": self.resolved,
+            },
+            "channel_id": self.channel_id.to_string(),
+            "user": user(self.user_id),
+            "token": format!("interaction-token-{}", next_id()),
+            "version": 1,
+            "locale": "fr",
+        });
+        if let Some(guild_id) = self.guild_id {
+            let mut member = member(self.user_id, &self.roles);
+            member["permissions"] = json!(self.permissions.to_string());
+            payload["guild_id"] = json!(guild_id.to_string());
+            payload["guild_locale"] = json!("fr");
+            payload["member"] = member;
+        }
+        payload
+    }
+    /// # Panics
+    ///
+    /// Panics if serenity cannot deserialize the payload.
+    pub fn build(&self) -> ApplicationCommandInteraction {
+        serde_json::from_value(self.payload())
+            .unwrap_or_else(|e| panic!("Invalid command /{}: {}", self.name, e))
+    }
+    /// Interaction creation event of the command.
+    pub fn event(&self) -> Event {
+        event(EventType::InteractionCreate, self.payload())
+    }
+}
@@ -0,0 +1,260 @@
+//! Local mock of the Discord HTTP API.
+
+use std::{
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex, atomic::{AtomicU64, Ordering}},
+};
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+use super::fixtures;
+
+/// Prefix of the Discord API routes, removed from the recorded paths.
+const API_PREFIX: &str = "/api/v10";
+
+/// Request received by the mock.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    /// HTTP method, in uppercase (`GET`, `POST`...).
+    pub method: String,
+    /// Route of the request, without the API prefix nor the query string.
+    /// For example `/channels/42/messages`.
+    pub path: String,
+    /// Query string of the request, if any.
+    pub query: Option<String>,
+    /// Body of the request.
+    ///
+    /// JSON bodies are parsed, other bodies are kept as a string and empty bodies are `null`.
+    pub body: Value,
+}
+
+impl Request {
+    /// Check if the request matches the method and the route pattern.
+    ///
+    /// See [`MockDiscord::respond`] for the pattern syntax.
+    pub fn is(&self, method: &str, pattern: &str) -> bool {
+        self.method.eq_ignore_ascii_case(method) && matches(pattern, &self.path)
+    }
+    /// Id in the `index`-th segment of the path, if it is a number.
+    ///
+    /// For `/channels/42/messages`, `segment_id(1)` is `Some(42)`.
+    pub fn segment_id(&self, index: usize) -> Option<u64> {
+        self.path.split('/').filter(|s| !s.is_empty()).nth(index)?.parse().ok()
+    }
+}
+
+/// Response sent by the mock.
+#[derive(Debug, Clone)]
+pub enum Reply {
+    /// Response without body with the given status.
+    Empty(u16),
+    /// JSON response with the given status.
+    Json(u16, Value),
+    /// Message built from the content and embeds of the request.
+    ///
+    /// Used by default for routes creating or editing messages.
+    Message,
+}
+
+struct Route {
+    method: String,
+    pattern: String,
+    reply: Reply,
+}
+
+#[derive(Default)]
+struct State {
+    requests: Vec<Request>,
+    routes: Vec<Route>,
+}
+
+/// # Mock of the Discord HTTP API
+///
+/// The mock listens on a random local port and records every request it receives.
+/// Responses are chosen from the routes registered with [`Self::respond`].
+/// When no route matches, a default response is sent:
+/// - routes creating or editing a message reply with the message ([`Reply::Message`]),
+/// - other `GET` requests reply with a `404` error,
+/// - other requests reply with `204 No Content`.
+///
+/// The mock stops when dropped.
+pub struct MockDiscord {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    server: JoinHandle<()>,
+}
+
+impl MockDiscord {
+    /// Start the mock on a random local port.
+    pub async fn start() -> io::Result<MockDiscord> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State::default()));
+        let server_state = Arc::clone(&state);
+        let server = tokio::spawn(async move {
+            let ids = Arc::new(AtomicU64::new(1));
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = Arc::clone(&server_state);
+                let ids = Arc::clone(&ids);
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, state, ids).await {
+                        eprintln!("MockDiscord: connection error: {}", e);
+                    }
+                });
+            }
+        });
+        Ok(MockDiscord { addr, state, server })
+    }
+    /// URL of the mock, to use as the proxy of [`serenity::http::HttpBuilder`].
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+    /// Reply with `reply` to the requests matching the method and the route pattern.
+    ///
+    /// A pattern is a route where `*` matches any segment, like `/channels/*/messages`.
+    /// The last registered route is used first, so a route can be overridden.
+    pub fn respond(&self, method: &str, pattern: &str, reply: Reply) {
+        self.lock().routes.push(Route {
+            method: method.to_uppercase(),
+            pattern: pattern.to_string(),
+            reply,
+        });
+    }
+    /// Every request received since the start or the last [`Self::clear`], in order.
+    pub fn requests(&self) -> Vec<Request> {
+        self.lock().requests.clone()
+    }
+    /// Requests matching the method and the route pattern, in order.
+    pub fn find(&self, method: &str, pattern: &str) -> Vec<Request> {
+        self.lock().requests.iter()
+            .filter(|request| request.is(method, pattern))
+            .cloned()
+            .collect()
+    }
+    /// Forget the recorded requests. Registered routes are kept.
+    pub fn clear(&self) {
+        self.lock().requests.clear();
+    }
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for MockDiscord {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// Check if `path` matches `pattern`, segment by segment.
+fn matches(pattern: &str, path: &str) -> bool {
+    let mut pattern = pattern.split('/').filter(|s| !s.is_empty());
+    let mut path = path.split('/').filter(|s| !s.is_empty());
+    loop {
+        match (pattern.next(), path.next()) {
+            (None, None) => return true,
+            (Some("*"), Some(_)) => (),
+            (Some(expected), Some(segment)) if expected == segment => (),
+            _ => return false,
+        }
+    }
+}
+
+/// Default response when no registered route matches the request.
+fn default_reply(request: &Request) -> Reply {
+    const MESSAGE_ROUTES: [(&str, &str); 4] = [
+        ("POST", "/channels/*/messages"),
+        ("PATCH", "/channels/*/messages/*"),
+        ("POST", "/webhooks/*/*"),
+        ("PATCH", "/webhooks/*/*/messages/*"),
+    ];
+    if MESSAGE_ROUTES.iter().any(|(method, pattern)| request.is(method, pattern)) {
+        Reply::Message
+    } else if request.method == "GET" {
+        Reply::Json(404, json!({
+            "code": 0,
+            "message": format!("No mock response for GET {}", request.path),
+        }))
+    } else {
+        Reply::Empty(204)
+    }
+}
+
+async fn handle_connection(stream: TcpStream, state: Arc<Mutex<State>>, ids: Arc<AtomicU64>) -> io::Result<()> {
+    let mut stream = BufReader::new(stream);
+    let request = match read_request(&mut stream).await? {
+        Some(request) => request,
+        None => return Ok(()),
+    };
+    let reply = {
+        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+        let reply = state.routes.iter().rev()
+            .find(|route| request.is(&route.method, &route.pattern))
+            .map(|route| route.reply.clone())
+            .unwrap_or_else(|| default_reply(&request));
+        state.requests.push(request.clone());
+        reply
+    };
+    let (status, body) = match reply {
+        Reply::Empty(status) => (status, String::new()),
+        Reply::Json(status, value) => (status, value.to_string()),
+        Reply::Message => {
+            let channel_id = match request.path.starts_with("/channels/") {
+                true => request.segment_id(1).unwrap_or_default(),
+                false => 0,
+            };
+            (200, fixtures::reply_message(ids.fetch_add(1, Ordering::Relaxed), channel_id, &request.body).to_string())
+        }
+    };
+    let reason = match status {
+        200 => "OK",
+        204 => "No Content",
+        404 => "Not Found",
+        _ => "Mock",
+    };
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, reason, body.len(), body
+    );
+    stream.get_mut().write_all(response.as_bytes()).await?;
+    stream.get_mut().shutdown().await
+}
+
+async fn read_request(stream: &mut BufReader<TcpStream>) -> io::Result<Option<Request>> {
+    let mut line = String::new();
+    if stream.read_line(&mut line).await? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_uppercase();
+    let target = parts.next().unwrap_or_default().to_string();
+    let mut content_length = 0;
+    loop {
+        line.clear();
+        if stream.read_line(&mut line).await? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    stream.read_exact(&mut body).await?;
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), Some(query.to_string())),
+        None => (target, None),
+    };
+    let path = path.strip_prefix(API_PREFIX).map(str::to_string).unwrap_or(path);
+    let body = match body.is_empty() {
+        true => Value::Null,
+        false => serde_json::from_slice(&body)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&body).into_owned())),
+    };
+    Ok(Some(Request { method, path, query, body }))
+}
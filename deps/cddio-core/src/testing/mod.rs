@@ -0,0 +1,113 @@
+//! # Offline test harness
+//!
+//! Components handle events with a live serenity [`Context`]. The [`Harness`] builds a context
+//! whose HTTP client targets a local [`MockDiscord`] instead of the Discord API, dispatches
+//! synthetic events to a component and records every request the component sends.
+//! Tests run with `cargo test`, without network access nor Discord token.
+//!
+//! The module is only compiled with the `testing` feature: enable it from `[dev-dependencies]`.
+//!
+//! ```
+//! # async fn example(component: impl cddio_core::ComponentEvent) {
+//! use cddio_core::testing::{Harness, fixtures::CommandBuilder};
+//!
+//! let harness = Harness::new().await.unwrap();
+//! harness.command(&component, CommandBuilder::new("ping")).await;
+//! let responses = harness.mock.find("POST", "/interactions/*/*/callback");
+//! assert_eq!(responses.len(), 1);
+//! # }
+//! ```
+//!
+//! [`Context`]: serenity::client::Context
+
+pub mod fixtures;
+pub mod mock;
+
+use std::{io, sync::Arc};
+use futures::channel::mpsc::{self, UnboundedReceiver};
+use serde_json::Value;
+use serenity::{
+    cache::Cache,
+    client::{bridge::gateway::ShardMessenger, Context},
+    gateway::InterMessage,
+    http::HttpBuilder,
+    model::event::{Event, EventType},
+    prelude::{RwLock, TypeMap},
+};
+use crate::{container::RefContainer, ComponentEvent};
+use fixtures::CommandBuilder;
+pub use mock::{MockDiscord, Reply, Request};
+
+/// # Test harness of a component
+///
+/// Owns the mock of the Discord API and the context given to the components.
+/// Events are dispatched synchronously: once [`Self::dispatch`] returns, every request sent
+/// by the component while handling the event is recorded in [`Self::mock`].
+pub struct Harness {
+    pub mock: MockDiscord,
+    ctx: Context,
+    /// Messages sent to the shard (presence updates...), kept so that sending them does not fail.
+    shard: UnboundedReceiver<InterMessage>,
+}
+
+impl Harness {
+    /// Start the mock and build the context of the components.
+    pub async fn new() -> io::Result<Harness> {
+        let mock = MockDiscord::start().await?;
+        let http = HttpBuilder::new("mock-token")
+            .application_id(fixtures::APPLICATION_ID)
+            .ratelimiter_disabled(true)
+            .proxy(mock.url())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?
+            .build();
+        let (tx, shard) = mpsc::unbounded();
+        let ctx = Context {
+            data: Arc::new(RwLock::new(TypeMap::new())),
+            shard: ShardMessenger::new(tx),
+            shard_id: 0,
+            http: Arc::new(http),
+            cache: Arc::new(Cache::new()),
+        };
+        Ok(Harness { mock, ctx, shard })
+    }
+    /// Context given to the components.
+    ///
+    /// Data can be inserted in `ctx.data` and the cache filled before dispatching events.
+    pub fn context(&self) -> &Context {
+        &self.ctx
+    }
+    /// Add a guild to the cache, as if the bot received it from the gateway.
+    ///
+    /// See [`fixtures::guild`].
+    pub fn cache_guild(&self, guild: Value) {
+        if let Event::GuildCreate(mut event) = fixtures::event(EventType::GuildCreate, guild) {
+            self.ctx.cache.update(&mut event);
+        }
+    }
+    /// Dispatch an event to the component and wait for it to be handled.
+    pub async fn dispatch<C: ComponentEvent + ?Sized>(&self, component: &C, event: &Event) {
+        component.event(&self.ctx, event).await;
+    }
+    /// Dispatch an event to the components of the container like [`ComponentEventDispatcher`],
+    /// and wait for every component to handle it.
+    ///
+    /// [`ComponentEventDispatcher`]: crate::event::ComponentEventDispatcher
+    pub async fn dispatch_container(&self, container: &RefContainer, event: &Event) {
+        let components = container.read().await.event_components(event);
+        for component in components {
+            component.event(&self.ctx, event).await;
+        }
+    }
+    /// Send an application command to the component and wait for it to be handled.
+    pub async fn command<C: ComponentEvent + ?Sized>(&self, component: &C, command: CommandBuilder) {
+        self.dispatch(component, &command.event()).await;
+    }
+    /// Number of messages sent to the shard since the last call.
+    pub fn shard_messages(&mut self) -> usize {
+        let mut count = 0;
+        while let Ok(Some(_)) = self.shard.try_next() {
+            count += 1;
+        }
+        count
+    }
+}
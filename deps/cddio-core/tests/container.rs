@@ -0,0 +1,102 @@
+use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
+use cddio_core::{
+    container::RefContainer,
+    event,
+    testing::{fixtures::{self, CommandBuilder}, Harness},
+    Component, ComponentContainer, ComponentDeclarative, ComponentEvent, ComponentLifecycle,
+};
+use futures_locks::RwLock;
+use serenity::{async_trait, client::Context, model::{event::Event, id::GuildId}};
+
+/// Component counting the events it receives.
+struct Counter {
+    name: &'static str,
+    events: AtomicUsize,
+}
+
+impl Counter {
+    fn new(name: &'static str) -> Self {
+        Counter { name, events: AtomicUsize::new(0) }
+    }
+    fn events(&self) -> usize {
+        self.events.load(Ordering::Relaxed)
+    }
+}
+
+#[async_trait]
+impl ComponentEvent for Counter {
+    async fn event(&self, _: &Context, _: &Event) {
+        self.events.fetch_add(1, Ordering::Relaxed);
+    }
+}
+impl ComponentDeclarative for Counter {}
+impl ComponentLifecycle for Counter {}
+impl Component for Counter {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+async fn container() -> (RefContainer, Arc<Counter>, Arc<Counter>) {
+    let container = RwLock::new(ComponentContainer::new());
+    let (first, second) = {
+        let mut container = container.write().await;
+        (container.add_component(Counter::new("first")), container.add_component(Counter::new("second")))
+    };
+    (container, first, second)
+}
+
+#[tokio::test]
+async fn components_are_enabled_per_guild() {
+    let (container, _, _) = container().await;
+    let guild = GuildId(fixtures::GUILD_ID);
+    let other = GuildId(fixtures::GUILD_ID + 1);
+    let mut container = container.write().await;
+
+    assert!(container.set_enabled(guild, "first", false));
+    assert!(!container.set_enabled(guild, "first", false));
+    assert!(!container.is_enabled(guild, "first"));
+    assert!(container.is_enabled(other, "first"));
+    assert_eq!(container.enabled_components(guild).map(|comp| comp.name()).collect::<Vec<_>>(), ["second"]);
+
+    assert!(container.set_enabled(guild, "first", true));
+    assert!(container.is_enabled(guild, "first"));
+    assert!(container.find("second").is_some());
+    assert!(container.find("third").is_none());
+}
+
+#[tokio::test]
+async fn disabled_components_do_not_receive_guild_events() {
+    let harness = Harness::new().await.unwrap();
+    let (container, first, second) = container().await;
+    container.write().await.set_enabled(GuildId(fixtures::GUILD_ID), "first", false);
+
+    harness.dispatch_container(&container, &CommandBuilder::new("ping").event()).await;
+    assert_eq!((first.events(), second.events()), (0, 1));
+
+    let other_guild = CommandBuilder::new("ping").guild(Some(fixtures::GUILD_ID + 1)).event();
+    harness.dispatch_container(&container, &other_guild).await;
+    assert_eq!((first.events(), second.events()), (1, 2));
+
+    let direct_message = CommandBuilder::new("ping").guild(None).event();
+    harness.dispatch_container(&container, &direct_message).await;
+    assert_eq!((first.events(), second.events()), (2, 3));
+}
+
+#[tokio::test]
+async fn components_added_later_receive_events() {
+    let harness = Harness::new().await.unwrap();
+    let (container, first, _) = container().await;
+    let third = container.write().await.add_component(Counter::new("third"));
+
+    harness.dispatch_container(&container, &CommandBuilder::new("ping").event()).await;
+    assert_eq!((first.events(), third.events()), (1, 1));
+}
+
+#[test]
+fn events_are_related_to_their_guild() {
+    let message = fixtures::message_create(fixtures::GUILD_ID, fixtures::CHANNEL_ID, fixtures::USER_ID, "salut");
+    assert_eq!(event::guild_id(&message), Some(GuildId(fixtures::GUILD_ID)));
+    let direct_message = CommandBuilder::new("ping").guild(None).event();
+    assert_eq!(event::guild_id(&direct_message), None);
+}
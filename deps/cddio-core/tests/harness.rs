@@ -0,0 +1,161 @@
+use cddio_core::{
+    message,
+    testing::{fixtures::{self, CommandBuilder}, Harness, Reply},
+    ApplicationCommandEmbed, ComponentEvent,
+};
+use serde_json::json;
+use serenity::{
+    async_trait,
+    client::Context,
+    model::{
+        application::interaction::{application_command::CommandDataOptionValue, Interaction},
+        event::Event,
+        id::{ChannelId, RoleId},
+    },
+};
+
+/// Component answering to `/ping`, `/lent` and to the `!salon` message.
+struct Echo;
+
+#[async_trait]
+impl ComponentEvent for Echo {
+    async fn event(&self, ctx: &Context, event: &Event) {
+        match event {
+            Event::InteractionCreate(event) => {
+                let interaction = match &event.interaction {
+                    Interaction::ApplicationCommand(interaction) => interaction,
+                    _ => return,
+                };
+                let app_cmd = ApplicationCommandEmbed::new(interaction);
+                match app_cmd.fullname().as_str() {
+                    "ping" => {
+                        app_cmd.direct_response(ctx, message::success("Pong!")).await.unwrap();
+                    },
+                    "lent" => {
+                        let response = app_cmd.delayed_response(ctx, true).await.unwrap();
+                        response.send_message(message::Message::with_text("Terminé".to_string())).await.unwrap();
+                    },
+                    _ => (),
+                }
+            },
+            Event::MessageCreate(event) if event.message.content == "!salon" => {
+                let content = match event.message.channel_id.to_channel(ctx).await {
+                    Ok(channel) => format!("Salon {}", channel.id()),
+                    Err(_) => "Salon inconnu".to_string(),
+                };
+                event.message.channel_id.say(ctx, content).await.unwrap();
+            },
+            _ => (),
+        }
+    }
+}
+
+#[tokio::test]
+async fn direct_response_is_recorded() {
+    let harness = Harness::new().await.unwrap();
+    harness.command(&Echo, CommandBuilder::new("ping")).await;
+
+    let requests = harness.mock.requests();
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].is("POST", "/interactions/*/*/callback"));
+    assert_eq!(requests[0].body["type"], 4);
+    assert_eq!(requests[0].body["data"]["embeds"][0]["description"], "Pong!");
+}
+
+#[tokio::test]
+async fn delayed_response_edits_the_original_response() {
+    let harness = Harness::new().await.unwrap();
+    harness.command(&Echo, CommandBuilder::new("lent")).await;
+
+    let callback = harness.mock.find("POST", "/interactions/*/*/callback");
+    assert_eq!(callback.len(), 1);
+    assert_eq!(callback[0].body["type"], 5);
+    assert_eq!(callback[0].body["data"]["flags"], 64);
+    let edit = harness.mock.find("PATCH", "/webhooks/*/*/messages/@original");
+    assert_eq!(edit.len(), 1);
+    assert_eq!(edit[0].segment_id(1), Some(fixtures::APPLICATION_ID));
+    assert_eq!(edit[0].body["content"], "Terminé");
+}
+
+#[tokio::test]
+async fn unknown_routes_fail_like_discord() {
+    let harness = Harness::new().await.unwrap();
+    let event = fixtures::message_create(fixtures::GUILD_ID, fixtures::CHANNEL_ID, fixtures::USER_ID, "!salon");
+    harness.dispatch(&Echo, &event).await;
+
+    let sent = harness.mock.find("POST", "/channels/*/messages");
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].segment_id(1), Some(fixtures::CHANNEL_ID));
+    assert_eq!(sent[0].body["content"], "Salon inconnu");
+}
+
+#[tokio::test]
+async fn registered_replies_are_used() {
+    let harness = Harness::new().await.unwrap();
+    harness.mock.respond("GET", "/channels/*", Reply::Json(200, json!({
+        "id": fixtures::CHANNEL_ID.to_string(),
+        "guild_id": fixtures::GUILD_ID.to_string(),
+        "name": "general",
+        "type": 0,
+        "position": 0,
+        "permission_overwrites": [],
+    })));
+    let event = fixtures::message_create(fixtures::GUILD_ID, fixtures::CHANNEL_ID, fixtures::USER_ID, "!salon");
+    harness.dispatch(&Echo, &event).await;
+
+    let sent = harness.mock.find("POST", "/channels/*/messages");
+    assert_eq!(sent[0].body["content"], format!("Salon {}", ChannelId(fixtures::CHANNEL_ID)));
+
+    harness.mock.clear();
+    assert!(harness.mock.requests().is_empty());
+}
+
+#[test]
+fn command_builder_nests_options() {
+    let interaction = CommandBuilder::new("autobahn")
+        .group("lockdown")
+        .subcommand("arrivants")
+        .string("action", "quarantaine")
+        .role("role", 42)
+        .user("membre", 43)
+        .integer("duree", 60)
+        .build();
+    let app_cmd = ApplicationCommandEmbed::new(&interaction);
+
+    assert_eq!(app_cmd.fullname(), "autobahn.lockdown.arrivants");
+    assert_eq!(app_cmd.get_guild_id().map(|id| id.0), Some(fixtures::GUILD_ID));
+    match app_cmd.get_argument("role").and_then(|arg| arg.resolved.as_ref()) {
+        Some(CommandDataOptionValue::Role(role)) => assert_eq!(role.id.0, 42),
+        other => panic!("Unexpected role argument: {:?}", other),
+    }
+    match app_cmd.get_argument("membre").and_then(|arg| arg.resolved.as_ref()) {
+        Some(CommandDataOptionValue::User(user, _)) => assert_eq!(user.id.0, 43),
+        other => panic!("Unexpected user argument: {:?}", other),
+    }
+    match app_cmd.get_argument("duree").and_then(|arg| arg.resolved.as_ref()) {
+        Some(CommandDataOptionValue::Integer(duration)) => assert_eq!(*duration, 60),
+        other => panic!("Unexpected integer argument: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn guilds_are_cached() {
+    let harness = Harness::new().await.unwrap();
+    harness.cache_guild(fixtures::guild(fixtures::GUILD_ID, &[fixtures::role(42, "staff")]));
+
+    let roles = harness.context().cache.guild_roles(fixtures::GUILD_ID).unwrap();
+    assert_eq!(roles.len(), 2);
+    assert_eq!(roles[&RoleId(42)].name, "staff");
+    assert_eq!(roles[&RoleId(fixtures::GUILD_ID)].position, 0);
+}
+
+#[tokio::test]
+async fn channels_are_deserialized() {
+    let harness = Harness::new().await.unwrap();
+    let channel_id = fixtures::next_id();
+    harness.mock.respond("GET", &format!("/channels/{}", channel_id), Reply::Json(200, fixtures::channel(channel_id, fixtures::GUILD_ID, "ticket-1")));
+
+    let channel = ChannelId(channel_id).to_channel(harness.context()).await.unwrap().guild().unwrap();
+    assert_eq!(channel.name, "ticket-1");
+    assert_eq!(channel.guild_id.0, fixtures::GUILD_ID);
+}
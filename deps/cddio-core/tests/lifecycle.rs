@@ -0,0 +1,119 @@
+use std::{sync::{Arc, Mutex}, time::Duration};
+use cddio_core::{
+    event::ComponentEventDispatcher,
+    lifecycle::Health,
+    testing::{fixtures::CommandBuilder, Harness},
+    Component, ComponentContainer, ComponentDeclarative, ComponentEvent, ComponentLifecycle,
+};
+use futures_locks::RwLock;
+use serenity::{async_trait, client::Context, model::event::Event, prelude::RawEventHandler};
+
+type Journal = Arc<Mutex<Vec<String>>>;
+
+/// Component writing its lifecycle to a shared journal.
+struct Recorder {
+    name: &'static str,
+    journal: Journal,
+    /// Time taken to handle an event or to stop
+    delay: Duration,
+    fail_init: bool,
+}
+
+impl Recorder {
+    fn new(name: &'static str, journal: &Journal) -> Self {
+        Recorder { name, journal: Arc::clone(journal), delay: Duration::ZERO, fail_init: false }
+    }
+    fn log(&self, entry: &str) {
+        self.journal.lock().unwrap().push(format!("{} {}", entry, self.name));
+    }
+}
+
+#[async_trait]
+impl ComponentEvent for Recorder {
+    async fn event(&self, _: &Context, _: &Event) {
+        tokio::time::sleep(self.delay).await;
+        self.log("event");
+    }
+}
+impl ComponentDeclarative for Recorder {}
+#[async_trait]
+impl ComponentLifecycle for Recorder {
+    async fn init(&self) -> Result<(), String> {
+        self.log("init");
+        match self.fail_init {
+            true => Err("échec".to_string()),
+            false => Ok(()),
+        }
+    }
+    async fn on_shutdown(&self) {
+        tokio::time::sleep(self.delay).await;
+        self.log("shutdown");
+    }
+    async fn health(&self) -> Health {
+        match self.fail_init {
+            true => Health::Unhealthy("échec".to_string()),
+            false => Health::Healthy,
+        }
+    }
+}
+impl Component for Recorder {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+fn journal(journal: &Journal) -> Vec<String> {
+    journal.lock().unwrap().clone()
+}
+
+#[tokio::test]
+async fn components_start_in_order_and_stop_in_reverse_order() {
+    let log = Journal::default();
+    let mut container = ComponentContainer::new();
+    container.add_component(Recorder::new("settings", &log));
+    container.add_component(Recorder::new("moderation", &log));
+
+    container.init_components().await.unwrap();
+    container.shutdown_components(Duration::from_secs(1)).await;
+    assert_eq!(journal(&log), ["init settings", "init moderation", "shutdown moderation", "shutdown settings"]);
+}
+
+#[tokio::test]
+async fn init_stops_at_the_first_error() {
+    let log = Journal::default();
+    let mut container = ComponentContainer::new();
+    container.add_component(Recorder { fail_init: true, ..Recorder::new("settings", &log) });
+    container.add_component(Recorder::new("moderation", &log));
+
+    assert_eq!(container.init_components().await, Err("settings: échec".to_string()));
+    assert_eq!(journal(&log), ["init settings"]);
+    assert_eq!(container.health().await, [
+        ("settings", Health::Unhealthy("échec".to_string())),
+        ("moderation", Health::Healthy),
+    ]);
+}
+
+#[tokio::test]
+async fn slow_components_do_not_block_the_shutdown() {
+    let log = Journal::default();
+    let mut container = ComponentContainer::new();
+    container.add_component(Recorder::new("settings", &log));
+    container.add_component(Recorder { delay: Duration::from_secs(60), ..Recorder::new("moderation", &log) });
+
+    container.shutdown_components(Duration::from_millis(50)).await;
+    assert_eq!(journal(&log), ["shutdown settings"]);
+}
+
+#[tokio::test]
+async fn pending_events_are_awaited() {
+    let harness = Harness::new().await.unwrap();
+    let log = Journal::default();
+    let container = RwLock::new(ComponentContainer::new());
+    container.write().await.add_component(Recorder { delay: Duration::from_millis(50), ..Recorder::new("tickets", &log) });
+    let dispatcher = ComponentEventDispatcher::new(container);
+
+    dispatcher.raw_event(harness.context().clone(), CommandBuilder::new("ping").event()).await;
+    assert!(journal(&log).is_empty());
+    dispatcher.pending_events().wait().await;
+    assert_eq!(journal(&log), ["event tickets"]);
+}
@@ -7,6 +7,7 @@ use super::util::*;
 use super::command::Command;
 use super::event::Event;
 use super::message_component::Interaction;
+use super::modal_submit::ModalSubmit;
 
 pub trait Function : ToTokens + std::fmt::Debug {
     fn name(&self) -> pm2::TokenStream;
@@ -104,7 +105,8 @@ impl FunctionType {
         to_event!(impl_fn, 
             ("command", Command, Command),
             ("event", Event, Event),
-            ("message_component", Interaction, Event)
+            ("message_component", Interaction, Event),
+            ("modal_submit", ModalSubmit, Event)
         )
     }
     pub fn new_rc(impl_fn: syn::ImplItemMethod) -> syn::Result<RefFunction> {
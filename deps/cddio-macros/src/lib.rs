@@ -186,6 +186,7 @@ Déclare un événement Discord de type *message component*.
 |argument|optionnel|description|
 |:-|:-:|:-|
 |*custom_id*| |custom_id intégré au message component|
+|*custom_id_prefix*| |à la place de *custom_id* : accepte tous les custom_id commençant par ce préfixe|
 
 
 ```rust
@@ -209,12 +210,38 @@ use serenity::model::{
 async fn on_button_ticket_close(&self, ctx: &Context, message_interaction: &MessageComponentInteraction) 
 {}
 ```
+
+Le préfixe permet de transmettre des informations dans le custom_id, par exemple `ticket_rating:<salon>:<note>` :
+
+```rust,ignore
+#[message_component(custom_id_prefix="ticket_rating:")]
+async fn on_button_ticket_rating(&self, ctx: &Context, msg: &MessageComponentInteraction) 
+{}
+```
+
+### `#[modal_submit()]`
+
+Déclare un événement Discord de type *modal submit*, déclenché à la validation d'une fenêtre modale.
+
+|argument|optionnel|description|
+|:-|:-:|:-|
+|*custom_id*| |custom_id de la fenêtre modale|
+|*custom_id_prefix*| |à la place de *custom_id* : accepte tous les custom_id commençant par ce préfixe|
+
+```rust,ignore
+#[modal_submit(custom_id="modal_ticket_create")]
+async fn on_modal_ticket_create(&self, ctx: &Context, modal: &ModalSubmitInteraction) 
+{}
+```
+
+Les valeurs saisies sont récupérables avec `cddio_core::modal::values`.
  */
 
 mod function;
 mod command;
 mod event;
 mod message_component;
+mod modal_submit;
 
 mod util;
 mod log;
@@ -350,11 +377,18 @@ fn expand_commands(input: proc_macro2::TokenStream) -> syn::Result<proc_macro2::
             }
         }
     };
+    let component_name = struct_name.path.segments.last()
+        .map(|segment| segment.ident.to_string().to_lowercase())
+        .unwrap_or_default();
     let result = quote! {
         #impl_event
         #impl_declaratives
 
-        impl cddio_core::Component for #struct_name {}
+        impl cddio_core::Component for #struct_name {
+            fn name(&self) -> &'static str {
+                #component_name
+            }
+        }
         
         #impl_functions
     };
@@ -8,7 +8,9 @@ use crate::function::Function;
 
 #[derive(Debug, Clone, Default)]
 pub struct InteractionAttribute {
-    custom_id: String,
+    pub custom_id: String,
+    /// Le custom_id est un préfixe: l'interaction est acceptée si son custom_id commence par celui-ci
+    pub prefix: bool,
 }
 pub struct Interaction {
     attr: InteractionAttribute,
@@ -16,7 +18,7 @@ pub struct Interaction {
 }
 
 impl InteractionAttribute {
-    fn from_attr(attr: syn::Attribute) -> syn::Result<Self> {
+    pub fn from_attr(attr: syn::Attribute) -> syn::Result<Self> {
         use syn::*;
         let attr_span = attr.span();
         let mut result = Self::default();
@@ -24,6 +26,10 @@ impl InteractionAttribute {
         for arg in args.value.args.into_iter() {
             match (arg.name.to_string().as_str(), arg.value) {
                 ("custom_id", Lit::Str(s)) => result.custom_id = s.value(),
+                ("custom_id_prefix", Lit::Str(s)) => {
+                    result.custom_id = s.value();
+                    result.prefix = true;
+                },
                 _ => return Err(Error::new_spanned(arg.name, "Argument inconnu ou mal typé.")),
             }
         }
@@ -32,6 +38,15 @@ impl InteractionAttribute {
         }
         Ok(result)
     }
+    /// Condition sur le custom_id de l'interaction `value`.
+    pub fn guard(&self, value: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        let custom_id = &self.custom_id;
+        if self.prefix {
+            quote!{ #value.starts_with(#custom_id) }
+        } else {
+            quote!{ #value == #custom_id }
+        }
+    }
 }
 
 impl Interaction {
@@ -52,9 +67,9 @@ impl Function for Interaction {
 
     fn event_handle(&self) -> syn::Result<proc_macro2::TokenStream> {
         let func_name = self.name();
-        let custom_id = &self.attr.custom_id;
+        let guard = self.attr.guard(quote!{ message_interaction.data.custom_id });
         Ok(quote!{
-            serenity::model::event::Event::InteractionCreate(serenity::model::event::InteractionCreateEvent{interaction: serenity::model::application::interaction::Interaction::MessageComponent(message_interaction), ..}) if message_interaction.data.custom_id == #custom_id => self.#func_name(ctx, message_interaction).await
+            serenity::model::event::Event::InteractionCreate(serenity::model::event::InteractionCreateEvent{interaction: serenity::model::application::interaction::Interaction::MessageComponent(message_interaction), ..}) if #guard => self.#func_name(ctx, message_interaction).await
         })
     }
 }
@@ -1,29 +1,40 @@
 //! Core de l'application. 
 //! L'initialisation du bot et la gestion des composants se fait dans ce module.
 
+use std::{sync::Arc, time::Duration};
 use futures_locks::RwLock;
 use serenity::{Client, model::id::{ApplicationId, UserId}, prelude::GatewayIntents};
-use crate::{components as cmp, config::Config};
+use crate::{components as cmp, config::Config, log_info, log_warn};
 use cddio_core as core;
 
 type Result<T> = serenity::Result<T>;
 
+/// Durée maximale laissée aux événements en cours puis à chaque composant pour s'arrêter
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// Structure du bot.
 /// 
 /// Il s'agit de la classe mère de l'application. 
 /// 
 /// Le bot est composé de plusieurs composants qui sont créés et placés dans un [ComponentContainer]. 
-/// Le conteneur retourne au client du bot un [ComponentEventDispatcher], un event dispatcher 
-/// qui se charge de déployer les événements Discord dans les composants.
+/// Le client du bot reçoit un [ComponentEventDispatcher], un event dispatcher 
+/// qui se charge de déployer les événements Discord dans les composants activés du conteneur.
+/// 
+/// 
+/// À l'arrêt du bot (SIGINT ou SIGTERM), les composants terminent les événements en cours
+/// puis enregistrent leurs données (voir [ComponentLifecycle]).
 /// 
 /// [ComponentContainer]: core::ComponentContainer
 /// [ComponentEventDispatcher]: core::event::ComponentEventDispatcher
+/// [ComponentLifecycle]: core::ComponentLifecycle
 pub struct Bot {
     /// Client discord de serenity
     client: Client,
     /// Handler des composants.
     /// Actuellement un vecteur mais prochainement un gestionnaire est prévu.
-    _cmp_container: RwLock<core::ComponentContainer>
+    cmp_container: RwLock<core::ComponentContainer>,
+    /// Événements en cours de traitement par les composants
+    pending_events: core::event::PendingEvents,
 }
 
 impl Bot {
@@ -40,31 +51,74 @@ impl Bot {
         {
             let mut container = ref_container.write().await;
             container.add_component(cmp::Help::new(ref_container.clone()));
-            let modo = container.add_component(cmp::Moderation::new());
-            container.add_component(cmp::Tickets::new());
-            container.add_component(cmp::SlashCommand::new(app_id, ref_container.clone(), owners_id));
+            let settings = container.add_component(cmp::Settings::new(owners_id.clone()));
+            let modo = container.add_component(cmp::Moderation::new(settings.clone()));
+            container.add_shared_component(Arc::new_cyclic(|me| cmp::Tickets::new(settings.clone(), me.clone())));
+            let slash = container.add_component(cmp::SlashCommand::new(app_id, ref_container.clone(), owners_id));
             container.add_component(cmp::Misc::new(app_id, perms, ref_container.clone()));
             container.add_component(cmp::DalleMini);
-            container.add_component(cmp::Autobahn::new(modo));
+            container.add_component(cmp::Autobahn::new(modo, settings));
+            slash.restore_components(&mut container).await;
         }
-        let client = Client::builder(&config.token, GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT)
-            .raw_event_handler(ref_container.read().await.get_event_dispatcher())
+        let dispatcher = core::event::ComponentEventDispatcher::new(ref_container.clone());
+        let pending_events = dispatcher.pending_events();
+        let client = Client::builder(&config.token, GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT | GatewayIntents::GUILD_MEMBERS)
+            .raw_event_handler(dispatcher)
             .application_id(config.app_id)
             .await?;
         Ok(Bot{
             client,
-            _cmp_container: ref_container
+            cmp_container: ref_container,
+            pending_events,
         })
     }
-    /// Lance le bot.
-    pub async fn start(&mut self) -> Result<()> {
-        self.client.start().await
+    /// Initialise les composants puis lance le bot jusqu'à la réception de SIGINT ou SIGTERM.
+    /// 
+    /// Les composants sont ensuite arrêtés avant de rendre la main.
+    pub async fn start(&mut self) -> std::result::Result<(), String> {
+        self.cmp_container.read().await.init_components().await
+            .map_err(|e| format!("Impossible d'initialiser le composant {}", e))?;
+        let shard_manager = Arc::clone(&self.client.shard_manager);
+        let signal = tokio::spawn(async move {
+            let signal = wait_for_signal().await;
+            log_info!("{} reçu, arrêt du bot", signal);
+            shard_manager.lock().await.shutdown_all().await;
+        });
+        let result = self.client.start().await.map_err(|e| e.to_string());
+        signal.abort();
+        self.shutdown().await;
+        result
+    }
+    /// Attend la fin des événements en cours puis arrête les composants.
+    async fn shutdown(&self) {
+        if tokio::time::timeout(SHUTDOWN_TIMEOUT, self.pending_events.wait()).await.is_err() {
+            log_warn!("Des événements étaient encore en cours après {:?}", SHUTDOWN_TIMEOUT);
+        }
+        self.cmp_container.read().await.shutdown_components(SHUTDOWN_TIMEOUT).await;
+        log_info!("Bot arrêté");
     }
 }
 
-//TODO: Enregistrer la configuration du bot lors du drop de ce dernier
-impl Drop for Bot {
-    fn drop(&mut self) {
-        println!("Bot dropped");
+/// Attend la réception de SIGINT (Ctrl+C) ou de SIGTERM et retourne son nom.
+async fn wait_for_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => tokio::select! {
+                _ = tokio::signal::ctrl_c() => "SIGINT",
+                _ = sigterm.recv() => "SIGTERM",
+            },
+            Err(e) => {
+                log_warn!("Impossible d'écouter SIGTERM: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+                "SIGINT"
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        "SIGINT"
     }
 }
\ No newline at end of file
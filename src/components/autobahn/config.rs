@@ -0,0 +1,147 @@
+//! Configuration d'Autobahn par serveur
+//!
+//! Les seuils de détection, la durée du mute et les exemptions sont propres à chaque serveur.
+//! En mode simulation, les détections sont seulement signalées dans le salon des logs.
+
+use std::collections::HashMap;
+use cddio_core::message;
+use serde::{Deserialize, Serialize};
+use serenity::model::id::*;
+
+use super::{rules::{Rule, RuleAction}, raid::RaidConfig, detection::Thresholds, history};
+use super::super::utils::time_parser as time;
+
+/// Configuration d'Autobahn pour un serveur
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AutobahnConfig {
+    /// Nombre de messages identiques tolérés
+    pub max_messages: usize,
+    /// Durée en secondes pendant laquelle les messages sont comparés
+    pub max_time: u64,
+    /// Durée du mute en secondes
+    pub mute_time: u64,
+    /// Similarité en pourcentage à partir de laquelle deux messages sont considérés identiques
+    pub similarity: u8,
+    /// Nombre de salons différents dans lesquels un même message déclenche la détection.
+    /// La détection sur plusieurs salons est désactivée si la valeur est inférieure à 2.
+    pub max_channels: usize,
+    /// Sanction de chaque règle de filtrage. Les règles absentes sont désactivées.
+    pub rules: HashMap<Rule, RuleAction>,
+    /// Nombre de mentions tolérées dans un message
+    pub max_mentions: usize,
+    /// Nombre de fichiers tolérés pendant la fenêtre de détection
+    pub max_attachments: usize,
+    /// Nombre d'emojis tolérés dans un message
+    pub max_emojis: usize,
+    /// Rôles dont les membres ne sont pas surveillés
+    pub exempt_roles: Vec<RoleId>,
+    /// Salons non surveillés
+    pub exempt_channels: Vec<ChannelId>,
+    /// Signale les détections sans sanctionner
+    pub dry_run: bool,
+    /// Détection des raids et confinement du serveur
+    pub raid: RaidConfig,
+}
+
+impl Default for AutobahnConfig {
+    fn default() -> Self {
+        AutobahnConfig {
+            max_messages: 4,
+            max_time: 20,
+            mute_time: 24 * 60 * 60,
+            similarity: 80,
+            max_channels: 3,
+            // Seuls les liens de phishing sont supprimés, les autres règles sont activées par les admins.
+            // Sans liste de domaines chargée, la règle n'a aucun effet.
+            rules: HashMap::from([(Rule::Phishing, RuleAction::Delete)]),
+            max_mentions: 5,
+            max_attachments: 6,
+            max_emojis: 20,
+            exempt_roles: Vec::new(),
+            exempt_channels: Vec::new(),
+            dry_run: false,
+            raid: RaidConfig::default(),
+        }
+    }
+}
+
+impl AutobahnConfig {
+    /// Indique si un message envoyé dans `channel_id` par un membre ayant les rôles `roles` est ignoré.
+    pub fn is_exempt(&self, channel_id: ChannelId, roles: &[RoleId]) -> bool {
+        self.exempt_channels.contains(&channel_id)
+            || roles.iter().any(|role| self.exempt_roles.contains(role))
+    }
+    /// Nombre de messages récents à conserver par membre pour appliquer les seuils de détection.
+    pub fn history_capacity(&self) -> usize {
+        history::capacity(self.max_messages.max(self.max_channels).max(self.max_attachments))
+    }
+    /// Seuils de détection des messages répétés.
+    pub fn thresholds(&self) -> Thresholds {
+        Thresholds {
+            max_messages: self.max_messages,
+            window: self.max_time,
+            similarity: self.similarity,
+            max_channels: self.max_channels,
+        }
+    }
+    /// Ajoute l'élément s'il est absent, le retire sinon.
+    pub fn toggle<T: PartialEq>(list: &mut Vec<T>, value: T) {
+        match list.iter().position(|v| *v == value) {
+            Some(pos) => { list.remove(pos); },
+            None => list.push(value),
+        }
+    }
+    pub fn to_message(&self) -> message::Message {
+        fn list<T: std::fmt::Display>(values: &[T], mention: &str) -> String {
+            match values.is_empty() {
+                true => "*Aucun*".to_string(),
+                false => values.iter().map(|v| format!("<{}{}>", mention, v)).collect::<Vec<_>>().join(", "),
+            }
+        }
+        let mut msg = message::Message::new();
+        msg.add_embed(|embed| {
+            embed
+                .title("Configuration d'Autobahn")
+                .color(message::COLOR_INFO)
+                .field("Messages identiques tolérés", self.max_messages.to_string(), true)
+                .field("Fenêtre de détection", time::format_duration(self.max_time), true)
+                .field("Durée du mute", time::format_duration(self.mute_time), true)
+                .field("Similarité minimale", format!("{}%", self.similarity), true)
+                .field("Salons différents", match self.max_channels {
+                    0 | 1 => "Désactivé".to_string(),
+                    n => n.to_string(),
+                }, true)
+                .field("Limites", format!(
+                    "{} mentions par message\n{} fichiers en {}\n{} emojis par message",
+                    self.max_mentions, self.max_attachments, time::format_duration(self.max_time), self.max_emojis
+                ), false)
+                .field("Règles", Rule::ALL.iter()
+                    .map(|rule| match self.rules.get(rule) {
+                        Some(action) => format!("**{}** : {}", rule.name(), action.describe(self.mute_time)),
+                        None => format!("**{}** : désactivée", rule.name()),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"), false)
+                .field("Rôles exemptés", list(&self.exempt_roles, "@&"), false)
+                .field("Salons exemptés", list(&self.exempt_channels, "#"), false)
+                .field("Mode simulation", if self.dry_run { "Activé" } else { "Désactivé" }, true)
+        });
+        msg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_phishing_is_filtered_by_default() {
+        let config = AutobahnConfig::default();
+        assert_eq!(config.rules, HashMap::from([(Rule::Phishing, RuleAction::Delete)]));
+        // Une configuration enregistrée sans règles reçoit les règles par défaut
+        let saved: AutobahnConfig = serde_json::from_str(r#"{"max_messages": 6}"#).unwrap();
+        assert_eq!(saved.max_messages, 6);
+        assert_eq!(saved.rules, config.rules);
+    }
+}
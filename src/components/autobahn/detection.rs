@@ -0,0 +1,148 @@
+//! Détection des messages répétés
+//!
+//! Un message est comparé aux messages récents de son auteur : il est détecté comme du spam
+//! si trop de messages similaires ont été envoyés pendant la fenêtre de détection,
+//! ou si un message similaire a été envoyé dans trop de salons différents.
+
+use serde::{Deserialize, Serialize};
+use serenity::model::id::*;
+
+use super::{history::{History, Timestamped}, similarity::Fingerprint};
+
+/// Message récent d'un membre
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MessageInfo {
+    /// Date d'envoi (timestamp unix)
+    pub time: i64,
+    pub id: (ChannelId, MessageId),
+    /// Nombre de fichiers joints au message
+    pub attachments: usize,
+    pub fingerprint: Fingerprint,
+}
+
+impl Timestamped for MessageInfo {
+    fn timestamp(&self) -> i64 {
+        self.time
+    }
+}
+
+/// Serveur et auteur d'un message
+pub type MessageKey = (GuildId, UserId);
+/// Historique des messages récents de chaque membre
+pub type MessageHistory = History<MessageKey, MessageInfo>;
+
+/// Seuils de détection des messages répétés
+#[derive(Debug, Clone, Copy)]
+pub struct Thresholds {
+    /// Nombre de messages similaires tolérés
+    pub max_messages: usize,
+    /// Durée en secondes pendant laquelle les messages sont comparés
+    pub window: u64,
+    /// Similarité en pourcentage à partir de laquelle deux messages sont considérés identiques
+    pub similarity: u8,
+    /// Nombre de salons différents dans lesquels un même message déclenche la détection.
+    /// La détection sur plusieurs salons est désactivée si la valeur est inférieure à 2.
+    pub max_channels: usize,
+}
+
+impl Thresholds {
+    /// Indique si deux messages de similarité `similarity` (de 0 à 1) sont considérés identiques.
+    pub fn is_similar(&self, similarity: f64) -> bool {
+        similarity * 100.0 >= self.similarity as f64
+    }
+}
+
+/// Motif de détection d'un spam
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Detection {
+    /// Nombre de messages similaires envoyés dans la fenêtre de détection
+    Repeated(usize),
+    /// Nombre de salons dans lesquels un message similaire a été envoyé
+    CrossChannel(usize),
+}
+
+impl std::fmt::Display for Detection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Detection::Repeated(_) => write!(f, "Détection de spam"),
+            Detection::CrossChannel(_) => write!(f, "Détection de spam sur plusieurs salons"),
+        }
+    }
+}
+
+/// Compare le message `msg_info` de `key` à ses messages récents.
+pub fn detect(history: &MessageHistory, key: &MessageKey, msg_info: &MessageInfo, thresholds: &Thresholds) -> Option<Detection> {
+    let similar = history.entries(key, msg_info.time, thresholds.window)
+        .filter(|v| thresholds.is_similar(v.fingerprint.similarity(&msg_info.fingerprint)))
+        .collect::<Vec<_>>();
+    let mut channels = similar.iter().map(|v| v.id.0).collect::<Vec<_>>();
+    channels.push(msg_info.id.0);
+    channels.sort_unstable();
+    channels.dedup();
+    if similar.len()+1 > thresholds.max_messages {
+        Some(Detection::Repeated(similar.len()+1))
+    } else if thresholds.max_channels > 1 && channels.len() >= thresholds.max_channels {
+        Some(Detection::CrossChannel(channels.len()))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::history::MAX_ENTRIES;
+
+    const KEY: MessageKey = (GuildId(1), UserId(2));
+    const THRESHOLDS: Thresholds = Thresholds { max_messages: 3, window: 20, similarity: 80, max_channels: 3 };
+
+    fn info(time: i64, channel: u64, content: &str) -> MessageInfo {
+        MessageInfo {
+            time,
+            id: (ChannelId(channel), MessageId(time as u64 + 1)),
+            attachments: 0,
+            fingerprint: Fingerprint::new(content),
+        }
+    }
+
+    fn send(history: &mut MessageHistory, msg_info: MessageInfo) -> Option<Detection> {
+        let detection = detect(history, &KEY, &msg_info, &THRESHOLDS);
+        history.push(KEY, msg_info, THRESHOLDS.window, MAX_ENTRIES);
+        detection
+    }
+
+    #[test]
+    fn repeated_messages_are_detected() {
+        let mut history = MessageHistory::default();
+        assert_eq!(send(&mut history, info(0, 1, "free nitro")), None);
+        assert_eq!(send(&mut history, info(1, 1, "free nitro !")), None);
+        assert_eq!(send(&mut history, info(2, 1, "bonjour")), None);
+        assert_eq!(send(&mut history, info(3, 1, "FREE NITRO")), None);
+        assert_eq!(send(&mut history, info(4, 1, "free nitroo")), Some(Detection::Repeated(4)));
+    }
+
+    #[test]
+    fn old_messages_are_ignored() {
+        let mut history = MessageHistory::default();
+        for time in 0..3 {
+            send(&mut history, info(time, 1, "free nitro"));
+        }
+        assert_eq!(send(&mut history, info(2 + THRESHOLDS.window as i64, 1, "free nitro")), None);
+    }
+
+    #[test]
+    fn cross_channel_messages_are_detected() {
+        let mut history = MessageHistory::default();
+        assert_eq!(send(&mut history, info(0, 1, "free nitro")), None);
+        assert_eq!(send(&mut history, info(1, 2, "free nitro")), None);
+        assert_eq!(send(&mut history, info(2, 3, "free nitro")), Some(Detection::CrossChannel(3)));
+    }
+
+    #[test]
+    fn cross_channel_detection_can_be_disabled() {
+        let mut history = MessageHistory::default();
+        let thresholds = Thresholds { max_channels: 1, ..THRESHOLDS };
+        history.push(KEY, info(0, 1, "free nitro"), THRESHOLDS.window, MAX_ENTRIES);
+        assert_eq!(detect(&history, &KEY, &info(1, 2, "free nitro"), &thresholds), None);
+    }
+}
@@ -0,0 +1,351 @@
+//! Historique borné des messages récents
+//!
+//! Chaque membre dispose d'un tampon circulaire de [`BUCKETS`] intervalles de temps couvrant
+//! la fenêtre de détection. Les intervalles expirés sont vidés à l'insertion suivante :
+//! l'accès aux messages d'un membre ne dépend pas du nombre de membres suivis.
+//! Un membre conserve au plus le nombre d'entrées nécessaire à la détection, borné par [`MAX_ENTRIES`],
+//! les plus anciennes laissant leur place aux nouvelles. Les membres inactifs sont oubliés lors des
+//! nettoyages, et le nombre de membres suivis est borné par [`MAX_USERS`].
+
+use std::{collections::HashMap, hash::Hash};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Nombre d'intervalles du tampon circulaire d'un membre
+pub const BUCKETS: usize = 8;
+/// Nombre maximal d'entrées conservées par membre
+pub const MAX_ENTRIES: usize = 12;
+/// Entrées conservées en plus de celles nécessaires à la détection
+const MARGIN: usize = 2;
+/// Seuil maximal de détection, pour que l'historique d'un membre puisse l'atteindre
+pub const MAX_THRESHOLD: usize = MAX_ENTRIES - MARGIN;
+/// Nombre maximal de membres suivis
+pub const MAX_USERS: usize = 20_000;
+
+/// Nombre d'entrées à conserver par membre pour détecter jusqu'à `needed` entrées dans la fenêtre.
+pub fn capacity(needed: usize) -> usize {
+    (needed + MARGIN).min(MAX_ENTRIES)
+}
+
+/// Entrée datée de l'historique
+pub trait Timestamped {
+    /// Date de l'entrée (timestamp unix)
+    fn timestamp(&self) -> i64;
+}
+
+/// Intervalle de temps du tampon circulaire
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Bucket<T> {
+    /// Numéro de l'intervalle depuis l'epoch unix, en nombre d'intervalles
+    epoch: i64,
+    entries: Vec<T>,
+}
+
+/// Tampon circulaire d'un membre
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Ring<T> {
+    /// Durée d'un intervalle en secondes
+    span: i64,
+    /// Date de la dernière insertion (timestamp unix)
+    last: i64,
+    buckets: Vec<Bucket<T>>,
+}
+
+impl<T: Timestamped> Ring<T> {
+    fn new(span: i64) -> Self {
+        Ring {
+            span,
+            last: 0,
+            buckets: (0..BUCKETS).map(|_| Bucket { epoch: -1, entries: Vec::new() }).collect(),
+        }
+    }
+    /// Durée d'un intervalle pour que le tampon couvre `window` secondes.
+    fn span_for(window: u64) -> i64 {
+        ((window as i64 + BUCKETS as i64 - 2) / (BUCKETS as i64 - 1)).max(1)
+    }
+    /// Le tampon ne contient plus aucune entrée récente.
+    fn is_expired(&self, now: i64) -> bool {
+        now - self.last >= self.span * BUCKETS as i64
+    }
+    /// Intervalles couvrant les `BUCKETS` derniers intervalles de temps.
+    fn live(&self, now: i64) -> impl Iterator<Item = &Bucket<T>> {
+        let current = now / self.span;
+        self.buckets.iter()
+            .filter(move |bucket| bucket.epoch > current - BUCKETS as i64 && bucket.epoch <= current)
+    }
+    fn entries(&self, now: i64, window: u64) -> impl Iterator<Item = &T> {
+        self.live(now)
+            .flat_map(|bucket| bucket.entries.iter())
+            .filter(move |entry| now - entry.timestamp() < window as i64)
+    }
+    /// Ajoute une entrée en conservant au plus `capacity` entrées.
+    /// Retourne `false` si des entrées plus anciennes ont été retirées pour faire de la place.
+    fn push(&mut self, now: i64, entry: T, capacity: usize) -> bool {
+        let current = now / self.span;
+        for bucket in self.buckets.iter_mut() {
+            if bucket.epoch <= current - BUCKETS as i64 && !bucket.entries.is_empty() {
+                bucket.entries = Vec::new();
+            }
+        }
+        let index = current.rem_euclid(BUCKETS as i64) as usize;
+        if self.buckets[index].epoch != current {
+            self.buckets[index].epoch = current;
+            self.buckets[index].entries.clear();
+        }
+        self.last = now;
+        let mut kept = true;
+        while self.len(now) >= capacity.max(1) {
+            // Les entrées d'un intervalle sont dans l'ordre d'insertion
+            let oldest = self.buckets.iter_mut()
+                .filter(|bucket| bucket.epoch > current - BUCKETS as i64 && !bucket.entries.is_empty())
+                .min_by_key(|bucket| bucket.epoch);
+            match oldest {
+                Some(bucket) => bucket.entries.remove(0),
+                None => break,
+            };
+            kept = false;
+        }
+        self.buckets[index].entries.push(entry);
+        kept
+    }
+    fn len(&self, now: i64) -> usize {
+        self.live(now).map(|bucket| bucket.entries.len()).sum()
+    }
+}
+
+/// Statistiques de l'historique
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HistoryStats {
+    /// Nombre de membres suivis
+    pub users: usize,
+    /// Nombre d'entrées conservées
+    pub entries: usize,
+    /// Nombre d'entrées retirées pour faire de la place aux nouvelles
+    pub dropped: u64,
+    /// Nombre de membres oubliés car la limite de membres suivis était atteinte
+    pub evicted: u64,
+}
+
+/// Historique des entrées récentes de chaque clé
+#[serde_with::serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound(serialize = "K: Serialize, T: Serialize", deserialize = "K: DeserializeOwned + Eq + Hash, T: DeserializeOwned"))]
+pub struct History<K, T> {
+    #[serde_as(as = "Vec<(_, _)>")]
+    users: HashMap<K, Ring<T>>,
+    #[serde(skip)]
+    dropped: u64,
+    #[serde(skip)]
+    evicted: u64,
+}
+
+impl<K, T> Default for History<K, T> {
+    fn default() -> Self {
+        History {
+            users: HashMap::new(),
+            dropped: 0,
+            evicted: 0,
+        }
+    }
+}
+
+impl<K, T> History<K, T>
+    where K: Eq + Hash + Clone, T: Timestamped
+{
+    /// Entrées de `key` datant de moins de `window` secondes.
+    pub fn entries(&self, key: &K, now: i64, window: u64) -> impl Iterator<Item = &T> {
+        self.users.get(key)
+            .into_iter()
+            .flat_map(move |ring| ring.entries(now, window))
+    }
+    /// Ajoute une entrée à `key`, dont les `capacity` dernières entrées sont conservées pendant `window` secondes.
+    pub fn push(&mut self, key: K, entry: T, window: u64, capacity: usize) {
+        let now = entry.timestamp();
+        let span = Ring::<T>::span_for(window);
+        if !self.users.contains_key(&key) && self.users.len() >= MAX_USERS {
+            self.prune(now);
+            if self.users.len() >= MAX_USERS {
+                self.evict_oldest();
+            }
+        }
+        let ring = self.users.entry(key).or_insert_with(|| Ring::new(span));
+        if ring.span != span {
+            *ring = Ring::new(span);
+        }
+        if !ring.push(now, entry, capacity.min(MAX_ENTRIES)) {
+            self.dropped += 1;
+        }
+    }
+    /// Oublie les entrées de `key`.
+    pub fn remove(&mut self, key: &K) {
+        self.users.remove(key);
+    }
+    /// Oublie les membres sans entrée récente.
+    pub fn prune(&mut self, now: i64) {
+        self.users.retain(|_, ring| !ring.is_expired(now));
+    }
+    fn evict_oldest(&mut self) {
+        let oldest = self.users.iter()
+            .min_by_key(|(_, ring)| ring.last)
+            .map(|(key, _)| key.clone());
+        if let Some(key) = oldest {
+            self.users.remove(&key);
+            self.evicted += 1;
+        }
+    }
+    pub fn stats(&self, now: i64) -> HistoryStats {
+        HistoryStats {
+            users: self.users.len(),
+            entries: self.users.values().map(|ring| ring.len(now)).sum(),
+            dropped: self.dropped,
+            evicted: self.evicted,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Entry(i64);
+
+    impl Timestamped for Entry {
+        fn timestamp(&self) -> i64 {
+            self.0
+        }
+    }
+
+    fn times<'a>(entries: impl Iterator<Item = &'a Entry>) -> Vec<i64> {
+        let mut times = entries.map(|entry| entry.0).collect::<Vec<_>>();
+        times.sort_unstable();
+        times
+    }
+
+    #[test]
+    fn ring_covers_the_window() {
+        assert_eq!(Ring::<Entry>::span_for(0), 1);
+        assert_eq!(Ring::<Entry>::span_for(1), 1);
+        assert_eq!(Ring::<Entry>::span_for(7), 1);
+        assert_eq!(Ring::<Entry>::span_for(8), 2);
+        assert_eq!(Ring::<Entry>::span_for(20), 3);
+        for window in 1..500u64 {
+            let span = Ring::<Entry>::span_for(window);
+            // Les intervalles précédant l'intervalle courant couvrent toute la fenêtre
+            assert!(span * (BUCKETS as i64 - 1) >= window as i64, "window {}", window);
+            assert!((span - 1) * (BUCKETS as i64 - 1) < window as i64, "window {}", window);
+        }
+    }
+
+    #[test]
+    fn ring_wraps_around() {
+        let mut ring = Ring::new(1);
+        for time in 0..BUCKETS as i64 * 3 {
+            assert!(ring.push(time, Entry(time), MAX_ENTRIES));
+        }
+        let now = BUCKETS as i64 * 3 - 1;
+        let expected = (now + 1 - BUCKETS as i64..=now).collect::<Vec<_>>();
+        assert_eq!(times(ring.entries(now, 1000)), expected);
+        assert_eq!(ring.len(now), BUCKETS);
+        // Les intervalles dépassés ne sont plus lus avant d'être réutilisés
+        assert_eq!(times(ring.entries(now + 2, 1000)), expected[2..]);
+        assert_eq!(ring.len(now + BUCKETS as i64), 0);
+    }
+
+    #[test]
+    fn entries_are_filtered_by_window() {
+        let mut ring = Ring::new(Ring::<Entry>::span_for(20));
+        for time in [0, 5, 10, 15, 19] {
+            ring.push(time, Entry(time), MAX_ENTRIES);
+        }
+        assert_eq!(times(ring.entries(19, 20)), [0, 5, 10, 15, 19]);
+        assert_eq!(times(ring.entries(25, 20)), [10, 15, 19]);
+        assert!(ring.entries(25, 5).next().is_none());
+    }
+
+    #[test]
+    fn capacity_is_bounded() {
+        assert_eq!(capacity(4), 6);
+        assert_eq!(capacity(MAX_THRESHOLD), MAX_ENTRIES);
+        assert_eq!(capacity(1000), MAX_ENTRIES);
+    }
+
+    #[test]
+    fn oldest_entries_make_room() {
+        let mut history = History::default();
+        for time in 0..10 {
+            history.push(1, Entry(time), 20, 4);
+        }
+        let stats = history.stats(9);
+        assert_eq!((stats.entries, stats.dropped), (4, 6));
+        assert_eq!(times(history.entries(&1, 9, 20)), [6, 7, 8, 9]);
+        // Une capacité plus petite retire les entrées en trop
+        history.push(1, Entry(10), 20, 2);
+        assert_eq!(times(history.entries(&1, 10, 20)), [9, 10]);
+        // La capacité est bornée, quelle que soit la demande
+        for time in 11..100 {
+            history.push(1, Entry(time), 1000, usize::MAX);
+        }
+        assert_eq!(history.stats(99).entries, MAX_ENTRIES);
+    }
+
+    #[test]
+    fn expired_buckets_are_emptied() {
+        let mut ring = Ring::new(1);
+        for time in 0..4 {
+            ring.push(time, Entry(time), MAX_ENTRIES);
+        }
+        // Les intervalles expirés ne gardent aucune entrée en mémoire
+        ring.push(BUCKETS as i64 * 2, Entry(BUCKETS as i64 * 2), MAX_ENTRIES);
+        assert_eq!(ring.buckets.iter().map(|bucket| bucket.entries.len()).sum::<usize>(), 1);
+    }
+
+    #[test]
+    fn window_change_resets_the_ring() {
+        let mut history = History::default();
+        history.push(1, Entry(0), 20, MAX_ENTRIES);
+        history.push(1, Entry(1), 60, MAX_ENTRIES);
+        assert_eq!(times(history.entries(&1, 1, 60)), [1]);
+    }
+
+    #[test]
+    fn inactive_keys_are_pruned() {
+        let mut history = History::default();
+        let span = Ring::<Entry>::span_for(20);
+        history.push(1, Entry(0), 20, MAX_ENTRIES);
+        history.push(2, Entry(10), 20, MAX_ENTRIES);
+        history.prune(span * BUCKETS as i64 - 1);
+        assert_eq!(history.stats(0).users, 2);
+        history.prune(span * BUCKETS as i64);
+        assert_eq!(history.stats(0).users, 1);
+        assert_eq!(history.entries(&1, 10, 20).count(), 0);
+        history.remove(&2);
+        assert_eq!(history.stats(0).users, 0);
+    }
+
+    #[test]
+    fn oldest_key_is_evicted() {
+        let mut history = History::default();
+        for key in 0..MAX_USERS {
+            history.push(key, Entry(key as i64 / 1000), 20, MAX_ENTRIES);
+        }
+        let now = (MAX_USERS / 1000) as i64;
+        history.push(MAX_USERS, Entry(now), 20, MAX_ENTRIES);
+        let stats = history.stats(now);
+        assert_eq!((stats.users, stats.evicted), (MAX_USERS, 1));
+        assert_eq!(history.entries(&MAX_USERS, now, 20).count(), 1);
+        // Un des membres dont le dernier message est le plus ancien est oublié
+        assert_eq!((0..1000).filter(|key| history.entries(key, now, 100).count() == 0).count(), 1);
+    }
+
+    #[test]
+    fn expired_keys_are_pruned_before_eviction() {
+        let mut history = History::default();
+        for key in 0..MAX_USERS {
+            history.push(key, Entry(0), 20, MAX_ENTRIES);
+        }
+        let now = Ring::<Entry>::span_for(20) * BUCKETS as i64;
+        history.push(MAX_USERS, Entry(now), 20, MAX_ENTRIES);
+        let stats = history.stats(now);
+        assert_eq!((stats.users, stats.evicted), (1, 0));
+    }
+}
@@ -0,0 +1,142 @@
+//! Confinement d'un serveur
+//!
+//! Pendant un confinement, le niveau de vérification du serveur est relevé et `@everyone`
+//! ne peut plus envoyer de messages dans les salons configurés. Les permissions et le niveau
+//! de vérification d'origine sont conservés dans la tâche de levée du confinement,
+//! pour être restaurés à la fin de celui-ci.
+
+use std::sync::Arc;
+use cddio_core::message;
+use serde::{Deserialize, Serialize};
+use serenity::{
+    async_trait,
+    client::Context,
+    model::{
+        channel::{PermissionOverwrite, PermissionOverwriteType},
+        guild::VerificationLevel,
+        id::*,
+        permissions::Permissions,
+    },
+};
+use crate::log_warn;
+
+use super::raid::RaidConfig;
+use super::super::{Settings, utils::task};
+
+/// Salon bloqué pendant le confinement
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LockedChannel {
+    pub channel_id: ChannelId,
+    /// Permissions de `@everyone` dans le salon avant le confinement
+    pub previous: Option<PermissionOverwrite>,
+}
+
+/// Confinement en cours d'un serveur, levé à l'expiration de sa tâche.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Lockdown {
+    pub guild_id: GuildId,
+    /// Motif du confinement
+    pub reason: String,
+    /// Niveau de vérification avant le confinement, s'il a été modifié
+    pub verification_level: Option<VerificationLevel>,
+    /// Salons bloqués
+    pub channels: Vec<LockedChannel>,
+}
+
+impl Lockdown {
+    /// Confine le serveur. Les salons qui n'ont pas pu être bloqués sont ignorés.
+    pub async fn engage(ctx: &Context, mut guild_id: GuildId, config: &RaidConfig, reason: String) -> Result<Self, String> {
+        let guild = guild_id.to_partial_guild(ctx).await
+            .map_err(|e| format!("Impossible de récupérer le serveur: {}", e))?;
+        let verification_level = match guild.verification_level < VerificationLevel::High {
+            true => match guild_id.edit(ctx, |edit| edit.verification_level(VerificationLevel::High)).await {
+                Ok(_) => Some(guild.verification_level),
+                Err(e) => {
+                    log_warn!("lockdown: Failed to raise verification level of {}: {}", guild_id, e);
+                    None
+                }
+            },
+            false => None,
+        };
+        let everyone = PermissionOverwriteType::Role(RoleId(guild_id.0));
+        let mut channels = Vec::with_capacity(config.channels.len());
+        for &channel_id in &config.channels {
+            let channel = match channel_id.to_channel(ctx).await.map(|channel| channel.guild()) {
+                Ok(Some(channel)) => channel,
+                Ok(None) => continue,
+                Err(e) => {
+                    log_warn!("lockdown: Failed to get channel {}: {}", channel_id, e);
+                    continue;
+                }
+            };
+            let previous = channel.permission_overwrites.into_iter().find(|overwrite| overwrite.kind == everyone);
+            let (allow, deny) = previous.as_ref()
+                .map(|overwrite| (overwrite.allow, overwrite.deny))
+                .unwrap_or_default();
+            let overwrite = PermissionOverwrite {
+                allow: allow - Permissions::SEND_MESSAGES,
+                deny: deny | Permissions::SEND_MESSAGES,
+                kind: everyone,
+            };
+            match channel_id.create_permission(ctx, &overwrite).await {
+                Ok(_) => channels.push(LockedChannel { channel_id, previous }),
+                Err(e) => log_warn!("lockdown: Failed to lock channel {}: {}", channel_id, e),
+            }
+        }
+        Ok(Lockdown { guild_id, reason, verification_level, channels })
+    }
+    /// Lève le confinement en restaurant les permissions et le niveau de vérification d'origine.
+    pub async fn release(&self, ctx: &Context) {
+        let everyone = PermissionOverwriteType::Role(RoleId(self.guild_id.0));
+        for locked in &self.channels {
+            let result = match &locked.previous {
+                Some(overwrite) => locked.channel_id.create_permission(ctx, overwrite).await,
+                None => locked.channel_id.delete_permission(ctx, everyone).await,
+            };
+            if let Err(e) = result {
+                log_warn!("lockdown: Failed to unlock channel {}: {}", locked.channel_id, e);
+            }
+        }
+        if let Some(level) = self.verification_level {
+            let mut guild_id = self.guild_id;
+            if let Err(e) = guild_id.edit(ctx, |edit| edit.verification_level(level)).await {
+                log_warn!("lockdown: Failed to restore verification level of {}: {}", self.guild_id, e);
+            }
+        }
+    }
+    /// Prévient le staff dans le salon des logs.
+    pub async fn notify(ctx: &Context, settings: &Settings, guild_id: GuildId, title: &str, description: String) {
+        let channel = match settings.log_channel(guild_id).await {
+            Some(channel) => channel,
+            None => {
+                log_warn!("lockdown: No log channel set on guild {}: {}", guild_id, title);
+                return;
+            }
+        };
+        let content = settings.staff_role(ctx, guild_id).await.ok().map(|role| format!("<@&{}>", role));
+        let result = channel.send_message(ctx, |msg| {
+            if let Some(content) = content {
+                msg.content(content);
+            }
+            msg.embed(|embed| {
+                embed
+                    .title(title)
+                    .color(message::COLOR_WARN)
+                    .description(description)
+            })
+        }).await;
+        if let Err(e) = result {
+            log_warn!("lockdown: Failed to notify staff in {}: {}", channel, e);
+        }
+    }
+}
+
+#[async_trait]
+impl task::DataFunc for Lockdown {
+    type Persistent = (Context, Arc<Settings>);
+    async fn run(&self, (ctx, settings): &(Context, Arc<Settings>)) -> Result<(), String> {
+        self.release(ctx).await;
+        Self::notify(ctx, settings, self.guild_id, "🔓 Fin du confinement", "Le confinement du serveur a expiré.".to_string()).await;
+        Ok(())
+    }
+}
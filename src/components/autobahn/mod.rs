@@ -1,33 +1,157 @@
 //! Anti-spam system
 
+mod config;
+mod similarity;
+mod rules;
+mod raid;
+mod lockdown;
+mod history;
+mod detection;
+
 use crate::{log_error, log_warn, log_info};
-use std::sync::Arc;
-use std::collections::HashMap;
+use std::sync::{Arc, Mutex, atomic::{AtomicI64, Ordering}};
+use std::collections::{HashMap, HashSet};
 use chrono::Utc;
 use futures_locks::RwLock;
+use cddio_core::{message, ApplicationCommandEmbed, ComponentLifecycle, lifecycle::Health};
 use cddio_macros::component;
+use serde::{Serialize, Deserialize};
 use serenity::{model::{*, prelude::*}, client::Context};
-use std::hash::Hash;
-use super::Moderation;
-type MessageHash = u64;
-
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct MessageInfo {
-    time: chrono::DateTime<chrono::Utc>,
-    who: (id::GuildId, id::UserId),
-    id: (ChannelId, MessageId)
-}
+use super::{Moderation, Settings};
+use super::utils::{
+    data::{self, Data, DataError, DATA_DIR},
+    time_parser as time,
+    task::{self, Registry},
+    registry_file::RegistryFile,
+};
+use self::{
+    config::AutobahnConfig,
+    similarity::Fingerprint,
+    rules::{Rule, RuleAction, Violation, PhishingList, InviteCache, InviteTarget},
+    raid::{RaidConfig, RaidDetector, JoinerAction},
+    lockdown::Lockdown,
+    detection::{Detection, MessageHistory, MessageInfo, MessageKey},
+};
+
+/// Gestionnaire des levées de confinement
+type LockdownManager = task::TaskManager<Lockdown, RegistryFile<Lockdown>, (Context, Arc<Settings>)>;
+
+/// Fichier des domaines de phishing, dans le dossier des données
+const PHISHING_FILE: &str = "phishing_domains.txt";
+/// Intervalle minimal en secondes entre deux enregistrements de l'historique des messages
+const SAVE_INTERVAL: i64 = 30;
 
 pub struct Autobahn {
-    sent_messages: RwLock<Vec<(MessageHash, MessageInfo)>>,
+    /// Historique des messages récents, enregistré au plus toutes les [`SAVE_INTERVAL`] secondes
+    sent_messages: RwLock<Data<MessageHistory>>,
+    /// Date du dernier enregistrement de l'historique (timestamp unix)
+    last_save: AtomicI64,
     cmp_moderation: Arc<Moderation>,
+    /// Configuration des serveurs
+    settings: Arc<Settings>,
+    /// Données persistantes
+    data: RwLock<Data<DataAutobahn>>,
+    /// Domaines de phishing connus
+    phishing: RwLock<PhishingList>,
+    /// Serveurs des invitations déjà résolues
+    invites: RwLock<InviteCache>,
+    /// Historique des arrivées et messages pour la détection des raids
+    raid: RaidDetector,
+    /// Confinements en cours
+    lockdowns: RwLock<Option<LockdownManager>>,
+    /// Serveurs dont le confinement est en cours de mise en place
+    engaging: Mutex<HashSet<GuildId>>,
+}
+
+/// Réservation d'un serveur pendant la mise en place de son confinement, libérée à la destruction.
+struct LockdownReservation<'a> {
+    engaging: &'a Mutex<HashSet<GuildId>>,
+    guild_id: GuildId,
+}
+
+impl Drop for LockdownReservation<'_> {
+    fn drop(&mut self) {
+        self.engaging.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.guild_id);
+    }
+}
+
+/// Données persistantes du composant
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct DataAutobahn {
+    /// Configuration d'Autobahn par serveur
+    #[serde(default)]
+    guilds: HashMap<GuildId, AutobahnConfig>,
+}
 
-    max_messages: usize,
-    max_time: chrono::Duration,
-    mute_time: chrono::Duration,
+#[serenity::async_trait]
+impl ComponentLifecycle for Autobahn {
+    async fn init(&self) -> Result<(), String> {
+        match PhishingList::load(DATA_DIR.join(PHISHING_FILE)) {
+            Ok(phishing) => *self.phishing.write().await = phishing,
+            Err(e) => log_warn!("autobahn: No phishing domain loaded: {}", e),
+        }
+        Ok(())
+    }
+    async fn on_shutdown(&self) {
+        if let Some(lockdowns) = self.lockdowns.write().await.as_mut() {
+            lockdowns.shutdown().await;
+        }
+        self.sent_messages.write().await.write().prune(Utc::now().timestamp());
+    }
+    async fn health(&self) -> Health {
+        match self.lockdowns.read().await.is_some() {
+            true => Health::Healthy,
+            false => Health::Degraded("les confinements en cours ne sont pas chargés".to_string()),
+        }
+    }
 }
+
 #[component]
+#[group(name="autobahn", description="Configuration de l'anti spam")]
+#[group(name="lockdown", description="Détection des raids et confinement du serveur")]
 impl Autobahn {
+    #[event(Ready)]
+    async fn on_ready(&self, ctx: &Context, _: &ReadyEvent) {
+        let mut lockdowns = self.lockdowns.write().await;
+        let persistent = (ctx.clone(), self.settings.clone());
+        match &mut *lockdowns {
+            Some(lockdowns) => lockdowns.reset_persistent(persistent),
+            None => {
+                let registry = match RegistryFile::from_file(DATA_DIR.join("lockdowns.json")).await {
+                    Ok(registry) => registry,
+                    Err(e) => {
+                        log_error!("autobahn: Failed to load lockdowns: {}", e);
+                        return;
+                    }
+                };
+                let mut new_lockdowns = task::TaskManager::new(registry, persistent);
+                new_lockdowns.init().await;
+                *lockdowns = Some(new_lockdowns);
+            }
+        }
+    }
+    #[event(GuildMemberAdd)]
+    async fn on_member_add(&self, ctx: &Context, event: &GuildMemberAddEvent) {
+        let member = &event.member;
+        if member.user.bot {
+            return;
+        }
+        let guild_id = member.guild_id;
+        let config = self.config(guild_id).await.raid;
+        if self.lockdown_task(guild_id).await.is_some() {
+            self.handle_joiner(ctx, guild_id, member.user.id, &config).await;
+            return;
+        }
+        if !config.enabled {
+            return;
+        }
+        let account_created = member.user.id.created_at().unix_timestamp();
+        if let Some(trigger) = self.raid.on_join(guild_id, account_created, Utc::now().timestamp(), &config).await {
+            if let Err(e) = self.engage_lockdown(ctx, guild_id, None, trigger.to_string(), config.lockdown_duration).await {
+                log_error!("autobahn: Failed to engage lockdown on {}: {}", guild_id, e);
+            }
+        }
+    }
     #[event(MessageCreate)]
     async fn on_message_create(&self, ctx: &Context, msg_create: &MessageCreateEvent) {
         let msg = &msg_create.message;
@@ -43,54 +167,683 @@ impl Autobahn {
                 return;
             },
         };
-        let msg_hash = hashers::fx_hash::fxhash64(msg_content.as_bytes());
-        log_info!("{} sent message, hash: {}", msg.author.name, msg_hash);
+        let config = self.config(guild_id).await;
+        let roles = msg.member.as_ref().map(|member| member.roles.as_slice()).unwrap_or_default();
+        if config.is_exempt(msg.channel_id, roles) {
+            return;
+        }
+        let now = Utc::now().timestamp();
+        let joined_at = msg.member.as_ref().and_then(|member| member.joined_at);
+        if let (true, Some(joined_at)) = (config.raid.enabled, joined_at) {
+            if let Some(trigger) = self.raid.on_message(guild_id, joined_at.unix_timestamp(), now, &config.raid).await {
+                if self.lockdown_task(guild_id).await.is_none() {
+                    if let Err(e) = self.engage_lockdown(ctx, guild_id, None, trigger.to_string(), config.raid.lockdown_duration).await {
+                        log_error!("autobahn: Failed to engage lockdown on {}: {}", guild_id, e);
+                    }
+                }
+            }
+        }
+        log_info!("{} sent message", msg.author.name);
 
+        let key = (guild_id, msg.author.id);
         let msg_info = MessageInfo {
-            time: chrono::Utc::now(),
-            who: (guild_id, msg.author.id),
-            id: (msg.channel_id, msg.id)
+            time: now,
+            id: (msg.channel_id, msg.id),
+            attachments: msg.attachments.len(),
+            fingerprint: Fingerprint::new(msg_content),
         };
+
+        if let Some(violation) = self.check_rules(ctx, msg, &key, &msg_info, &config).await {
+            if let Some(action) = config.rules.get(&violation.rule) {
+                self.apply_rule(ctx, msg, violation, *action, &config).await;
+                return;
+            }
+        }
         
-        self.remove_old_messages().await;
-        
-        let nb_found = self.sent_messages.read().await.iter()
-            .filter(|(k,v)| k == &msg_hash && v.who == msg_info.who)
-            .count()+1;
-        if nb_found > self.max_messages {
+        let detection = detection::detect(self.sent_messages.read().await.read(), &key, &msg_info, &config.thresholds());
+        if let Some(detection) = detection {
+            if config.dry_run {
+                let detail = match detection {
+                    Detection::Repeated(n) => ("Messages similaires", format!("{} en moins de {}", n, time::format_duration(config.max_time))),
+                    Detection::CrossChannel(n) => ("Salons différents", format!("{} en moins de {}", n, time::format_duration(config.max_time))),
+                };
+                self.report_dry_run(ctx, msg, &detection.to_string(), detail, format!("mute pendant {}", time::format_duration(config.mute_time))).await;
+                self.sent_messages.write().await.value.remove(&key);
+                return;
+            }
             match msg.delete(ctx).await {
                 Ok(_) => (),
                 Err(e) => println!("autobahn: Failed to delete messages: {}", e)
             }
-            if let Err(e) = self.cmp_moderation.mute(ctx, guild_id, msg.author.id, None, "Détection de spam".into(), Some(Utc::now() + self.mute_time)).await {
+            if let Err(e) = self.cmp_moderation.mute(ctx, guild_id, msg.author.id, None, detection.to_string(), Some(Utc::now() + chrono::Duration::seconds(config.mute_time as i64))).await {
                 log_error!("autobahn: Failed to mute user: {}", e);
                 return;
             };
-            self.delete_messages(ctx, |(_, msg)| msg.who == msg_info.who).await;
-            self.retain_messages(|(_,msg)| !(msg.who == msg_info.who)).await;
+            self.delete_messages(ctx, &key, now, config.max_time).await;
+            self.sent_messages.write().await.value.remove(&key);
         } else {
-            self.sent_messages.write().await.push((msg_hash, msg_info));
+            self.track_message(key, msg_info, config.max_time, config.history_capacity()).await;
         } 
     }
+    #[command(group="autobahn", name="afficher", description="Affiche la configuration de l'anti spam")]
+    async fn com_show(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>) {
+        let msg = match app_cmd.get_guild_id() {
+            Some(guild_id) => self.config(guild_id).await.to_message(),
+            None => message::error("Cette commande doit être executé sur un serveur."),
+        };
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="autobahn", name="seuils", description="Définit les seuils de détection et la durée du mute")]
+    async fn com_thresholds(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Nombre de messages identiques tolérés")]
+        messages: Option<u64>,
+        #[argument(description="Durée pendant laquelle les messages sont comparés")]
+        duree: Option<String>,
+        #[argument(description="Durée du mute")]
+        mute: Option<String>
+    ) {
+        let msg = self.edit(app_cmd.get_guild_id(), |config| {
+            let parse = |value: String| match time::parse(value) {
+                Ok(0) => Err("La durée doit être supérieure à 0".to_string()),
+                Ok(v) => Ok(v),
+                Err(e) => Err(format!("Impossible de parser la durée: {}", e)),
+            };
+            if let Some(messages) = messages {
+                if messages == 0 || messages as usize > history::MAX_THRESHOLD {
+                    return Err(format!("Le nombre de messages tolérés doit être compris entre 1 et {}", history::MAX_THRESHOLD));
+                }
+                config.max_messages = messages as usize;
+            }
+            if let Some(duree) = duree {
+                config.max_time = parse(duree)?;
+            }
+            if let Some(mute) = mute {
+                config.mute_time = parse(mute)?;
+            }
+            Ok(())
+        }).await.unwrap_or_else(message::error);
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="autobahn", name="similarite", description="Définit la similarité à partir de laquelle deux messages sont considérés identiques")]
+    async fn com_similarity(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Similarité en pourcentage, de 1 à 100")]
+        pourcentage: u64
+    ) {
+        let msg = self.edit(app_cmd.get_guild_id(), |config| {
+            if !(1..=100).contains(&pourcentage) {
+                return Err("La similarité doit être comprise entre 1 et 100".to_string());
+            }
+            config.similarity = pourcentage as u8;
+            Ok(())
+        }).await.unwrap_or_else(message::error);
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="autobahn", name="salons", description="Définit le nombre de salons dans lesquels un même message est considéré comme du spam")]
+    async fn com_max_channels(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Nombre de salons différents. 0 pour désactiver")]
+        nombre: u64
+    ) {
+        let msg = self.edit(app_cmd.get_guild_id(), |config| {
+            if nombre as usize > history::MAX_THRESHOLD {
+                return Err(format!("Le nombre de salons doit être compris entre 0 et {}", history::MAX_THRESHOLD));
+            }
+            config.max_channels = nombre as usize;
+            Ok(())
+        }).await.unwrap_or_else(message::error);
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="autobahn", name="regle", description="Définit la sanction d'une règle de filtrage")]
+    async fn com_rule(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Règle (invitations, mentions, phishing, fichiers ou murs)")]
+        regle: String,
+        #[argument(description="Sanction (suppression, warn, mute, kick, ban) ou aucune pour désactiver la règle")]
+        sanction: String
+    ) {
+        let msg = self.edit(app_cmd.get_guild_id(), |config| {
+            let rule = Rule::parse(&regle)?;
+            if sanction.eq_ignore_ascii_case("aucune") {
+                config.rules.remove(&rule);
+            } else {
+                config.rules.insert(rule, RuleAction::parse(&sanction)?);
+            }
+            Ok(())
+        }).await.unwrap_or_else(message::error);
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="autobahn", name="limites", description="Définit les limites des règles de filtrage")]
+    async fn com_limits(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Nombre de mentions tolérées dans un message")]
+        mentions: Option<u64>,
+        #[argument(description="Nombre de fichiers tolérés pendant la fenêtre de détection")]
+        fichiers: Option<u64>,
+        #[argument(description="Nombre d'emojis tolérés dans un message")]
+        emojis: Option<u64>
+    ) {
+        let msg = self.edit(app_cmd.get_guild_id(), |config| {
+            if let Some(mentions) = mentions {
+                config.max_mentions = mentions as usize;
+            }
+            if let Some(fichiers) = fichiers {
+                if fichiers as usize > history::MAX_THRESHOLD {
+                    return Err(format!("Le nombre de fichiers tolérés doit être compris entre 0 et {}", history::MAX_THRESHOLD));
+                }
+                config.max_attachments = fichiers as usize;
+            }
+            if let Some(emojis) = emojis {
+                config.max_emojis = emojis as usize;
+            }
+            Ok(())
+        }).await.unwrap_or_else(message::error);
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="autobahn", name="phishing_recharger", description="Recharge la liste des domaines de phishing")]
+    async fn com_reload_phishing(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>) {
+        let msg = match PhishingList::load(DATA_DIR.join(PHISHING_FILE)) {
+            Ok(list) => {
+                let count = list.count();
+                *self.phishing.write().await = list;
+                message::success(format!("{} domaine(s) de phishing chargé(s)", count))
+            },
+            Err(e) => message::error(e),
+        };
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="autobahn", name="exemption_role", description="Ajoute ou retire un rôle des exemptions")]
+    async fn com_exempt_role(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Rôle dont les membres ne sont pas surveillés")]
+        role: RoleId
+    ) {
+        let msg = self.edit(app_cmd.get_guild_id(), |config| {
+            AutobahnConfig::toggle(&mut config.exempt_roles, role);
+            Ok(())
+        }).await.unwrap_or_else(message::error);
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="autobahn", name="exemption_salon", description="Ajoute ou retire un salon des exemptions")]
+    async fn com_exempt_channel(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Salon non surveillé")]
+        salon: ChannelId
+    ) {
+        let msg = self.edit(app_cmd.get_guild_id(), |config| {
+            AutobahnConfig::toggle(&mut config.exempt_channels, salon);
+            Ok(())
+        }).await.unwrap_or_else(message::error);
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="autobahn", name="simulation", description="Signale les détections dans le salon des logs sans sanctionner")]
+    async fn com_dry_run(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Activer le mode simulation ?")]
+        actif: bool
+    ) {
+        let msg = self.edit(app_cmd.get_guild_id(), |config| {
+            config.dry_run = actif;
+            Ok(())
+        }).await.unwrap_or_else(message::error);
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="autobahn", name="statistiques", description="Affiche l'occupation de l'historique des messages")]
+    async fn com_stats(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>) {
+        let stats = self.sent_messages.read().await.read().stats(Utc::now().timestamp());
+        let mut msg = message::Message::new();
+        msg.add_embed(|embed| {
+            embed
+                .title("Historique d'Autobahn")
+                .description("Messages récents conservés pour la détection, tous serveurs confondus.")
+                .color(message::COLOR_INFO)
+                .field("Membres suivis", format!("{} / {}", stats.users, history::MAX_USERS), true)
+                .field("Messages conservés", stats.entries.to_string(), true)
+                .field("Messages remplacés", stats.dropped.to_string(), true)
+                .field("Membres oubliés", stats.evicted.to_string(), true)
+        });
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="lockdown", name="afficher", description="Affiche la configuration de la détection des raids et l'état du confinement")]
+    async fn com_lockdown_show(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>) {
+        let msg = match app_cmd.get_guild_id() {
+            Some(guild_id) => {
+                let mut msg = self.config(guild_id).await.raid.to_message();
+                let status = match self.lockdown_task(guild_id).await {
+                    Some((_, task)) => format!("Serveur confiné jusqu'au <t:{}:f>\n**Motif** : {}", task.until, task.data.reason),
+                    None => "Serveur non confiné".to_string(),
+                };
+                msg.add_embed(|embed| {
+                    embed
+                        .title("Confinement")
+                        .color(message::COLOR_INFO)
+                        .description(status)
+                });
+                msg
+            },
+            None => message::error("Cette commande doit être executé sur un serveur."),
+        };
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="lockdown", name="on", description="Confine le serveur")]
+    async fn com_lockdown_on(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Durée du confinement. Durée configurée si absente")]
+        duree: Option<String>
+    ) {
+        let resp = match app_cmd.delayed_response(ctx, false).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                log_error!("{}", e);
+                return;
+            }
+        };
+        let msg = match app_cmd.get_guild_id() {
+            Some(guild_id) => {
+                let duration = match duree.map(time::parse) {
+                    Some(Ok(0)) => Err("La durée doit être supérieure à 0".to_string()),
+                    Some(Ok(duration)) => Ok(duration),
+                    Some(Err(e)) => Err(format!("Impossible de parser la durée: {}", e)),
+                    None => Ok(self.config(guild_id).await.raid.lockdown_duration),
+                };
+                match duration {
+                    Ok(duration) => self.engage_lockdown(ctx, guild_id, Some(app_cmd.0.user.id), "Confinement manuel".to_string(), duration).await,
+                    Err(e) => Err(e),
+                }.unwrap_or_else(message::error)
+            },
+            None => message::error("Cette commande doit être executé sur un serveur."),
+        };
+        if let Err(e) = resp.send_message(msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="lockdown", name="off", description="Lève le confinement du serveur")]
+    async fn com_lockdown_off(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>) {
+        let resp = match app_cmd.delayed_response(ctx, false).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                log_error!("{}", e);
+                return;
+            }
+        };
+        let msg = match app_cmd.get_guild_id() {
+            Some(guild_id) => self.release_lockdown(ctx, guild_id, app_cmd.0.user.id).await.unwrap_or_else(message::error),
+            None => message::error("Cette commande doit être executé sur un serveur."),
+        };
+        if let Err(e) = resp.send_message(msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="lockdown", name="detection", description="Active le confinement automatique lorsqu'un raid est détecté")]
+    async fn com_lockdown_detection(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Activer la détection des raids ?")]
+        actif: bool
+    ) {
+        let msg = self.edit_raid(app_cmd.get_guild_id(), |config| {
+            config.enabled = actif;
+            Ok(())
+        }).await.unwrap_or_else(message::error);
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="lockdown", name="seuils", description="Définit les seuils de détection des raids")]
+    async fn com_lockdown_thresholds(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Nombre d'arrivées tolérées pendant la fenêtre de détection")]
+        arrivees: Option<u64>,
+        #[argument(description="Nombre d'arrivées de comptes récents tolérées pendant la fenêtre de détection")]
+        nouveaux_comptes: Option<u64>,
+        #[argument(description="Nombre de messages des nouveaux membres tolérés pendant la fenêtre de détection")]
+        messages: Option<u64>,
+        #[argument(description="Durée de la fenêtre de détection")]
+        fenetre: Option<String>
+    ) {
+        let msg = self.edit_raid(app_cmd.get_guild_id(), |config| {
+            if let Some(arrivees) = arrivees {
+                config.max_joins = arrivees as usize;
+            }
+            if let Some(nouveaux_comptes) = nouveaux_comptes {
+                config.max_new_accounts = nouveaux_comptes as usize;
+            }
+            if let Some(messages) = messages {
+                config.max_burst = messages as usize;
+            }
+            if let Some(fenetre) = fenetre {
+                config.window = match time::parse(fenetre) {
+                    Ok(0) => return Err("La durée doit être supérieure à 0".to_string()),
+                    Ok(window) => window,
+                    Err(e) => return Err(format!("Impossible de parser la durée: {}", e)),
+                };
+            }
+            Ok(())
+        }).await.unwrap_or_else(message::error);
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="lockdown", name="age_compte", description="Définit l'ancienneté en dessous de laquelle un compte est considéré comme récent")]
+    async fn com_lockdown_account_age(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Ancienneté du compte")]
+        duree: String
+    ) {
+        let msg = self.edit_raid(app_cmd.get_guild_id(), |config| {
+            config.account_age = time::parse(duree).map_err(|e| format!("Impossible de parser la durée: {}", e))?;
+            Ok(())
+        }).await.unwrap_or_else(message::error);
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="lockdown", name="duree", description="Définit la durée par défaut du confinement")]
+    async fn com_lockdown_duration(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Durée du confinement")]
+        duree: String
+    ) {
+        let msg = self.edit_raid(app_cmd.get_guild_id(), |config| {
+            config.lockdown_duration = match time::parse(duree) {
+                Ok(0) => return Err("La durée doit être supérieure à 0".to_string()),
+                Ok(duration) => duration,
+                Err(e) => return Err(format!("Impossible de parser la durée: {}", e)),
+            };
+            Ok(())
+        }).await.unwrap_or_else(message::error);
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="lockdown", name="salon", description="Ajoute ou retire un salon bloqué pendant le confinement")]
+    async fn com_lockdown_channel(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Salon dont l'envoi de messages est bloqué")]
+        salon: ChannelId
+    ) {
+        let msg = self.edit_raid(app_cmd.get_guild_id(), |config| {
+            AutobahnConfig::toggle(&mut config.channels, salon);
+            Ok(())
+        }).await.unwrap_or_else(message::error);
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="lockdown", name="arrivants", description="Définit le traitement des membres arrivant pendant le confinement")]
+    async fn com_lockdown_joiners(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Action (ignorer, kick ou quarantaine)")]
+        action: String,
+        #[argument(description="Rôle de quarantaine")]
+        role: Option<RoleId>
+    ) {
+        let msg = self.edit_raid(app_cmd.get_guild_id(), |config| {
+            let action = JoinerAction::parse(&action)?;
+            if let Some(role) = role {
+                config.quarantine_role = Some(role);
+            }
+            if action == JoinerAction::Quarantine && config.quarantine_role.is_none() {
+                return Err("Un rôle de quarantaine est nécessaire".to_string());
+            }
+            config.joiners = action;
+            Ok(())
+        }).await.unwrap_or_else(message::error);
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
 }
 
 impl Autobahn {
-    pub fn new(cmp_moderation: Arc<Moderation>) -> Autobahn {
+    pub fn new(cmp_moderation: Arc<Moderation>, settings: Arc<Settings>) -> Autobahn {
         Autobahn {
-            sent_messages: RwLock::new(Vec::with_capacity(100)),
+            sent_messages: RwLock::new(Self::load_history()),
+            last_save: AtomicI64::new(Utc::now().timestamp()),
             cmp_moderation,
-            max_messages: 4,
-            max_time: chrono::Duration::seconds(20),
-            mute_time: chrono::Duration::days(1),
+            settings,
+            data: RwLock::new(Data::from_file("autobahn").unwrap()),
+            phishing: RwLock::new(PhishingList::default()),
+            invites: RwLock::new(InviteCache::default()),
+            raid: RaidDetector::default(),
+            lockdowns: RwLock::new(None),
+            engaging: Mutex::new(HashSet::new()),
         }
     }
-    async fn delete_messages<F>(&self, ctx: &Context, filter: F)
-        where F: Fn(&&(MessageHash, MessageInfo)) -> bool
+    /// Première règle de filtrage activée que le message ne respecte pas.
+    async fn check_rules(&self, ctx: &Context, msg: &Message, key: &MessageKey, msg_info: &MessageInfo, config: &AutobahnConfig) -> Option<Violation> {
+        let enabled = |rule| config.rules.contains_key(&rule);
+        if enabled(Rule::Phishing) {
+            if let Some(domain) = self.phishing.read().await.find(&msg.content) {
+                return Some(Violation::new(Rule::Phishing, domain));
+            }
+        }
+        if enabled(Rule::Mentions) {
+            let mentions = rules::count_mentions(msg);
+            if mentions > config.max_mentions {
+                return Some(Violation::new(Rule::Mentions, format!("{} mentions", mentions)));
+            }
+        }
+        if enabled(Rule::Wall) {
+            let emojis = rules::count_emojis(&msg.content);
+            if emojis > config.max_emojis {
+                return Some(Violation::new(Rule::Wall, format!("{} emojis", emojis)));
+            }
+            if rules::is_zalgo(&msg.content) {
+                return Some(Violation::new(Rule::Wall, "texte zalgo"));
+            }
+        }
+        if enabled(Rule::Attachments) && msg_info.attachments > 0 {
+            let attachments = self.sent_messages.read().await.read()
+                .entries(key, msg_info.time, config.max_time)
+                .map(|v| v.attachments)
+                .sum::<usize>() + msg_info.attachments;
+            if attachments > config.max_attachments {
+                return Some(Violation::new(Rule::Attachments, format!("{} fichiers en moins de {}", attachments, time::format_duration(config.max_time))));
+            }
+        }
+        if enabled(Rule::Invite) {
+            let mut resolved = 0;
+            for code in rules::invite_codes(&msg.content) {
+                let cached = self.invites.read().await.get(&code, msg_info.time);
+                let target = match cached {
+                    Some(target) => target,
+                    None if resolved >= rules::MAX_RESOLVED_INVITES => continue,
+                    None => {
+                        resolved += 1;
+                        let target = match invite::Invite::get(ctx, &code, false, false, None).await {
+                            Ok(invite) => match invite.guild {
+                                Some(guild) => InviteTarget::Guild(guild.id),
+                                None => InviteTarget::Group,
+                            },
+                            Err(e) => {
+                                log_warn!("autobahn: Failed to resolve invite {}: {}", code, e);
+                                InviteTarget::Invalid
+                            }
+                        };
+                        self.invites.write().await.insert(code.clone(), target, msg_info.time);
+                        target
+                    },
+                };
+                if target.is_foreign(key.0) {
+                    return Some(Violation::new(Rule::Invite, format!("discord.gg/{}", code)));
+                }
+            }
+        }
+        None
+    }
+    /// Supprime le message et applique la sanction de la règle non respectée.
+    async fn apply_rule(&self, ctx: &Context, msg: &Message, violation: Violation, action: RuleAction, config: &AutobahnConfig) {
+        let guild_id = match msg.guild_id {
+            Some(guild_id) => guild_id,
+            None => return,
+        };
+        if config.dry_run {
+            let detail = ("Règle", format!("{} ({})", violation.rule.name(), violation.detail));
+            self.report_dry_run(ctx, msg, violation.rule.reason(), detail, action.describe(config.mute_time)).await;
+            return;
+        }
+        if let Err(e) = msg.delete(ctx).await {
+            log_warn!("autobahn: Failed to delete message: {}", e);
+        }
+        let reason = violation.rule.reason().to_string();
+        let user_id = msg.author.id;
+        let result = match action {
+            RuleAction::Delete => return,
+            RuleAction::Warn => self.cmp_moderation.warn(ctx, guild_id, user_id, None, reason).await,
+            RuleAction::Mute => self.cmp_moderation.mute(ctx, guild_id, user_id, None, reason, Some(Utc::now() + chrono::Duration::seconds(config.mute_time as i64))).await,
+            RuleAction::Kick => self.cmp_moderation.kick(ctx, guild_id, user_id, None, reason).await,
+            RuleAction::Ban => self.cmp_moderation.ban(ctx, guild_id, user_id, None, reason, None, None).await,
+        };
+        if let Err(e) = result {
+            log_error!("autobahn: Failed to {} user: {}", action, e);
+        }
+    }
+    /// Configuration d'Autobahn du serveur, ou celle par défaut.
+    async fn config(&self, guild_id: GuildId) -> AutobahnConfig {
+        self.data.read().await.read().guilds.get(&guild_id).cloned().unwrap_or_default()
+    }
+    /// Modifie la configuration d'un serveur puis la retourne.
+    async fn update_config<F>(&self, guild_id: Option<GuildId>, f: F) -> Result<AutobahnConfig, String>
+        where F: FnOnce(&mut AutobahnConfig) -> Result<(), String>
     {
+        let guild_id = guild_id.ok_or_else(|| "Cette commande doit être executé sur un serveur.".to_string())?;
+        let mut config = self.config(guild_id).await;
+        f(&mut config)?;
+        self.data.write().await.write().guilds.insert(guild_id, config.clone());
+        Ok(config)
+    }
+    /// Modifie la configuration d'un serveur puis l'affiche.
+    async fn edit<F>(&self, guild_id: Option<GuildId>, f: F) -> Result<message::Message, String>
+        where F: FnOnce(&mut AutobahnConfig) -> Result<(), String>
+    {
+        self.update_config(guild_id, f).await.map(|config| config.to_message())
+    }
+    /// Modifie la configuration de la détection des raids d'un serveur puis l'affiche.
+    async fn edit_raid<F>(&self, guild_id: Option<GuildId>, f: F) -> Result<message::Message, String>
+        where F: FnOnce(&mut RaidConfig) -> Result<(), String>
+    {
+        self.update_config(guild_id, |config| f(&mut config.raid)).await.map(|config| config.raid.to_message())
+    }
+    /// Tâche de levée du confinement en cours du serveur.
+    async fn lockdown_task(&self, guild_id: GuildId) -> Option<(task::TaskID, task::Task<Lockdown>)> {
+        let lockdowns = self.lockdowns.read().await;
+        let registry = lockdowns.as_ref()?.registry();
+        let registry = registry.lock().await;
+        registry.find_one(|task| task.data.guild_id == guild_id).await
+    }
+    /// Réserve le serveur pour un nouveau confinement, s'il n'est pas déjà confiné ou en cours de confinement.
+    async fn reserve_lockdown(&self, guild_id: GuildId) -> Result<LockdownReservation<'_>, String> {
+        let lockdowns = self.lockdowns.write().await;
+        let lockdowns = lockdowns.as_ref().ok_or_else(|| "Le gestionnaire des confinements n'est pas prêt.".to_string())?;
+        let registry = lockdowns.registry();
+        let engaged = registry.lock().await.find_one(|task| task.data.guild_id == guild_id).await.is_some();
+        if engaged || !self.engaging.lock().unwrap_or_else(|e| e.into_inner()).insert(guild_id) {
+            return Err("Le serveur est déjà confiné.".to_string());
+        }
+        Ok(LockdownReservation { engaging: &self.engaging, guild_id })
+    }
+    /// Confine le serveur pendant `duration` secondes et prévient le staff.
+    async fn engage_lockdown(&self, ctx: &Context, guild_id: GuildId, user_by: Option<UserId>, reason: String, duration: u64) -> Result<message::Message, String> {
+        // La réservation empêche un second confinement tant que la tâche de levée n'est pas planifiée
+        let _reservation = self.reserve_lockdown(guild_id).await?;
+        let config = self.config(guild_id).await.raid;
+        let until = Utc::now() + chrono::Duration::seconds(duration as i64);
+        let lockdown = Lockdown::engage(ctx, guild_id, &config, reason.clone()).await?;
+        let locked = lockdown.channels.len();
+        {
+            let mut lockdowns = self.lockdowns.write().await;
+            let lockdowns = lockdowns.as_mut().ok_or_else(|| "Le gestionnaire des confinements n'est pas prêt.".to_string())?;
+            if let Err(e) = lockdowns.add(lockdown.clone(), until).await {
+                lockdown.release(ctx).await;
+                return Err(format!("Impossible de planifier la fin du confinement: {}", e));
+            }
+        }
+        self.raid.reset(guild_id).await;
+        let by = match user_by {
+            Some(user_by) => format!("Confinement déclenché par <@{}>", user_by),
+            None => "Confinement déclenché automatiquement".to_string(),
+        };
+        let description = format!("{}.\n**Motif** : {}\n**Salons bloqués** : {}\n**Fin** : <t:{}:R>", by, reason, locked, until.timestamp());
+        Lockdown::notify(ctx, &self.settings, guild_id, "🔒 Confinement du serveur", description).await;
+        Ok(message::success(format!("Serveur confiné jusqu'au <t:{}:f> ({} salon(s) bloqué(s))", until.timestamp(), locked)))
+    }
+    /// Lève le confinement en cours du serveur et prévient le staff.
+    async fn release_lockdown(&self, ctx: &Context, guild_id: GuildId, user_by: UserId) -> Result<message::Message, String> {
+        let (id, task) = self.lockdown_task(guild_id).await
+            .ok_or_else(|| "Le serveur n'est pas confiné.".to_string())?;
+        {
+            let mut lockdowns = self.lockdowns.write().await;
+            let lockdowns = lockdowns.as_mut().ok_or_else(|| "Le gestionnaire des confinements n'est pas prêt.".to_string())?;
+            lockdowns.remove(id).await
+                .map_err(|e| format!("Impossible de retirer le confinement: {}", e))?;
+        }
+        task.data.release(ctx).await;
+        Lockdown::notify(ctx, &self.settings, guild_id, "🔓 Fin du confinement", format!("Confinement levé par <@{}>.", user_by)).await;
+        Ok(message::success("Le confinement du serveur a été levé."))
+    }
+    /// Applique l'action configurée à un membre arrivé pendant le confinement.
+    async fn handle_joiner(&self, ctx: &Context, guild_id: GuildId, user_id: UserId, config: &RaidConfig) {
+        let result = match (config.joiners, config.quarantine_role) {
+            (JoinerAction::Ignore, _) => Ok(()),
+            (JoinerAction::Kick, _) => self.cmp_moderation.kick(ctx, guild_id, user_id, None, "Confinement du serveur".into()).await.map(|_| ()),
+            (JoinerAction::Quarantine, Some(role)) => ctx.http.add_member_role(guild_id.0, user_id.0, role.0, Some("Confinement du serveur")).await
+                .map_err(|e| e.to_string()),
+            (JoinerAction::Quarantine, None) => Err("aucun rôle de quarantaine défini".to_string()),
+        };
+        if let Err(e) = result {
+            log_warn!("autobahn: Failed to handle new member {} during lockdown of {}: {}", user_id, guild_id, e);
+        }
+    }
+    /// Signale une détection dans le salon des logs sans sanctionner l'auteur.
+    async fn report_dry_run(&self, ctx: &Context, msg: &Message, reason: &str, detail: (&str, String), planned: String) {
+        let guild_id = match msg.guild_id {
+            Some(guild_id) => guild_id,
+            None => return,
+        };
+        let channel = match self.settings.log_channel(guild_id).await {
+            Some(channel) => channel,
+            None => {
+                log_warn!("autobahn: Spam detected in simulation mode but no log channel is set on guild {}", guild_id);
+                return;
+            }
+        };
+        let content = if msg.content.chars().count() > 1000 {
+            format!("{}…", msg.content.chars().take(999).collect::<String>())
+        } else {
+            msg.content.clone()
+        };
+        let result = channel.send_message(ctx, |create_msg| {
+            create_msg.embed(|embed| {
+                embed
+                    .title(format!("Autobahn : {} (simulation)", reason.to_lowercase()))
+                    .color(message::COLOR_WARN)
+                    .description(format!("Aucune sanction n'a été appliquée. [Aller au message]({})", msg.link()))
+                    .field("Membre", format!("<@{}>", msg.author.id), true)
+                    .field("Salon", format!("<#{}>", msg.channel_id), true)
+                    .field(detail.0, detail.1, true)
+                    .field("Sanction prévue", planned, true)
+                    .field("Contenu", if content.is_empty() { "*Vide*".to_string() } else { content }, false)
+            })
+        }).await;
+        if let Err(e) = result {
+            log_warn!("autobahn: Failed to send dry run report to {}: {}", channel, e);
+        }
+    }
+    async fn delete_messages(&self, ctx: &Context, key: &MessageKey, now: i64, window: u64) {
         let mut msg_to_delete: HashMap<ChannelId, Vec<MessageId>> = HashMap::new();
-        self.sent_messages.read().await.iter()
-            .filter(filter)
-            .for_each(|(_, msg)| {
+        self.sent_messages.read().await.read()
+            .entries(key, now, window)
+            .for_each(|msg| {
                 msg_to_delete.entry(msg.id.0)
                     .or_insert_with(Vec::new)
                     .push(msg.id.1);
@@ -103,15 +856,114 @@ impl Autobahn {
             }
         }
     }
-    #[inline]
-    async fn retain_messages<F>(&self,filter: F)
-        where F: Fn(&(MessageHash, MessageInfo)) -> bool
-    {
-        let mut sent_messages = self.sent_messages.write().await;
-        sent_messages.retain(filter);
+    /// Charge l'historique des messages enregistré, sans les membres inactifs.
+    fn load_history() -> Data<MessageHistory> {
+        let mut history = Data::from_file("autobahn_history").unwrap_or_else(|e| {
+            log_warn!("autobahn: Failed to load message history: {:?}", e);
+            Data::new("autobahn_history", MessageHistory::default())
+        });
+        history.value.prune(Utc::now().timestamp());
+        history
+    }
+    /// Ajoute un message à l'historique.
+    ///
+    /// L'historique n'est modifié qu'en mémoire, puis nettoyé et enregistré
+    /// si le dernier enregistrement date de plus de [`SAVE_INTERVAL`] secondes.
+    /// L'historique est copié sous le verrou, puis sérialisé et écrit après l'avoir relâché.
+    async fn track_message(&self, key: MessageKey, msg_info: MessageInfo, window: u64, capacity: usize) {
+        let now = msg_info.time;
+        let last_save = self.last_save.load(Ordering::Relaxed);
+        let save = now - last_save >= SAVE_INTERVAL
+            && self.last_save.compare_exchange(last_save, now, Ordering::Relaxed, Ordering::Relaxed).is_ok();
+        {
+            let mut sent_messages = self.sent_messages.write().await;
+            sent_messages.value.push(key, msg_info, window, capacity);
+            if save {
+                sent_messages.value.prune(now);
+            }
+        }
+        if !save {
+            return;
+        }
+        let snapshot = self.sent_messages.read().await.clone();
+        let result = tokio::task::spawn_blocking(move || data::write_file(&snapshot.name, &snapshot.to_json()?)).await
+            .unwrap_or_else(|e| Err(DataError::FileError(e.into())));
+        if let Err(e) = result {
+            log_warn!("autobahn: Failed to save message history: {:?}", e);
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cddio_core::testing::{Harness, fixtures::{self, CommandBuilder}};
+
+    fn autobahn() -> Autobahn {
+        let settings = Arc::new(Settings::new(Vec::new()));
+        Autobahn::new(Arc::new(Moderation::new(Arc::clone(&settings))), settings)
     }
-    #[inline]
-    async fn remove_old_messages(&self) {
-        self.retain_messages(|(_,v)| Utc::now()-v.time < self.max_time).await;
+
+    #[tokio::test]
+    async fn show_displays_the_guild_config() {
+        let harness = Harness::new().await.unwrap();
+        harness.command(&autobahn(), CommandBuilder::new("autobahn").subcommand("afficher")).await;
+
+        let responses = harness.mock.find("POST", "/interactions/*/*/callback");
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].body["data"]["embeds"][0]["title"], "Configuration d'Autobahn");
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn lockdown_is_reserved_once_per_guild() {
+        let harness = Harness::new().await.unwrap();
+        let autobahn = autobahn();
+        let guild_id = GuildId(fixtures::GUILD_ID);
+        assert!(autobahn.reserve_lockdown(guild_id).await.is_err());
+
+        let registry = RegistryFile::from_file(DATA_DIR.join("lockdowns.json")).await.unwrap();
+        let persistent = (harness.context().clone(), Arc::clone(&autobahn.settings));
+        *autobahn.lockdowns.write().await = Some(task::TaskManager::new(registry, persistent));
+
+        let reservation = autobahn.reserve_lockdown(guild_id).await.unwrap();
+        assert!(autobahn.reserve_lockdown(guild_id).await.is_err());
+        assert!(autobahn.reserve_lockdown(GuildId(fixtures::GUILD_ID + 1)).await.is_ok());
+        drop(reservation);
+        assert!(autobahn.reserve_lockdown(guild_id).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn invite_lookups_are_capped_and_cached() {
+        let harness = Harness::new().await.unwrap();
+        let autobahn = autobahn();
+        let mut config = AutobahnConfig::default();
+        config.rules.insert(Rule::Invite, RuleAction::Delete);
+        let content = "discord.gg/a1 discord.gg/b2 discord.gg/c3 discord.gg/d4 discord.gg/e5";
+        let msg: Message = serde_json::from_value(fixtures::message(fixtures::next_id(), fixtures::CHANNEL_ID, fixtures::USER_ID, content)).unwrap();
+        let key = (GuildId(fixtures::GUILD_ID), msg.author.id);
+        let msg_info = MessageInfo {
+            time: Utc::now().timestamp(),
+            id: (msg.channel_id, msg.id),
+            attachments: 0,
+            fingerprint: Fingerprint::new(content),
+        };
+
+        // Les invitations inconnues ne sont pas sanctionnées
+        assert!(autobahn.check_rules(harness.context(), &msg, &key, &msg_info, &config).await.is_none());
+        assert_eq!(harness.mock.find("GET", "/invites/*").len(), rules::MAX_RESOLVED_INVITES);
+        // Les échecs sont mis en cache : seules les invitations restantes sont résolues
+        assert!(autobahn.check_rules(harness.context(), &msg, &key, &msg_info, &config).await.is_none());
+        assert_eq!(harness.mock.find("GET", "/invites/*").len(), 5);
+        assert!(autobahn.check_rules(harness.context(), &msg, &key, &msg_info, &config).await.is_none());
+        assert_eq!(harness.mock.find("GET", "/invites/*").len(), 5);
+    }
+
+    #[tokio::test]
+    async fn show_requires_a_guild() {
+        let harness = Harness::new().await.unwrap();
+        harness.command(&autobahn(), CommandBuilder::new("autobahn").subcommand("afficher").guild(None)).await;
+
+        let responses = harness.mock.find("POST", "/interactions/*/*/callback");
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].body["data"]["embeds"][0]["title"], "Erreur");
+    }
+}
@@ -0,0 +1,299 @@
+//! Détection des raids
+//!
+//! Contrairement au reste d'Autobahn, la détection porte sur l'ensemble du serveur :
+//! nombre d'arrivées, arrivées de comptes récents et rafales de messages envoyés par
+//! les nouveaux membres pendant la fenêtre de détection.
+
+use std::{collections::HashMap, fmt};
+use cddio_core::message;
+use futures_locks::RwLock;
+use serde::{Deserialize, Serialize};
+use serenity::model::id::*;
+
+use super::super::utils::time_parser as time;
+
+/// Ancienneté sur le serveur en dessous de laquelle les messages d'un membre comptent dans les rafales
+const RECENT_MEMBER: i64 = 24 * 60 * 60;
+
+/// Traitement des membres arrivant pendant un confinement
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinerAction {
+    /// Aucune action
+    Ignore,
+    /// Expulsion du membre
+    Kick,
+    /// Attribution du rôle de quarantaine
+    Quarantine,
+}
+
+impl JoinerAction {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "ignorer" => Ok(JoinerAction::Ignore),
+            "kick" => Ok(JoinerAction::Kick),
+            "quarantaine" => Ok(JoinerAction::Quarantine),
+            _ => Err(format!("Action \"{}\" inconnue, attendue: ignorer, kick, quarantaine", name)),
+        }
+    }
+}
+
+impl fmt::Display for JoinerAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JoinerAction::Ignore => write!(f, "ignorer"),
+            JoinerAction::Kick => write!(f, "kick"),
+            JoinerAction::Quarantine => write!(f, "quarantaine"),
+        }
+    }
+}
+
+/// Configuration de la détection des raids et du confinement d'un serveur
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct RaidConfig {
+    /// Confine automatiquement le serveur lorsqu'un raid est détecté
+    pub enabled: bool,
+    /// Durée de la fenêtre de détection en secondes
+    pub window: u64,
+    /// Nombre d'arrivées tolérées pendant la fenêtre de détection
+    pub max_joins: usize,
+    /// Ancienneté en secondes en dessous de laquelle un compte est considéré comme récent
+    pub account_age: u64,
+    /// Nombre d'arrivées de comptes récents tolérées pendant la fenêtre de détection
+    pub max_new_accounts: usize,
+    /// Nombre de messages des nouveaux membres tolérés pendant la fenêtre de détection
+    pub max_burst: usize,
+    /// Durée par défaut du confinement en secondes
+    pub lockdown_duration: u64,
+    /// Salons dont l'envoi de messages est bloqué pendant le confinement
+    pub channels: Vec<ChannelId>,
+    /// Traitement des membres arrivant pendant le confinement
+    pub joiners: JoinerAction,
+    /// Rôle attribué aux membres arrivant pendant le confinement
+    pub quarantine_role: Option<RoleId>,
+}
+
+impl Default for RaidConfig {
+    fn default() -> Self {
+        RaidConfig {
+            enabled: false,
+            window: 60,
+            max_joins: 10,
+            account_age: 7 * 24 * 60 * 60,
+            max_new_accounts: 5,
+            max_burst: 40,
+            lockdown_duration: 60 * 60,
+            channels: Vec::new(),
+            joiners: JoinerAction::Ignore,
+            quarantine_role: None,
+        }
+    }
+}
+
+impl RaidConfig {
+    pub fn to_message(&self) -> message::Message {
+        let channels = match self.channels.is_empty() {
+            true => "*Aucun*".to_string(),
+            false => self.channels.iter().map(|channel| format!("<#{}>", channel)).collect::<Vec<_>>().join(", "),
+        };
+        let joiners = match (self.joiners, self.quarantine_role) {
+            (JoinerAction::Quarantine, Some(role)) => format!("quarantaine avec <@&{}>", role),
+            (JoinerAction::Quarantine, None) => "quarantaine (*aucun rôle défini*)".to_string(),
+            (action, _) => action.to_string(),
+        };
+        let mut msg = message::Message::new();
+        msg.add_embed(|embed| {
+            embed
+                .title("Détection des raids")
+                .color(message::COLOR_INFO)
+                .field("Confinement automatique", if self.enabled { "Activé" } else { "Désactivé" }, true)
+                .field("Fenêtre de détection", time::format_duration(self.window), true)
+                .field("Durée du confinement", time::format_duration(self.lockdown_duration), true)
+                .field("Seuils", format!(
+                    "{} arrivées\n{} arrivées de comptes de moins de {}\n{} messages des nouveaux membres",
+                    self.max_joins, self.max_new_accounts, time::format_duration(self.account_age), self.max_burst
+                ), false)
+                .field("Salons bloqués", channels, false)
+                .field("Nouveaux arrivants", joiners, true)
+        });
+        msg
+    }
+}
+
+/// Motif de déclenchement du confinement
+#[derive(Debug, Clone, Copy)]
+pub enum RaidTrigger {
+    /// Nombre d'arrivées pendant la fenêtre de détection
+    Joins(usize),
+    /// Nombre d'arrivées de comptes récents pendant la fenêtre de détection
+    NewAccounts(usize),
+    /// Nombre de messages des nouveaux membres pendant la fenêtre de détection
+    Burst(usize),
+}
+
+impl fmt::Display for RaidTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RaidTrigger::Joins(n) => write!(f, "Raid détecté : {} arrivées", n),
+            RaidTrigger::NewAccounts(n) => write!(f, "Raid détecté : {} arrivées de comptes récents", n),
+            RaidTrigger::Burst(n) => write!(f, "Raid détecté : {} messages des nouveaux membres", n),
+        }
+    }
+}
+
+/// Arrivée d'un membre
+#[derive(Debug, Clone, Copy)]
+struct Join {
+    /// Date d'arrivée (timestamp unix)
+    time: i64,
+    /// Le compte est récent
+    new_account: bool,
+}
+
+/// Historique récent des arrivées et messages de chaque serveur
+#[derive(Default)]
+pub struct RaidDetector {
+    joins: RwLock<HashMap<GuildId, Vec<Join>>>,
+    messages: RwLock<HashMap<GuildId, Vec<i64>>>,
+}
+
+impl RaidDetector {
+    /// Enregistre l'arrivée à `now` d'un membre dont le compte a été créé à `account_created`.
+    pub async fn on_join(&self, guild_id: GuildId, account_created: i64, now: i64, config: &RaidConfig) -> Option<RaidTrigger> {
+        let mut joins = self.joins.write().await;
+        let joins = joins.entry(guild_id).or_default();
+        joins.retain(|join| now - join.time < config.window as i64);
+        joins.push(Join {
+            time: now,
+            new_account: now - account_created < config.account_age as i64,
+        });
+        let new_accounts = joins.iter().filter(|join| join.new_account).count();
+        if joins.len() > config.max_joins {
+            Some(RaidTrigger::Joins(joins.len()))
+        } else if new_accounts > config.max_new_accounts {
+            Some(RaidTrigger::NewAccounts(new_accounts))
+        } else {
+            None
+        }
+    }
+    /// Enregistre un message envoyé à `now` par un membre arrivé sur le serveur à `joined_at`.
+    pub async fn on_message(&self, guild_id: GuildId, joined_at: i64, now: i64, config: &RaidConfig) -> Option<RaidTrigger> {
+        if now - joined_at >= RECENT_MEMBER {
+            return None;
+        }
+        let mut messages = self.messages.write().await;
+        let messages = messages.entry(guild_id).or_default();
+        messages.retain(|time| now - time < config.window as i64);
+        messages.push(now);
+        match messages.len() > config.max_burst {
+            true => Some(RaidTrigger::Burst(messages.len())),
+            false => None,
+        }
+    }
+    /// Oublie l'historique d'un serveur, après son confinement.
+    pub async fn reset(&self, guild_id: GuildId) {
+        self.joins.write().await.remove(&guild_id);
+        self.messages.write().await.remove(&guild_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUILD: GuildId = GuildId(1);
+    const NOW: i64 = 1_700_000_000;
+
+    fn config() -> RaidConfig {
+        RaidConfig { enabled: true, max_joins: 3, max_new_accounts: 1, max_burst: 2, ..RaidConfig::default() }
+    }
+
+    #[test]
+    fn joiner_actions_are_parsed() {
+        assert_eq!(JoinerAction::parse("ignorer"), Ok(JoinerAction::Ignore));
+        assert_eq!(JoinerAction::parse("KICK"), Ok(JoinerAction::Kick));
+        assert_eq!(JoinerAction::parse("Quarantaine"), Ok(JoinerAction::Quarantine));
+        assert!(JoinerAction::parse("ban").is_err());
+        for action in [JoinerAction::Ignore, JoinerAction::Kick, JoinerAction::Quarantine] {
+            assert_eq!(JoinerAction::parse(&action.to_string()), Ok(action));
+        }
+    }
+
+    #[test]
+    fn detection_is_disabled_by_default() {
+        let config: RaidConfig = serde_json::from_str(r#"{"max_joins": 20}"#).unwrap();
+        assert!(!config.enabled);
+        assert_eq!(config.max_joins, 20);
+        assert_eq!(config.window, RaidConfig::default().window);
+        assert_eq!(config.joiners, JoinerAction::Ignore);
+    }
+
+    #[test]
+    fn config_shows_the_quarantine_role() {
+        let mut config = config();
+        config.joiners = JoinerAction::Quarantine;
+        let embed = |config: &RaidConfig| serde_json::to_string(&config.to_message().embeds[0].0).unwrap();
+        assert!(embed(&config).contains("quarantaine (*aucun rôle défini*)"));
+        config.quarantine_role = Some(RoleId(42));
+        assert!(embed(&config).contains("quarantaine avec <@&42>"));
+    }
+
+    #[tokio::test]
+    async fn joins_trigger_a_lockdown() {
+        let detector = RaidDetector::default();
+        let config = config();
+        for i in 0..3 {
+            assert!(detector.on_join(GUILD, 0, NOW + i, &config).await.is_none());
+        }
+        assert!(matches!(detector.on_join(GUILD, 0, NOW + 3, &config).await, Some(RaidTrigger::Joins(4))));
+        // Les autres serveurs ne sont pas concernés
+        assert!(detector.on_join(GuildId(2), 0, NOW, &config).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn old_joins_are_forgotten() {
+        let detector = RaidDetector::default();
+        let config = config();
+        for i in 0..3 {
+            assert!(detector.on_join(GUILD, 0, NOW + i, &config).await.is_none());
+        }
+        let later = NOW + config.window as i64 + 1;
+        assert!(detector.on_join(GUILD, 0, later, &config).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn new_accounts_trigger_a_lockdown() {
+        let detector = RaidDetector::default();
+        let config = config();
+        assert!(detector.on_join(GUILD, NOW - 60, NOW, &config).await.is_none());
+        let old_account = NOW - config.account_age as i64;
+        assert!(detector.on_join(GUILD, old_account, NOW, &config).await.is_none());
+        assert!(matches!(detector.on_join(GUILD, NOW - 60, NOW, &config).await, Some(RaidTrigger::NewAccounts(2))));
+    }
+
+    #[tokio::test]
+    async fn messages_of_new_members_trigger_a_lockdown() {
+        let detector = RaidDetector::default();
+        let config = config();
+        // Les messages des membres arrivés depuis longtemps ne comptent pas
+        for _ in 0..5 {
+            assert!(detector.on_message(GUILD, NOW - RECENT_MEMBER, NOW, &config).await.is_none());
+        }
+        assert!(detector.on_message(GUILD, NOW - 60, NOW, &config).await.is_none());
+        assert!(detector.on_message(GUILD, NOW - 60, NOW, &config).await.is_none());
+        assert!(matches!(detector.on_message(GUILD, NOW - 60, NOW, &config).await, Some(RaidTrigger::Burst(3))));
+    }
+
+    #[tokio::test]
+    async fn reset_forgets_the_guild() {
+        let detector = RaidDetector::default();
+        let config = config();
+        for _ in 0..3 {
+            detector.on_join(GUILD, 0, NOW, &config).await;
+            detector.on_message(GUILD, NOW, NOW, &config).await;
+        }
+        detector.reset(GUILD).await;
+        assert!(detector.on_join(GUILD, 0, NOW, &config).await.is_none());
+        assert!(detector.on_message(GUILD, NOW, NOW, &config).await.is_none());
+    }
+}
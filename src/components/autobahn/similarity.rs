@@ -0,0 +1,211 @@
+//! Comparaison approximative des messages
+//!
+//! Le contenu des messages est normalisé (minuscules, sans ponctuation, emojis ni espaces,
+//! caractères répétés fusionnés) pour qu'un caractère ajouté ne suffise pas à contourner la détection.
+//! Les messages courts sont comparés par distance d'édition, les autres par une signature MinHash
+//! de leurs trigrammes : l'empreinte d'un message a une taille bornée, quelle que soit sa longueur.
+
+use std::{collections::HashSet, hash::Hasher};
+use serde::{Deserialize, Serialize};
+
+/// Taille des fragments comparés pour les messages longs
+const SHINGLE_SIZE: usize = 3;
+/// Longueur en dessous de laquelle les messages sont comparés par distance d'édition
+const SHORT_LEN: usize = 32;
+/// Nombre de valeurs de la signature d'un message long
+const SIGNATURE_SIZE: usize = 64;
+
+/// Empreinte du contenu d'un message
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum Fingerprint {
+    /// Contenu normalisé d'un message court
+    Short(String),
+    /// Signature MinHash des fragments d'un message long
+    Long(Vec<u32>),
+}
+
+impl Fingerprint {
+    pub fn new(content: &str) -> Self {
+        let text = normalize(content);
+        match text.len() <= SHORT_LEN {
+            true => Fingerprint::Short(text.into_iter().collect()),
+            false => Fingerprint::Long(signature(&text)),
+        }
+    }
+    /// Similarité entre deux messages, de 0 (différents) à 1 (identiques).
+    pub fn similarity(&self, other: &Fingerprint) -> f64 {
+        match (self, other) {
+            (Fingerprint::Short(a), Fingerprint::Short(b)) => {
+                if a == b {
+                    return 1.0;
+                }
+                let (a, b) = (a.chars().collect::<Vec<_>>(), b.chars().collect::<Vec<_>>());
+                1.0 - levenshtein(&a, &b) as f64 / a.len().max(b.len()) as f64
+            },
+            (Fingerprint::Long(a), Fingerprint::Long(b)) => estimate_jaccard(a, b),
+            (Fingerprint::Short(text), Fingerprint::Long(sig)) | (Fingerprint::Long(sig), Fingerprint::Short(text)) => {
+                estimate_jaccard(&signature(&text.chars().collect::<Vec<_>>()), sig)
+            },
+        }
+    }
+}
+
+fn hash(chars: &[char]) -> u64 {
+    let mut hasher = hashers::fx_hash::FxHasher64::default();
+    chars.iter().for_each(|c| hasher.write_u32(*c as u32));
+    hasher.finish()
+}
+
+/// Signature MinHash des trigrammes du texte : pour chacune des [`SIGNATURE_SIZE`] fonctions de hachage,
+/// le plus petit hash des trigrammes.
+fn signature(text: &[char]) -> Vec<u32> {
+    let shingles: HashSet<u64> = match text.len() < SHINGLE_SIZE {
+        true => HashSet::from([hash(text)]),
+        false => text.windows(SHINGLE_SIZE).map(hash).collect(),
+    };
+    let mut signature = vec![u32::MAX; SIGNATURE_SIZE];
+    for shingle in shingles {
+        let shingle = mix(shingle);
+        for (seed, min) in signature.iter_mut().enumerate() {
+            // Hachage multiplicatif, avec une graine différente par valeur de la signature
+            let value = (shingle ^ (seed as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15)).wrapping_mul(0xD6E8_FEB8_6659_FD93) >> 32;
+            *min = (*min).min(value as u32);
+        }
+    }
+    signature
+}
+
+/// Mélange les bits du hash d'un fragment (finaliseur de splitmix64).
+fn mix(hash: u64) -> u64 {
+    let mut z = hash;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Estimation de l'indice de Jaccard des trigrammes de deux messages : la proportion de valeurs communes des signatures.
+fn estimate_jaccard(a: &[u32], b: &[u32]) -> f64 {
+    let common = a.iter().zip(b).filter(|(a, b)| a == b).count();
+    common as f64 / SIGNATURE_SIZE as f64
+}
+
+/// Normalise le contenu d'un message.
+///
+/// Si le message ne contient aucune lettre ni chiffre (emojis seuls par exemple),
+/// son contenu brut est conservé pour ne pas confondre tous ces messages entre eux.
+fn normalize(content: &str) -> Vec<char> {
+    let mut text: Vec<char> = content
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if text.is_empty() {
+        text = content.trim().chars().collect();
+    }
+    text.dedup();
+    text
+}
+
+/// Nombre minimal d'insertions, suppressions et substitutions pour passer de `a` à `b`.
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPAM: &str = "Bonjour à tous, je vous offre un abonnement Nitro gratuit pendant un an, \
+        il suffit de cliquer sur le lien suivant et de vous connecter avec votre compte";
+
+    fn similarity(a: &str, b: &str) -> f64 {
+        Fingerprint::new(a).similarity(&Fingerprint::new(b))
+    }
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    #[test]
+    fn content_is_normalized() {
+        assert_eq!(normalize("Salut, TOUT le monde !!!"), chars("salutoutlemonde"));
+        assert_eq!(normalize("Spaaaaam 🚀🚀"), chars("spam"));
+        assert_eq!(normalize("Ça va ?"), chars("çava"));
+    }
+
+    #[test]
+    fn emoji_only_content_is_kept() {
+        assert_eq!(normalize("🎉🎉🎉"), chars("🎉"));
+        assert_eq!(normalize("  🎉 🔥  "), chars("🎉 🔥"));
+    }
+
+    #[test]
+    fn levenshtein_distance() {
+        assert_eq!(levenshtein(&chars("kitten"), &chars("sitting")), 3);
+        assert_eq!(levenshtein(&chars(""), &chars("abc")), 3);
+        assert_eq!(levenshtein(&chars("abc"), &chars("")), 3);
+        assert_eq!(levenshtein(&chars("abc"), &chars("abc")), 0);
+        assert_eq!(levenshtein(&chars("abc"), &chars("acb")), 2);
+    }
+
+    #[test]
+    fn added_character_is_ignored() {
+        assert_eq!(similarity("achetez des cryptos", "achetez des cryptos!"), 1.0);
+        assert_eq!(similarity("achetez des cryptos", "achetez des cryptos 🚀"), 1.0);
+        assert_eq!(similarity("achetez des cryptos", "achetez  des   cryptoss"), 1.0);
+        assert!(similarity("achetez des cryptos", "achetez des cryptosz") >= 0.9);
+    }
+
+    #[test]
+    fn short_messages_use_edit_distance() {
+        assert!((similarity("salut", "salue") - 0.8).abs() < 1e-9);
+        assert!(similarity("salut", "bonsoir") < 0.5);
+    }
+
+    #[test]
+    fn long_messages_use_fragments() {
+        let edited = SPAM.replace("gratuit", "gratuits").replace("lien", "lein");
+        assert!(similarity(SPAM, &edited) >= 0.8);
+        assert_eq!(similarity(SPAM, &format!("{} 🎁", SPAM)), 1.0);
+        let other = "Le prochain événement du serveur aura lieu samedi soir, \
+            pensez à vous inscrire dans le salon dédié avant vendredi midi";
+        assert!(similarity(SPAM, other) < 0.3);
+    }
+
+    #[test]
+    fn short_and_long_messages_differ() {
+        assert!(similarity("nitro gratuit", SPAM) < 0.3);
+        assert!(similarity(SPAM, "nitro gratuit") < 0.3);
+    }
+
+    #[test]
+    fn fingerprint_size_is_bounded() {
+        assert_eq!(Fingerprint::new("Salut !!"), Fingerprint::Short("salut".to_string()));
+        match Fingerprint::new(&SPAM.repeat(50)) {
+            Fingerprint::Long(signature) => assert_eq!(signature.len(), SIGNATURE_SIZE),
+            short => panic!("{:?}", short),
+        }
+    }
+
+    #[test]
+    fn emoji_only_messages_are_compared() {
+        assert_eq!(similarity("🎉", "🎉🎉🎉"), 1.0);
+        assert_eq!(similarity("🎉", "🔥"), 0.0);
+        assert!(similarity("🎉 🔥", "🔥 🎉") < 0.8);
+        assert!(similarity("🎉", "salut") < 0.5);
+    }
+}
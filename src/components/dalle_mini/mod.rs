@@ -20,6 +20,8 @@ const DALLE_MINI_MAX_COUNT: u8 = 5;
 /// The dalle mini component.
 pub struct DalleMini;
 
+impl cddio_core::ComponentLifecycle for DalleMini {}
+
 #[component]
 impl DalleMini {
     /// Command to send a dalle mini image to a channel.
@@ -5,7 +5,8 @@
 use cddio_core::{self as core, ApplicationCommandEmbed, message, message::ToMessage};
 use self::core::declarative::IterType;
 use cddio_macros::component;
-use serenity::client::Context;
+use serenity::{client::Context, model::id::GuildId};
+use std::sync::Arc;
 
 pub struct Help {
     container: core::container::RefContainer,
@@ -19,6 +20,8 @@ impl Help {
     }
 }
 
+impl core::ComponentLifecycle for Help {}
+
 #[component]
 impl Help {
     #[command(description="Affiche l'aide d'une commanded ou du bot")]
@@ -26,7 +29,7 @@ impl Help {
         #[argument(description="Nom de la commande ou du groupe")]
         commande: String
     ) {
-        let info = self.get_command_info(commande.as_str()).await;
+        let info = self.get_command_info(commande.as_str(), app_cmd.get_guild_id()).await;
         let msg = match info {
             Some((_, IterType::Command(comm))) => comm.to_message(),
             Some((_, IterType::Node(node))) => node.to_message(),
@@ -42,7 +45,7 @@ impl Help {
     #[command(description="Affiche la liste des commandes du bot")]
     async fn liste_commandes(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>) {
         let container = self.container.read().await;
-        let msg = container.as_ref().iter()
+        let msg = Self::components(&container, app_cmd.get_guild_id())
             .filter_map(|comp| comp.declarative())
             .flat_map(|node| node.iter_flat())
             .filter_map(|(fullname, iter_type)| {
@@ -62,11 +65,19 @@ impl Help {
         }
     }
 
-    async fn get_command_info(&self, command: &str) -> Option<(String, IterType)> {
+    async fn get_command_info(&self, command: &str, guild_id: Option<GuildId>) -> Option<(String, IterType)> {
         let container = self.container.read().await;
-        container.as_ref().iter()
+        let info = Self::components(&container, guild_id)
             .filter_map(|comp| comp.declarative())
             .flat_map(|node| node.iter_flat())
-            .find(|(fullname, _)| fullname == command)
+            .find(|(fullname, _)| fullname == command);
+        info
     }
-}
+    /// Composants activés sur le serveur, ou tous les composants hors d'un serveur.
+    fn components(container: &core::ComponentContainer, guild_id: Option<GuildId>) -> Box<dyn Iterator<Item = &Arc<dyn core::Component>> + '_> {
+        match guild_id {
+            Some(guild_id) => Box::new(container.enabled_components(guild_id)),
+            None => Box::new(container.as_ref().iter()),
+        }
+    }
+}
\ No newline at end of file
@@ -1,7 +1,7 @@
 //! Miscellaneous commands and events.
 
 use crate::{log_error, log_warn};
-use cddio_core::{self as core, ApplicationCommandEmbed, message};
+use cddio_core::{self as core, ApplicationCommandEmbed, message, lifecycle::Health};
 use cddio_macros::component;
 use serenity::{
     client::Context, 
@@ -14,6 +14,8 @@ pub struct Misc {
     container: core::container::RefContainer,
 }
 
+impl core::ComponentLifecycle for Misc {}
+
 #[component]
 impl Misc {
     pub fn new(app_id: ApplicationId, bot_permissions: u64, container: core::container::RefContainer) -> Self {
@@ -47,4 +49,56 @@ impl Misc {
             log_error!("ping: Erreur lors de la réponse: {}", e);
         }
     }
-}
\ No newline at end of file
+    #[command(description="Affiche l'état des composants du bot")]
+    async fn statut(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>) {
+        let health = self.container.read().await.health().await;
+        let healthy = health.iter().all(|(_, health)| *health == Health::Healthy);
+        let list = health.iter()
+            .map(|(name, health)| format!("**{}**: {}", name, health))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let color = match healthy {
+            true => message::COLOR_SUCCESS,
+            false => message::COLOR_WARN,
+        };
+        let msg = message::custom_embed("État des composants", list, color);
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("statut: Erreur lors de la réponse: {}", e);
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cddio_core::testing::{Harness, fixtures::{self, CommandBuilder}};
+    use futures_locks::RwLock;
+
+    #[tokio::test]
+    async fn ping_responds_pong() {
+        let harness = Harness::new().await.unwrap();
+        let misc = Misc::new(ApplicationId(fixtures::APPLICATION_ID), 0, RwLock::new(core::ComponentContainer::new()));
+        harness.command(&misc, CommandBuilder::new("ping")).await;
+
+        let responses = harness.mock.find("POST", "/interactions/*/*/callback");
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].body["data"]["embeds"][0]["description"], "Pong!");
+    }
+
+    #[tokio::test]
+    async fn statut_reports_degraded_components() {
+        let harness = Harness::new().await.unwrap();
+        let container = RwLock::new(core::ComponentContainer::new());
+        let misc = {
+            let mut cont = container.write().await;
+            let settings = cont.add_component(crate::components::Settings::new(Vec::new()));
+            cont.add_component(crate::components::Moderation::new(settings));
+            cont.add_component(Misc::new(ApplicationId(fixtures::APPLICATION_ID), 0, container.clone()))
+        };
+        harness.command(misc.as_ref(), CommandBuilder::new("statut")).await;
+
+        let responses = harness.mock.find("POST", "/interactions/*/*/callback");
+        let embed = &responses[0].body["data"]["embeds"][0];
+        assert_eq!(embed["color"], message::COLOR_WARN.0);
+        assert_eq!(embed["description"], "**settings**: OK\n**moderation**: Dégradé : les sanctions temporaires ne sont pas chargées\n**misc**: OK");
+    }
+}
@@ -1,5 +1,7 @@
 //! Module comportant les composants
 
+pub mod settings;
+pub use settings::*;
 pub mod misc;
 pub use misc::*;
 pub mod help;
@@ -0,0 +1,181 @@
+//! Politique d'escalade des avertissements.
+//!
+//! Lorsqu'un membre cumule un certain nombre d'avertissements actifs,
+//! une sanction est automatiquement appliquée (ex: 3 avertissements → mute de 1 jour).
+//! Les avertissements expirent après une durée configurable par serveur.
+
+use std::fmt;
+
+use chrono::{DateTime, Duration, Utc};
+use cddio_core::message;
+use serde::{Deserialize, Serialize};
+
+use super::utils::time_parser as time;
+
+/// Sanction appliquée lors d'une escalade.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EscalationAction {
+    Mute,
+    Kick,
+    Ban,
+}
+
+impl EscalationAction {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "mute" => Ok(EscalationAction::Mute),
+            "kick" => Ok(EscalationAction::Kick),
+            "ban" => Ok(EscalationAction::Ban),
+            _ => Err(format!("Sanction \"{}\" inconnue, attendue: mute, kick, ban", name)),
+        }
+    }
+}
+
+impl fmt::Display for EscalationAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EscalationAction::Mute => write!(f, "mute"),
+            EscalationAction::Kick => write!(f, "kick"),
+            EscalationAction::Ban => write!(f, "ban"),
+        }
+    }
+}
+
+/// Palier d'escalade.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EscalationStep {
+    /// Nombre d'avertissements actifs déclenchant le palier
+    pub warns: u32,
+    /// Sanction appliquée
+    pub action: EscalationAction,
+    /// Durée de la sanction en secondes. Définitive si absente.
+    pub duration: Option<u64>,
+}
+
+impl fmt::Display for EscalationStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} avertissement(s) → {}", self.warns, self.action)?;
+        match (self.action, self.duration) {
+            (EscalationAction::Kick, _) => Ok(()),
+            (_, Some(duration)) => write!(f, " pendant {}", time::format_duration(duration)),
+            (_, None) => write!(f, " définitif"),
+        }
+    }
+}
+
+/// Politique d'escalade d'un serveur.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EscalationPolicy {
+    /// Durée de validité d'un avertissement en secondes
+    pub window: u64,
+    /// Paliers d'escalade, triés par nombre d'avertissements
+    pub steps: Vec<EscalationStep>,
+}
+
+impl Default for EscalationPolicy {
+    fn default() -> Self {
+        const DAY: u64 = 24 * 60 * 60;
+        EscalationPolicy {
+            window: 30 * DAY,
+            steps: vec![
+                EscalationStep { warns: 3, action: EscalationAction::Mute, duration: Some(DAY) },
+                EscalationStep { warns: 5, action: EscalationAction::Ban, duration: Some(7 * DAY) },
+            ],
+        }
+    }
+}
+
+impl EscalationPolicy {
+    /// Retourne le palier le plus élevé franchi en passant de `previous` à `warns` avertissements actifs,
+    /// c'est-à-dire tel que `previous < palier <= warns`.
+    ///
+    /// Un palier sauté (avertissement retiré, politique modifiée) est donc tout de même appliqué.
+    pub fn step_for(&self, previous: usize, warns: usize) -> Option<&EscalationStep> {
+        self.steps.iter()
+            .filter(|step| previous < step.warns as usize && step.warns as usize <= warns)
+            .max_by_key(|step| step.warns)
+    }
+    /// Date d'expiration d'un avertissement donné à `now`.
+    pub fn warn_expiry(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now + Duration::seconds(self.window as _)
+    }
+    /// Ajoute ou remplace un palier.
+    pub fn set_step(&mut self, step: EscalationStep) {
+        self.steps.retain(|v| v.warns != step.warns);
+        self.steps.push(step);
+        self.steps.sort_by_key(|v| v.warns);
+    }
+    /// Retire un palier. Retourne `false` si le palier n'existe pas.
+    pub fn remove_step(&mut self, warns: u32) -> bool {
+        let len = self.steps.len();
+        self.steps.retain(|v| v.warns != warns);
+        len != self.steps.len()
+    }
+    pub fn to_message(&self) -> message::Message {
+        let steps = if self.steps.is_empty() {
+            "*Aucun palier*".to_string()
+        } else {
+            self.steps.iter().map(|step| format!("• {}", step)).collect::<Vec<_>>().join("\n")
+        };
+        let mut msg = message::Message::new();
+        msg.add_embed(|embed| {
+            embed
+                .title("Escalade des avertissements")
+                .color(message::COLOR_INFO)
+                .field("Expiration", time::format_duration(self.window), false)
+                .field("Paliers", steps, false)
+        });
+        msg
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn policy() -> EscalationPolicy {
+        EscalationPolicy::default()
+    }
+
+    #[test]
+    fn step_is_applied_when_threshold_is_reached() {
+        let policy = policy();
+        assert!(policy.step_for(1, 2).is_none());
+        assert_eq!(policy.step_for(2, 3).map(|step| step.action), Some(EscalationAction::Mute));
+        assert!(policy.step_for(3, 4).is_none());
+        assert_eq!(policy.step_for(4, 5).map(|step| step.action), Some(EscalationAction::Ban));
+    }
+
+    #[test]
+    fn skipped_steps_are_applied() {
+        let policy = policy();
+        // Palier de 3 ajouté alors que le membre avait déjà 4 avertissements
+        assert_eq!(policy.step_for(0, 4).map(|step| step.warns), Some(3));
+        // Plusieurs paliers franchis : le plus élevé l'emporte
+        assert_eq!(policy.step_for(0, 6).map(|step| step.warns), Some(5));
+        // Paliers déjà appliqués
+        assert!(policy.step_for(5, 6).is_none());
+    }
+
+    #[test]
+    fn steps_are_sorted_and_replaced() {
+        let mut policy = policy();
+        policy.set_step(EscalationStep { warns: 1, action: EscalationAction::Kick, duration: None });
+        policy.set_step(EscalationStep { warns: 3, action: EscalationAction::Ban, duration: None });
+        assert_eq!(policy.steps.iter().map(|step| step.warns).collect::<Vec<_>>(), [1, 3, 5]);
+        assert_eq!(policy.step_for(2, 3).map(|step| step.action), Some(EscalationAction::Ban));
+        assert!(policy.remove_step(1));
+        assert!(!policy.remove_step(1));
+    }
+
+    #[test]
+    fn warns_expire_after_the_window() {
+        let mut policy = policy();
+        let now = Utc.ymd(2022, 6, 1).and_hms(12, 0, 0);
+        assert_eq!(policy.warn_expiry(now), Utc.ymd(2022, 7, 1).and_hms(12, 0, 0));
+        policy.window = 60 * 60;
+        assert_eq!(policy.warn_expiry(now), Utc.ymd(2022, 6, 1).and_hms(13, 0, 0));
+    }
+}
@@ -0,0 +1,111 @@
+//! Mise en forme de l'historique des sanctions.
+//!
+//! L'historique est reconstruit à partir du journal de modération (voir [`log_audit`]).
+//!
+//! [`log_audit`]: super::log_audit
+
+use chrono::{DateTime, Utc};
+use cddio_core::message;
+use serenity::model::id::UserId;
+
+use super::log_audit::{CaseId, LogEntry};
+use super::sanction::{Sanction, SanctionType};
+
+/// Nombre de sanctions affichées par page de l'historique.
+pub const PAGE_SIZE: usize = 10;
+/// Taille maximale d'une raison affichée dans l'historique.
+const MAX_REASON_LEN: usize = 200;
+
+fn format_date(date: &DateTime<Utc>) -> String {
+    date.format("%d/%m/%Y %H:%M").to_string()
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        text.to_string()
+    } else {
+        format!("{}…", text.chars().take(max).collect::<String>())
+    }
+}
+
+/// Résumé d'une sanction sur quelques lignes.
+fn summary(entry: &LogEntry<Sanction>, max_reason: usize) -> String {
+    let sanction = &entry.data;
+    let mut lines = vec![format!("Par <@{}>", sanction.user_by)];
+    if let Some(edit) = edit_summary(sanction) {
+        lines.push(edit);
+    }
+    if let Some(reason) = sanction.reason() {
+        let reason = if reason.is_empty() { "*Aucune raison*".to_string() } else { truncate(reason, max_reason) };
+        lines.push(format!("Raison : {}", reason));
+    }
+    if let Some(until) = sanction.until() {
+        lines.push(format!("Jusqu'au : {}", format_date(&until)));
+    }
+    lines.join("\n")
+}
+
+/// Description d'une modification de sanction temporaire.
+fn edit_summary(sanction: &Sanction) -> Option<String> {
+    match &sanction.data {
+        SanctionType::Edit { task, sanction, until: Some(_) } => Some(format!("{} (tâche #{}) replanifié", sanction, task)),
+        SanctionType::Edit { task, sanction, until: None } => Some(format!("{} (tâche #{}) annulé", sanction, task)),
+        _ => None
+    }
+}
+
+/// Génère une page de l'historique des sanctions d'un membre.
+///
+/// `entries` doit être trié de la sanction la plus récente à la plus ancienne.
+/// `page` commence à 1.
+pub fn history_message(user_id: UserId, username: &str, entries: &[(CaseId, LogEntry<Sanction>)], page: usize) -> message::Message {
+    if entries.is_empty() {
+        return message::info(format!("Aucune sanction enregistrée pour {}", username));
+    }
+    let nb_pages = entries.len().div_ceil(PAGE_SIZE);
+    if page == 0 || page > nb_pages {
+        return message::error(format!("Page invalide. L'historique de {} contient {} page(s).", username, nb_pages));
+    }
+    let mut msg = message::Message::new();
+    msg.add_embed(|embed| {
+        embed
+            .title(format!("Historique de {}", username))
+            .description(format!("<@{}> : {} sanction(s) enregistrée(s)", user_id, entries.len()))
+            .color(message::COLOR_INFO)
+            .footer(|footer| footer.text(format!("Page {}/{}", page, nb_pages)));
+        for (case_id, entry) in entries.iter().skip((page - 1) * PAGE_SIZE).take(PAGE_SIZE) {
+            embed.field(
+                format!("Case #{} · {} · {}", case_id, entry.data.name(), format_date(&entry.datetime)),
+                summary(entry, MAX_REASON_LEN),
+                false
+            );
+        }
+        embed
+    });
+    msg
+}
+
+/// Génère le détail d'une case du journal de modération.
+pub fn case_message(case_id: CaseId, entry: &LogEntry<Sanction>) -> message::Message {
+    let sanction = &entry.data;
+    let mut msg = message::Message::new();
+    msg.add_embed(|embed| {
+        embed
+            .title(format!("Case #{} · {}", case_id, sanction.name()))
+            .color(message::COLOR_INFO)
+            .field("Membre", format!("<@{}>", sanction.user_id), true)
+            .field("Par", format!("<@{}>", sanction.user_by), true)
+            .field("Date", format_date(&entry.datetime), true);
+        if let Some(edit) = edit_summary(sanction) {
+            embed.field("Modification", edit, false);
+        }
+        if let Some(until) = sanction.until() {
+            embed.field("Jusqu'au", format_date(&until), true);
+        }
+        if let Some(reason) = sanction.reason() {
+            embed.field("Raison", if reason.is_empty() { "*Aucune raison*".to_string() } else { truncate(reason, 1000) }, false);
+        }
+        embed
+    });
+    msg
+}
@@ -1,27 +1,49 @@
 use std::path::PathBuf;
 
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use crate::log_warn;
+use futures_locks::Mutex;
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+
+/// Identifiant d'une entrée du journal de modération.
+///
+/// Il s'agit du numéro de ligne (à partir de 1) de l'entrée dans le fichier.
+/// Le fichier n'étant jamais réécrit, ce numéro est stable dans le temps.
+pub type CaseId = u64;
 
 pub struct Log {
     path: PathBuf,
+    /// Nombre de lignes du journal, lu à la première écriture
+    nb_lines: Mutex<Option<CaseId>>,
 }
 
-#[derive(Serialize)]
-struct LogEntry<D> {
-    datetime: DateTime<Utc>,
-    data: D,
+#[derive(Serialize, Deserialize)]
+pub struct LogEntry<D> {
+    pub datetime: DateTime<Utc>,
+    pub data: D,
 }
 
 impl Log {
     pub fn new<P: Into<PathBuf>>(path: P) -> Self {
         Self {
             path: path.into(),
+            nb_lines: Mutex::new(None),
         }
     }
-    pub async fn push<D: Serialize>(&self, data: &D) -> Result<(), String> {
+    /// Ajoute une entrée au journal et retourne son numéro de case.
+    pub async fn push<D: Serialize>(&self, data: &D) -> Result<CaseId, String> {
         use async_std::fs::OpenOptions;
         use async_std::prelude::*;
+        // Le verrou est gardé jusqu'à la fin de l'écriture pour garantir des numéros de case uniques
+        let mut nb_lines = self.nb_lines.lock().await;
+        let case_id = match *nb_lines {
+            Some(nb) => nb + 1,
+            None if self.path.exists() => async_std::fs::read_to_string(&self.path).await
+                .map_err(|e| format!("modo: Impossible de lire le fichier de log: {}", e))?
+                .lines()
+                .count() as CaseId + 1,
+            None => 1,
+        };
         let mut file = OpenOptions::new()
             .create(true)
             .append(true)
@@ -36,6 +58,41 @@ impl Log {
             .or_else(|e| Err(format!("modo: Impossible d'écrire le fichier de log: {}", e.to_string())))?;
         file.write(b"\n").await
             .or_else(|e| Err(format!("modo: Impossible d'écrire dans le fichier de log: {}", e.to_string())))?;
-        Ok(())
+        *nb_lines = Some(case_id);
+        Ok(case_id)
+    }
+    /// Lit l'intégralité du journal.
+    ///
+    /// Chaque entrée est accompagnée de son numéro de case.
+    /// Les lignes illisibles sont ignorées mais conservent leur numéro.
+    pub async fn read_all<D: DeserializeOwned>(&self) -> Result<Vec<(CaseId, LogEntry<D>)>, String> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = async_std::fs::read_to_string(&self.path).await
+            .map_err(|e| format!("modo: Impossible de lire le fichier de log: {}", e))?;
+        let entries = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .filter_map(|(i, line)| {
+                let case_id = i as CaseId + 1;
+                match serde_json::from_str::<LogEntry<D>>(line) {
+                    Ok(entry) => Some((case_id, entry)),
+                    Err(e) => {
+                        log_warn!("modo: Entrée {} du journal illisible: {}", case_id, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        Ok(entries)
     }
-}
\ No newline at end of file
+    /// Cherche une entrée du journal à partir de son numéro de case.
+    pub async fn find<D: DeserializeOwned>(&self, case_id: CaseId) -> Result<Option<LogEntry<D>>, String> {
+        Ok(self.read_all().await?
+            .into_iter()
+            .find(|(id, _)| *id == case_id)
+            .map(|(_, entry)| entry))
+    }
+}
@@ -1,14 +1,18 @@
 //! Moderation management
 
 mod sanction;
-mod registry_file;
 mod log_audit;
+mod history;
+mod escalation;
 
-use chrono::{Duration, Utc, DateTime};
+use chrono::{Duration, Utc, DateTime, TimeZone};
 use crate::{log_error, log_warn, log_info};
 use futures_locks::{RwLock, Mutex};
-use cddio_core::{ApplicationCommandEmbed, message};
+use cddio_core::{ApplicationCommandEmbed, ComponentLifecycle, lifecycle::Health, message};
 use cddio_macros::component;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::sync::Arc;
 use serenity::{
     client::Context,
     model::{
@@ -20,24 +24,82 @@ use super::utils::{
     self, 
     task::Registry,
     time_parser as time,
-    task
+    task,
+    registry_file::RegistryFile,
+    data::{Data, DATA_DIR}
 };
+use super::Settings;
 use self::{
-    sanction::{Sanction, SanctionType},
-    registry_file::RegistryFile,
+    sanction::{Sanction, SanctionType, MuteMode},
+    log_audit::CaseId,
+    escalation::{EscalationPolicy, EscalationStep, EscalationAction},
 };
 
+/// Gestionnaire des sanctions temporaires
+type SanctionManager = task::TaskManager<Sanction, RegistryFile<Sanction>, (Context, Arc<Settings>, Arc<log_audit::Log>)>;
+
 pub struct Moderation {
-    tasks: RwLock<Option<task::TaskManager<Sanction, RegistryFile, Context>>>,
-    logger: log_audit::Log,
-    bot_id: Mutex<UserId>
+    tasks: RwLock<Option<SanctionManager>>,
+    logger: Arc<log_audit::Log>,
+    bot_id: Mutex<UserId>,
+    /// Données persistantes
+    data: RwLock<Data<DataModeration>>,
+    /// Configuration des serveurs
+    settings: Arc<Settings>,
+    /// Dernière entrée d'audit traitée par serveur et par type d'action
+    audit_seen: Mutex<HashMap<(GuildId, u8), AuditLogEntryId>>,
+    /// État de mute connu des membres mutés, pour ne consulter l'audit que lorsqu'il change
+    mute_states: Mutex<HashMap<(GuildId, UserId), MuteState>>,
+}
+
+/// État de mute d'un membre, tel que reçu dans les mises à jour de membre.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+struct MuteState {
+    /// Le membre a le rôle mute
+    role: bool,
+    /// Fin de l'exclusion temporaire du membre
+    timeout: Option<serenity::model::Timestamp>,
+}
+
+/// Données persistantes du composant
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct DataModeration {
+    /// Politique d'escalade des avertissements par serveur
+    #[serde(default)]
+    escalation: HashMap<GuildId, EscalationPolicy>,
+    /// Méthode de mute par serveur
+    #[serde(default)]
+    mute_mode: HashMap<GuildId, MuteMode>,
+    /// Dernier palier d'escalade appliqué à chaque membre
+    #[serde(default)]
+    escalated: HashMap<GuildId, HashMap<UserId, u32>>,
 }
+
 impl Moderation {
-    pub fn new() -> Self {
+    pub fn new(settings: Arc<Settings>) -> Self {
         Moderation {
             tasks: RwLock::new(None),
-            logger: log_audit::Log::new("data/moderation.json"),
-            bot_id: Mutex::new(UserId(0))
+            logger: Arc::new(log_audit::Log::new(DATA_DIR.join("moderation.json"))),
+            bot_id: Mutex::new(UserId(0)),
+            data: RwLock::new(Data::from_file("modo_settings").unwrap()),
+            settings,
+            audit_seen: Mutex::new(HashMap::new()),
+            mute_states: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[serenity::async_trait]
+impl ComponentLifecycle for Moderation {
+    async fn on_shutdown(&self) {
+        if let Some(tasks) = self.tasks.write().await.as_mut() {
+            tasks.shutdown().await;
+        }
+    }
+    async fn health(&self) -> Health {
+        match self.tasks.read().await.is_some() {
+            true => Health::Healthy,
+            false => Health::Degraded("les sanctions temporaires ne sont pas chargées".to_string()),
         }
     }
 }
@@ -45,17 +107,20 @@ impl Moderation {
 const AUDIT_TIME_THRESHOLD: i64 = 60;
 
 #[component]
+#[group(name="modo", description="Outils de modération")]
+#[group(name="avertissements", description="Configuration des avertissements", parent="modo")]
+#[group(name="sanctions", description="Gestion des sanctions temporaires", parent="modo")]
 impl Moderation {
     #[event(Ready)]
     async fn on_ready(&self, ctx: &Context, ready: &ReadyEvent) {
         *self.bot_id.lock().await = ready.ready.user.id;
         let mut tasks = self.tasks.write().await;
-        let ctx = ctx.clone();
+        let persistent = (ctx.clone(), self.settings.clone(), self.logger.clone());
         match &mut *tasks {
-            Some(tasks) => tasks.reset_persistent(ctx.clone()),
+            Some(tasks) => tasks.reset_persistent(persistent),
             None => {
-                let registry = RegistryFile::from_file("./data/moderation2.json").await.unwrap();
-                let mut new_tasks = task::TaskManager::new(registry, ctx.clone());
+                let registry = RegistryFile::from_file(DATA_DIR.join("moderation2.json")).await.unwrap();
+                let mut new_tasks = task::TaskManager::new(registry, persistent);
                 new_tasks.init().await;
                 *tasks = Some(new_tasks);
             }
@@ -84,9 +149,28 @@ impl Moderation {
     }
     #[event(GuildMemberUpdate)]
     async fn on_member_update(&self, ctx: &Context, event: &GuildMemberUpdateEvent) {
-        match self.push_log(ctx, event.guild_id, event.user.id, 20).await {
-            Ok(_) => {},
-            Err(e) => log_error!("{}", e),
+        let mute_role = self.settings.mute_role(ctx, event.guild_id).await.ok();
+        let state = MuteState {
+            role: mute_role.is_some_and(|role| event.roles.contains(&role)),
+            timeout: event.communication_disabled_until.filter(|until| until.unix_timestamp() > Utc::now().timestamp()),
+        };
+        let previous = {
+            let mut mute_states = self.mute_states.lock().await;
+            let key = (event.guild_id, event.user.id);
+            let previous = mute_states.get(&key).copied().unwrap_or_default();
+            match state == MuteState::default() {
+                true => mute_states.remove(&key),
+                false => mute_states.insert(key, state),
+            };
+            previous
+        };
+        // 25: mute par rôle, 24: mute par timeout
+        let action_types = [(25, previous.role != state.role), (24, previous.timeout != state.timeout)];
+        for (action_type, _) in action_types.into_iter().filter(|(_, changed)| *changed) {
+            match self.push_log(ctx, event.guild_id, event.user.id, action_type).await {
+                Ok(_) => {},
+                Err(e) => log_error!("{}", e),
+            }
         }
     }
     
@@ -167,6 +251,35 @@ impl Moderation {
         }
     }
     
+    #[command(name="warn",description="Avertit un membre du serveur")]
+    async fn com_warn(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Membre à avertir", name="qui")]
+        member: UserId,
+        #[argument(description="Raison de l'avertissement")]
+        raison: String
+    ) {
+        let resp = match app_cmd.delayed_response(ctx, false).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                log_error!("{}", e);
+                return;
+            }
+        };
+        let msg = match app_cmd.get_guild_id() {
+            Some(guild_id) => match self.warn(ctx, guild_id, member, Some(app_cmd.0.user.id), raison).await {
+                Ok(v) => v,
+                Err(e) => {
+                    log_error!("{}", e);
+                    message::error(e)
+                }
+            },
+            None => message::error("Cette commande doit être executé sur un serveur.".to_string()),
+        };
+        match resp.send_message(msg).await {
+            Ok(_) => {},
+            Err(e) => log_error!("{}", e),
+        }
+    }
     #[command(name="mute",description="Mute un membre du serveur")]
     async fn com_mute(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
         #[argument(description="Membre à mute", name="qui")]
@@ -268,7 +381,192 @@ impl Moderation {
             Err(e) => log_error!("{}", e),
         }
     }
-
+    #[command(group="modo", name="historique", description="Affiche l'historique des sanctions d'un membre")]
+    async fn com_history(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Membre dont on veut l'historique", name="membre")]
+        member: UserId,
+        #[argument(description="Page de l'historique (1 par défaut)")]
+        page: Option<u64>
+    ) {
+        let resp = match app_cmd.delayed_response(ctx, true).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                log_error!("{}", e);
+                return;
+            }
+        };
+        let msg = match self.history(ctx, app_cmd.get_guild_id(), member, page.unwrap_or(1) as usize).await {
+            Ok(v) => v,
+            Err(e) => {
+                log_error!("{}", e);
+                message::error(e)
+            }
+        };
+        match resp.send_message(msg).await {
+            Ok(_) => {},
+            Err(e) => log_error!("{}", e),
+        }
+    }
+    #[command(group="modo", name="case", description="Affiche le détail d'une sanction à partir de son numéro")]
+    async fn com_case(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Numéro de la sanction", name="id")]
+        case_id: u64
+    ) {
+        let resp = match app_cmd.delayed_response(ctx, true).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                log_error!("{}", e);
+                return;
+            }
+        };
+        let msg = match self.case(app_cmd.get_guild_id(), case_id).await {
+            Ok(v) => v,
+            Err(e) => {
+                log_error!("{}", e);
+                message::error(e)
+            }
+        };
+        match resp.send_message(msg).await {
+            Ok(_) => {},
+            Err(e) => log_error!("{}", e),
+        }
+    }
+    #[command(group="modo", name="mode_mute", description="Choisit la méthode utilisée pour mute un membre")]
+    async fn com_mute_mode(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="role (rôle \"muted\") ou timeout (exclusion temporaire Discord)")]
+        mode: String
+    ) {
+        let msg = match (app_cmd.get_guild_id(), MuteMode::parse(&mode)) {
+            (None, _) => message::error("Cette commande doit être executé sur un serveur.".to_string()),
+            (_, Err(e)) => message::error(e),
+            (Some(guild_id), Ok(mode)) => {
+                self.data.write().await.write().mute_mode.insert(guild_id, mode);
+                message::success(format!("Les membres seront désormais mute par {}", mode.name()))
+            }
+        };
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="avertissements", name="liste", description="Affiche la politique d'escalade des avertissements")]
+    async fn com_escalation_list(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>) {
+        let msg = match app_cmd.get_guild_id() {
+            Some(guild_id) => self.escalation_policy(guild_id).await.to_message(),
+            None => message::error("Cette commande doit être executé sur un serveur.".to_string()),
+        };
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="avertissements", name="palier", description="Ajoute ou remplace un palier d'escalade")]
+    async fn com_escalation_step(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Nombre d'avertissements actifs déclenchant le palier", name="avertissements")]
+        warns: u64,
+        #[argument(description="Sanction appliquée (mute, kick ou ban)")]
+        sanction: String,
+        #[argument(description="Durée de la sanction. Définitive si absente")]
+        duree: Option<String>
+    ) {
+        let msg = match self.set_escalation_step(app_cmd.get_guild_id(), warns, sanction, duree).await {
+            Ok(v) => v,
+            Err(e) => message::error(e),
+        };
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="avertissements", name="retirer_palier", description="Retire un palier d'escalade")]
+    async fn com_escalation_remove(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Nombre d'avertissements du palier à retirer", name="avertissements")]
+        warns: u64
+    ) {
+        let msg = match app_cmd.get_guild_id() {
+            Some(guild_id) => {
+                let mut policy = self.escalation_policy(guild_id).await;
+                if policy.remove_step(warns as u32) {
+                    self.data.write().await.write().escalation.insert(guild_id, policy.clone());
+                    policy.to_message()
+                } else {
+                    message::error(format!("Aucun palier à {} avertissement(s)", warns))
+                }
+            },
+            None => message::error("Cette commande doit être executé sur un serveur.".to_string()),
+        };
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="avertissements", name="expiration", description="Définit la durée de validité des avertissements")]
+    async fn com_escalation_window(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Durée de validité d'un avertissement")]
+        duree: String
+    ) {
+        let msg = match (app_cmd.get_guild_id(), time::parse(duree)) {
+            (None, _) => message::error("Cette commande doit être executé sur un serveur.".to_string()),
+            (_, Err(e)) => message::error(format!("Impossible de parser la durée: {}", e)),
+            (_, Ok(0)) => message::error("La durée de validité doit être supérieure à 0"),
+            (Some(guild_id), Ok(window)) => {
+                let mut policy = self.escalation_policy(guild_id).await;
+                policy.window = window;
+                self.data.write().await.write().escalation.insert(guild_id, policy.clone());
+                policy.to_message()
+            }
+        };
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="sanctions", name="en_cours", description="Liste les sanctions temporaires en cours")]
+    async fn com_pending_sanctions(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>) {
+        let msg = match self.pending_sanctions(app_cmd.get_guild_id()).await {
+            Ok(v) => v,
+            Err(e) => message::error(e),
+        };
+        if let Err(e) = app_cmd.direct_response(ctx, msg.set_ephemeral(true)).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="sanctions", name="prolonger", description="Prolonge une sanction temporaire")]
+    async fn com_extend_sanction(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Identifiant de la sanction (voir /modo sanctions en_cours)")]
+        id: u64,
+        #[argument(description="Durée à ajouter")]
+        duree: String
+    ) {
+        let msg = match time::parse(duree) {
+            Ok(v) => self.edit_sanction(ctx, app_cmd.get_guild_id(), app_cmd.0.user.id, id, Duration::seconds(v as _)).await,
+            Err(e) => Err(format!("Impossible de parser la durée: {}", e)),
+        }.unwrap_or_else(message::error);
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="sanctions", name="raccourcir", description="Raccourcit une sanction temporaire")]
+    async fn com_shorten_sanction(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Identifiant de la sanction (voir /modo sanctions en_cours)")]
+        id: u64,
+        #[argument(description="Durée à retirer")]
+        duree: String
+    ) {
+        let msg = match time::parse(duree) {
+            Ok(v) => self.edit_sanction(ctx, app_cmd.get_guild_id(), app_cmd.0.user.id, id, -Duration::seconds(v as _)).await,
+            Err(e) => Err(format!("Impossible de parser la durée: {}", e)),
+        }.unwrap_or_else(message::error);
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="sanctions", name="annuler", description="Annule une sanction temporaire et la lève immédiatement")]
+    async fn com_cancel_sanction(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Identifiant de la sanction (voir /modo sanctions en_cours)")]
+        id: u64
+    ) {
+        let msg = self.cancel_sanction(ctx, app_cmd.get_guild_id(), app_cmd.0.user.id, id).await
+            .unwrap_or_else(message::error);
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
 }
 
 impl Moderation {
@@ -299,6 +597,20 @@ impl Moderation {
         self.do_sanction(ctx, sanction).await
     }
     #[inline]
+    pub async fn warn(&self, ctx: &Context, guild_id: GuildId, user_id: UserId, user_by: Option<UserId>, reason: String) -> Result<message::Message, String> {
+        let until = self.escalation_policy(guild_id).await.warn_expiry(Utc::now());
+        let sanction = Sanction {
+            user_id,
+            guild_id,
+            user_by: user_by.unwrap_or(ctx.cache.current_user_id()),
+            data: SanctionType::Warn{
+                until: Some(until),
+                reason
+            }
+        };
+        self.do_sanction(ctx, sanction).await
+    }
+    #[inline]
     pub async fn mute(&self, ctx: &Context, guild_id: GuildId, user_id: UserId, user_by: Option<UserId>, reason: String, until: Option<DateTime<Utc>>) -> Result<message::Message, String> {
         let sanction = Sanction {
             user_id,
@@ -306,7 +618,8 @@ impl Moderation {
             user_by: user_by.unwrap_or(ctx.cache.current_user_id()),
             data: SanctionType::Mute{
                 reason,
-                until
+                until,
+                mode: self.mute_mode(guild_id).await
             }
         };
         self.do_sanction(ctx, sanction).await
@@ -331,6 +644,24 @@ impl Moderation {
         };
         self.do_sanction(ctx, sanction).await
     }
+    /// Historique des sanctions d'un membre, de la plus récente à la plus ancienne.
+    async fn history(&self, ctx: &Context, guild_id: Option<GuildId>, member: UserId, page: usize) -> Result<message::Message, String> {
+        let guild_id = guild_id.ok_or_else(|| "Cette commande doit être executé sur un serveur.".to_string())?;
+        let mut entries = self.logger.read_all::<Sanction>().await?;
+        entries.retain(|(_, entry)| entry.data.guild_id == guild_id && entry.data.user_id == member);
+        entries.reverse();
+        let username = member.to_user(ctx).await
+            .map(|user| utils::user_fullname(&user))
+            .unwrap_or_else(|_| member.to_string());
+        Ok(history::history_message(member, &username, &entries, page))
+    }
+    async fn case(&self, guild_id: Option<GuildId>, case_id: CaseId) -> Result<message::Message, String> {
+        let guild_id = guild_id.ok_or_else(|| "Cette commande doit être executé sur un serveur.".to_string())?;
+        match self.logger.find::<Sanction>(case_id).await? {
+            Some(entry) if entry.data.guild_id == guild_id => Ok(history::case_message(case_id, &entry)),
+            _ => Err(format!("La case #{} n'existe pas sur ce serveur.", case_id)),
+        }
+    }
     async fn abort_last_sanction(&self, user_id: UserId, guild_id: GuildId) {
         match 
         {
@@ -341,7 +672,7 @@ impl Moderation {
                 .registry()
                 .lock().await;
             reg
-                .find_one(|v| v.data.user_id == user_id && v.data.guild_id == guild_id).await
+                .find_one(|v| v.data.user_id == user_id && v.data.guild_id == guild_id && !matches!(v.data.data, SanctionType::Warn{..})).await
                 .map(|(id, _)| id)
         } 
         // Some(2)
@@ -371,7 +702,26 @@ impl Moderation {
         
         Ok(top_role_by > top_role_to)
     }
+    /// Applique une sanction puis évalue la politique d'escalade si c'est un avertissement.
     async fn do_sanction(&self, ctx: &Context, sanction: Sanction) -> Result<message::Message, String> {
+        let is_warn = matches!(sanction.data, SanctionType::Warn{..});
+        let (user_id, guild_id) = (sanction.user_id(), sanction.guild_id());
+        let mut msg = self.execute_sanction(ctx, sanction).await?;
+        if !is_warn {
+            return Ok(msg);
+        }
+        if let Some(escalation) = self.escalate(ctx, guild_id, user_id).await {
+            match self.execute_sanction(ctx, escalation).await {
+                Ok(escalation_msg) => msg.embeds.extend(escalation_msg.embeds),
+                Err(e) => {
+                    log_error!("Escalade impossible pour le membre {}: {}", user_id, e);
+                    msg.embeds.extend(message::error(format!("Escalade automatique impossible: {}", e)).embeds);
+                }
+            }
+        }
+        Ok(msg)
+    }
+    async fn execute_sanction(&self, ctx: &Context, sanction: Sanction) -> Result<message::Message, String> {
         let user_id = sanction.user_id();
         let guild_id = sanction.guild_id();
         let user_by = sanction.user_by;
@@ -382,10 +732,12 @@ impl Moderation {
                 Err(e) => return Err(format!("Impossible de vérifier les roles des membres: {}", e.to_string())),
             }
         }
-        self.abort_last_sanction(user_id, guild_id).await;
+        if !matches!(sanction.data, SanctionType::Warn{..}) {
+            self.abort_last_sanction(user_id, guild_id).await;
+        }
 
         match sanction.data() {
-            SanctionType::Ban { .. } | SanctionType::Mute { .. } | SanctionType::Kick { .. } => {
+            SanctionType::Ban { .. } | SanctionType::Mute { .. } | SanctionType::Kick { .. } | SanctionType::Warn { .. } => {
                 let user = match user_id.to_user(&ctx).await {
                     Ok(v) => Some(v),
                     Err(_) => None
@@ -404,16 +756,17 @@ impl Moderation {
             }
             _ => ()
         }
-        match sanction.apply(ctx).await {
+        match sanction.apply(ctx, &self.settings).await {
             Ok(_) => (),
             Err(e) => return Err(format!("Impossible d'appliquer la sanction: {}", e.to_string())),
         };
-        if let Err(e) = self.logger.push(&sanction).await {
-            log_warn!("Impossible d'enregistrer la sanction dans les logs: {}", e.to_string());
+        let case_id = self.log_sanction(ctx, &sanction).await;
+        let mut msg = sanction.to_server_message(ctx).await;
+        if let (Some(case_id), Some(embed)) = (case_id, msg.last_embed_mut()) {
+            embed.footer(|f| f.text(format!("Case #{}", case_id)));
         }
-        let msg = sanction.to_server_message(ctx).await;
         match sanction {
-            Sanction { data: SanctionType::Ban { until: Some(until), .. } | SanctionType::Mute { until: Some(until), .. }, .. } => {
+            Sanction { data: SanctionType::Ban { until: Some(until), .. } | SanctionType::Mute { until: Some(until), .. } | SanctionType::Warn { until: Some(until), .. }, .. } => {
                 let mut tasks = self.tasks.write().await;
                 let tasks = tasks.as_mut().unwrap();
                 if let Err(e) = tasks.add(sanction, until).await {
@@ -424,6 +777,175 @@ impl Moderation {
         }
         Ok(msg)
     }
+    async fn pending_sanctions(&self, guild_id: Option<GuildId>) -> Result<message::Message, String> {
+        let guild_id = guild_id.ok_or_else(|| "Cette commande doit être executé sur un serveur.".to_string())?;
+        let mut pending = {
+            let tasks = self.tasks.read().await;
+            let reg = tasks
+                .as_ref()
+                .ok_or_else(|| "Le gestionnaire de sanctions n'est pas encore initialisé".to_string())?
+                .registry();
+            let reg = reg.lock().await;
+            reg.find_all(|v| v.data.guild_id == guild_id).await
+        };
+        if pending.is_empty() {
+            return Ok(message::info("Aucune sanction temporaire en cours"));
+        }
+        pending.sort_by_key(|(_, task)| task.until);
+        let mut msg = message::Message::new();
+        msg.add_embed(|embed| {
+            embed
+                .title("Sanctions temporaires en cours")
+                .color(message::COLOR_INFO);
+            if pending.len() > 25 {
+                embed.footer(|f| f.text(format!("{} sanctions affichées sur {}", 25, pending.len())));
+            }
+            for (id, task) in pending.iter().take(25) {
+                embed.field(
+                    format!("#{} · {}", id, task.data.name()),
+                    format!("<@{}>\nJusqu'au <t:{}:f> (<t:{}:R>)", task.data.user_id, task.until, task.until),
+                    true
+                );
+            }
+            embed
+        });
+        Ok(msg)
+    }
+    async fn find_pending_sanction(&self, guild_id: GuildId, id: task::TaskID) -> Result<Sanction, String> {
+        let task = {
+            let tasks = self.tasks.read().await;
+            let tasks = tasks
+                .as_ref()
+                .ok_or_else(|| "Le gestionnaire de sanctions n'est pas encore initialisé".to_string())?;
+            tasks.get(id).await
+        };
+        match task {
+            Some(task) if task.data.guild_id == guild_id => Ok(task.data),
+            _ => Err(format!("La sanction #{} n'existe pas ou est terminée", id)),
+        }
+    }
+    /// Décale la fin d'une sanction temporaire et enregistre la modification dans le journal.
+    async fn edit_sanction(&self, ctx: &Context, guild_id: Option<GuildId>, user_by: UserId, id: task::TaskID, delta: Duration) -> Result<message::Message, String> {
+        let guild_id = guild_id.ok_or_else(|| "Cette commande doit être executé sur un serveur.".to_string())?;
+        let mut sanction = self.find_pending_sanction(guild_id, id).await?;
+        let until = match sanction.until() {
+            Some(until) => until + delta,
+            None => return Err(format!("La sanction #{} n'est pas temporaire", id)),
+        };
+        sanction.set_until(until);
+        // Le timeout Discord a sa propre date de fin : il est réappliqué avec la nouvelle date
+        if let SanctionType::Mute{mode: MuteMode::Timeout, ..} = sanction.data {
+            Sanction::timeout_until(Some(&until))?;
+            if until > Utc::now() {
+                sanction.apply(ctx, &self.settings).await
+                    .map_err(|e| format!("Impossible de modifier le timeout: {}", e))?;
+            }
+        }
+        {
+            let mut tasks = self.tasks.write().await;
+            let tasks = tasks.as_mut().unwrap();
+            tasks.reschedule(id, sanction.clone(), until).await
+                .map_err(|e| format!("Impossible de replanifier la sanction: {}", e))?;
+        }
+        let edit = Sanction {
+            user_id: sanction.user_id,
+            guild_id,
+            user_by,
+            data: SanctionType::Edit {
+                task: id,
+                sanction: sanction.name().to_string(),
+                until: Some(until),
+            }
+        };
+        self.log_sanction(ctx, &edit).await;
+        Ok(message::success(format!("{} de <@{}> (#{}) jusqu'au <t:{}:f>", sanction.name(), sanction.user_id, id, until.timestamp())))
+    }
+    /// Annule une sanction temporaire, la lève immédiatement et enregistre l'annulation dans le journal.
+    async fn cancel_sanction(&self, ctx: &Context, guild_id: Option<GuildId>, user_by: UserId, id: task::TaskID) -> Result<message::Message, String> {
+        let guild_id = guild_id.ok_or_else(|| "Cette commande doit être executé sur un serveur.".to_string())?;
+        let sanction = self.find_pending_sanction(guild_id, id).await?;
+        {
+            let mut tasks = self.tasks.write().await;
+            let tasks = tasks.as_mut().unwrap();
+            tasks.remove(id).await
+                .map_err(|e| format!("Impossible de retirer la sanction: {}", e))?;
+        }
+        sanction.undo(ctx, &self.settings).await;
+        let edit = Sanction {
+            user_id: sanction.user_id,
+            guild_id,
+            user_by,
+            data: SanctionType::Edit {
+                task: id,
+                sanction: sanction.name().to_string(),
+                until: None,
+            }
+        };
+        self.log_sanction(ctx, &edit).await;
+        Ok(message::success(format!("{} de <@{}> (#{}) annulé", sanction.name(), sanction.user_id, id)))
+    }
+    async fn mute_mode(&self, guild_id: GuildId) -> MuteMode {
+        self.data.read().await.read().mute_mode.get(&guild_id).copied().unwrap_or_default()
+    }
+    async fn escalation_policy(&self, guild_id: GuildId) -> EscalationPolicy {
+        self.data.read().await.read().escalation.get(&guild_id).cloned().unwrap_or_default()
+    }
+    async fn set_escalation_step(&self, guild_id: Option<GuildId>, warns: u64, action: String, duration: Option<String>) -> Result<message::Message, String> {
+        let guild_id = guild_id.ok_or_else(|| "Cette commande doit être executé sur un serveur.".to_string())?;
+        if warns == 0 {
+            return Err("Le nombre d'avertissements doit être supérieur à 0".to_string());
+        }
+        let action = EscalationAction::parse(&action)?;
+        let duration = duration
+            .map(time::parse)
+            .transpose()
+            .map_err(|e| format!("Impossible de parser la durée: {}", e))?;
+        let mut policy = self.escalation_policy(guild_id).await;
+        policy.set_step(EscalationStep {
+            warns: warns as u32,
+            action,
+            duration
+        });
+        self.data.write().await.write().escalation.insert(guild_id, policy.clone());
+        Ok(policy.to_message())
+    }
+    /// Compte les avertissements actifs d'un membre et retourne la sanction 
+    /// à appliquer si un palier de la politique d'escalade est atteint.
+    async fn escalate(&self, ctx: &Context, guild_id: GuildId, user_id: UserId) -> Option<Sanction> {
+        let nb_warns = {
+            let tasks = self.tasks.read().await;
+            let reg = tasks
+                .as_ref()?
+                .registry();
+            let reg = reg.lock().await;
+            reg
+                .find_all(|v| v.data.user_id == user_id && v.data.guild_id == guild_id && matches!(v.data.data, SanctionType::Warn{..})).await
+                .len()
+        };
+        let policy = self.escalation_policy(guild_id).await;
+        // Le dernier palier appliqué ne compte plus si des avertissements ont expiré ou ont été retirés depuis
+        let previous = self.data.read().await.read().escalated
+            .get(&guild_id)
+            .and_then(|escalated| escalated.get(&user_id))
+            .map_or(0, |&v| v as usize)
+            .min(nb_warns.saturating_sub(1));
+        let step = policy.step_for(previous, nb_warns)?;
+        self.data.write().await.write().escalated.entry(guild_id).or_default().insert(user_id, step.warns);
+        log_info!("Escalade du membre {}: {}", user_id, step);
+        let reason = format!("Escalade automatique : {} avertissements actifs", nb_warns);
+        let until = step.duration.map(|v| Utc::now() + Duration::seconds(v as _));
+        let data = match step.action {
+            EscalationAction::Mute => SanctionType::Mute { until, reason, mode: self.mute_mode(guild_id).await },
+            EscalationAction::Kick => SanctionType::Kick { reason },
+            EscalationAction::Ban => SanctionType::Ban { until, historique: 0, reason },
+        };
+        Some(Sanction {
+            user_id,
+            guild_id,
+            user_by: ctx.cache.current_user_id(),
+            data
+        })
+    }
     #[inline]
     async fn duration_to_datetime(ctx: &Context, app_cmd: &ApplicationCommandEmbed<'_>, duration_str: Option<String>) -> Option<Option<DateTime<Utc>>> {
         let res = duration_str
@@ -463,6 +985,14 @@ impl Moderation {
             log_warn!("Audit entry is too old");
             return Ok(());
         }
+        {
+            let mut audit_seen = self.audit_seen.lock().await;
+            let last_seen = audit_seen.entry((guild_id, action_type)).or_insert(AuditLogEntryId(0));
+            if audit_entry.id <= *last_seen {
+                return Ok(());
+            }
+            *last_seen = audit_entry.id;
+        }
         let data = match action_type {
             22 => SanctionType::Ban{
                 until: None,
@@ -479,9 +1009,13 @@ impl Moderation {
                     Some(changes) => changes,
                     None => return Err("No changes found for mute in a mute event".into())
                 };
+                let mute_role = match self.settings.mute_role(ctx, guild_id).await {
+                    Ok(role) => role,
+                    Err(_) => return Ok(())
+                };
                 let is_mute = match changes.iter().filter_map(|change| match change {
                     Change::RolesAdded{new: Some(roles), ..} | Change::RolesRemove{old: Some(roles), ..} => {
-                        roles.iter().find(|role| role.name == sanction::ROLE_MUTED)
+                        roles.iter().find(|role| role.id == mute_role)
                             .and(Some(matches!(change, Change::RolesAdded{..})))
                     },
                     _ => None
@@ -493,21 +1027,153 @@ impl Moderation {
                     SanctionType::Mute{
                         until: None,
                         reason: audit_entry.reason.clone().unwrap_or_default(),
+                        mode: MuteMode::Role,
                     }
                 } else {
                     SanctionType::Unmute
                 }
             }
+            24 => {
+                use serenity::model::guild::audit_log::Change;
+                let changes = match &audit_entry.changes {
+                    Some(changes) => changes,
+                    None => return Ok(())
+                };
+                let until = match changes.iter().find_map(|change| match change {
+                    Change::CommunicationDisabledUntil{new, ..} => Some(new),
+                    _ => None
+                }) {
+                    Some(until) => until,
+                    None => return Ok(())
+                };
+                match until {
+                    Some(until) if until.unix_timestamp() > Utc::now().timestamp() => SanctionType::Mute{
+                        until: Utc.timestamp_opt(until.unix_timestamp(), 0).single(),
+                        reason: audit_entry.reason.clone().unwrap_or_default(),
+                        mode: MuteMode::Timeout,
+                    },
+                    _ => SanctionType::Unmute
+                }
+            }
             _ => unreachable!()
         };
         if audit_entry.user_id == self.bot_id.lock().await.0 {
             return Ok(());
         }
-        self.logger.push(&Sanction{
+        self.log_sanction(ctx, &Sanction{
             user_id,
             guild_id,
             user_by: audit_entry.user_id,
             data
-        }).await
+        }).await;
+        Ok(())
+    }
+    /// Enregistre une sanction dans le journal de modération puis 
+    /// l'envoie dans le salon des logs du serveur s'il est configuré.
+    async fn log_sanction(&self, ctx: &Context, sanction: &Sanction) -> Option<CaseId> {
+        sanction.log(ctx, &self.logger, &self.settings).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cddio_core::testing::{Harness, Reply, fixtures::{self, CommandBuilder}};
+
+    /// Composant de modération dont les sanctions temporaires sont chargées, comme après `Ready`.
+    /// 
+    /// Chaque test a son propre journal et son propre registre, les tests s'exécutant en parallèle.
+    async fn moderation(harness: &Harness) -> Moderation {
+        let mut moderation = Moderation::new(Arc::new(Settings::new(Vec::new())));
+        let id = fixtures::next_id();
+        moderation.logger = Arc::new(log_audit::Log::new(DATA_DIR.join(format!("moderation-{}.json", id))));
+        let registry = RegistryFile::from_file(DATA_DIR.join(format!("moderation2-{}.json", id))).await.unwrap();
+        let persistent = (harness.context().clone(), Arc::clone(&moderation.settings), Arc::clone(&moderation.logger));
+        *moderation.tasks.write().await = Some(task::TaskManager::new(registry, persistent));
+        moderation
     }
-}
\ No newline at end of file
+
+    fn warn(user_id: u64, reason: &str) -> Sanction {
+        Sanction {
+            user_id: UserId(user_id),
+            guild_id: GuildId(fixtures::GUILD_ID),
+            user_by: UserId(fixtures::USER_ID),
+            data: SanctionType::Warn { until: None, reason: reason.to_string() },
+        }
+    }
+
+    #[tokio::test]
+    async fn warn_is_applied_and_logged() {
+        let harness = Harness::new().await.unwrap();
+        let moderation = moderation(&harness).await;
+        let (staff_role, target) = (fixtures::next_id(), fixtures::next_id());
+        harness.cache_guild(fixtures::guild(fixtures::GUILD_ID, &[fixtures::role(staff_role, "modo")]));
+        harness.mock.respond("GET", &format!("/guilds/*/members/{}", target), Reply::Json(200, fixtures::member(target, &[])));
+        harness.mock.respond("GET", &format!("/guilds/*/members/{}", fixtures::USER_ID), Reply::Json(200, fixtures::member(fixtures::USER_ID, &[staff_role])));
+        harness.mock.respond("GET", &format!("/users/{}", target), Reply::Json(200, fixtures::user(target)));
+
+        let command = CommandBuilder::new("warn")
+            .user("qui", target)
+            .string("raison", "Spam")
+            .author(fixtures::USER_ID, &[staff_role]);
+        harness.command(&moderation, command).await;
+
+        let edits = harness.mock.find("PATCH", "/webhooks/*/*/messages/@original");
+        assert_eq!(edits.len(), 1);
+        let embed = &edits[0].body["embeds"][0];
+        assert_eq!(embed["title"], "Warn");
+        assert_eq!(embed["description"], format!("user{} a été averti", target));
+        assert!(embed["footer"]["text"].as_str().unwrap().starts_with("Case #"));
+        // Le membre est prévenu en message privé
+        assert_eq!(harness.mock.find("POST", "/users/@me/channels").len(), 1);
+
+        let entries = moderation.logger.read_all::<Sanction>().await.unwrap();
+        assert!(entries.iter().any(|(_, entry)| entry.data.user_id == UserId(target) && entry.data.reason() == Some("Spam")));
+        let tasks = moderation.tasks.read().await;
+        let registry = tasks.as_ref().unwrap().registry();
+        let registry = registry.lock().await;
+        assert!(registry.find_one(|task| task.data.user_id == UserId(target)).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn warn_requires_a_higher_role() {
+        let harness = Harness::new().await.unwrap();
+        let moderation = moderation(&harness).await;
+        let target = fixtures::next_id();
+        harness.mock.respond("GET", "/guilds/*/members/*", Reply::Json(200, fixtures::member(target, &[])));
+
+        let command = CommandBuilder::new("warn").user("qui", target).string("raison", "Spam");
+        harness.command(&moderation, command).await;
+
+        let edits = harness.mock.find("PATCH", "/webhooks/*/*/messages/@original");
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].body["embeds"][0]["title"], "Erreur");
+        assert!(harness.mock.find("POST", "/users/@me/channels").is_empty());
+        let entries = moderation.logger.read_all::<Sanction>().await.unwrap();
+        assert!(entries.iter().all(|(_, entry)| entry.data.user_id != UserId(target)));
+    }
+
+    #[tokio::test]
+    async fn history_lists_the_member_sanctions() {
+        let harness = Harness::new().await.unwrap();
+        let moderation = moderation(&harness).await;
+        let (target, other) = (fixtures::next_id(), fixtures::next_id());
+        for sanction in [warn(target, "Premier"), warn(other, "Autre membre"), warn(target, "Second")] {
+            moderation.logger.push(&sanction).await.unwrap();
+        }
+        harness.mock.respond("GET", &format!("/users/{}", target), Reply::Json(200, fixtures::user(target)));
+
+        harness.command(&moderation, CommandBuilder::new("modo").subcommand("historique").user("membre", target)).await;
+
+        let edits = harness.mock.find("PATCH", "/webhooks/*/*/messages/@original");
+        assert_eq!(edits.len(), 1);
+        let embed = &edits[0].body["embeds"][0];
+        assert_eq!(embed["title"], format!("Historique de user{}#0001", target));
+        assert_eq!(embed["description"], format!("<@{}> : 2 sanction(s) enregistrée(s)", target));
+        let fields = embed["fields"].as_array().unwrap();
+        assert_eq!(fields.len(), 2);
+        // Les sanctions les plus récentes sont affichées en premier
+        assert!(fields[0]["value"].as_str().unwrap().contains("Second"));
+        assert!(fields[1]["value"].as_str().unwrap().contains("Premier"));
+    }
+}
@@ -3,14 +3,18 @@ use crate::{log_error, log_warn};
 use cddio_core::{message, ApplicationCommandEmbed};
 use serenity::{
     client::Context,
-    model::id::*, 
+    model::{id::*, Timestamp}, 
     async_trait
 };
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use super::utils;
-pub const ROLE_MUTED: &str = "muted";
+use super::Settings;
+/// Durée maximale d'une exclusion temporaire Discord (28 jours).
+const MAX_TIMEOUT_DAYS: i64 = 28;
 
 use super::task;
+use super::log_audit::{CaseId, Log};
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Sanction {
@@ -32,12 +36,58 @@ pub enum SanctionType {
         #[serde_as(as = "Option<serde_with::TimestampSeconds>")]
         until: Option<DateTime<Utc>>,
         reason: String,
+        #[serde(default)]
+        mode: MuteMode,
     },
     Kick {
         reason: String,
     },
+    Warn {
+        /// Date d'expiration de l'avertissement
+        #[serde_as(as = "Option<serde_with::TimestampSeconds>")]
+        until: Option<DateTime<Utc>>,
+        reason: String,
+    },
     Unban,
     Unmute,
+    /// Modification d'une sanction temporaire en cours.
+    Edit {
+        /// Identifiant de la tâche modifiée
+        task: task::TaskID,
+        /// Nom de la sanction modifiée
+        sanction: String,
+        /// Nouvelle date de fin. Absente si la sanction a été annulée.
+        #[serde_as(as = "Option<serde_with::TimestampSeconds>")]
+        until: Option<DateTime<Utc>>,
+    },
+}
+
+/// Méthode utilisée pour rendre muet un membre.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub enum MuteMode {
+    /// Ajout du rôle mute configuré (voir `/config role_mute`) au membre.
+    #[default]
+    Role,
+    /// Exclusion temporaire native de Discord (timeout).
+    /// 
+    /// Limitée à 28 jours et nécessite une durée.
+    Timeout,
+}
+
+impl MuteMode {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "role" | "rôle" => Ok(MuteMode::Role),
+            "timeout" => Ok(MuteMode::Timeout),
+            _ => Err(format!("Mode \"{}\" inconnu, attendu: role, timeout", name)),
+        }
+    }
+    pub const fn name(&self) -> &'static str {
+        match self {
+            MuteMode::Role => "rôle",
+            MuteMode::Timeout => "timeout",
+        }
+    }
 }
 
 impl Sanction {
@@ -54,8 +104,10 @@ impl Sanction {
             SanctionType::Ban{..} => "Ban",
             SanctionType::Mute{..} => "Mute",
             SanctionType::Kick{..} => "Kick",
-            SanctionType::Unban{..} => "Unban",
-            SanctionType::Unmute{..} => "Unmute",
+            SanctionType::Warn{..} => "Warn",
+            SanctionType::Unban => "Unban",
+            SanctionType::Unmute => "Unmute",
+            SanctionType::Edit{..} => "Edit",
         }
     }
     pub const fn preterite(&self) -> &'static str {
@@ -63,60 +115,74 @@ impl Sanction {
             SanctionType::Ban{..} => "banni",
             SanctionType::Mute{..} => "mute",
             SanctionType::Kick{..} => "kick",
-            SanctionType::Unban{..} => "débanni",
-            SanctionType::Unmute{..} => "démute",
+            SanctionType::Warn{..} => "averti",
+            SanctionType::Unban => "débanni",
+            SanctionType::Unmute => "démute",
+            SanctionType::Edit{..} => "modifié",
         }
     }
-    pub async fn apply(&self, ctx: &Context) -> serenity::Result<()> {
+    pub async fn apply(&self, ctx: &Context, settings: &Settings) -> serenity::Result<()> {
         let (guild_id, user_id) = (self.guild_id, self.user_id);
         match &self.data {
             SanctionType::Ban{historique, reason, ..} => {
                 guild_id.ban_with_reason(ctx, user_id, *historique, reason).await
             },
-            SanctionType::Mute{..} => {
-                let role = guild_id
-                    .roles(ctx).await?
-                    .into_iter()
-                    .find(|(_, r)| r.name == ROLE_MUTED)
-                    .map(|(id, _)| id);
-                match role {
-                    Some(role) => {
+            SanctionType::Mute{mode: MuteMode::Role, ..} => {
+                match settings.mute_role(ctx, guild_id).await {
+                    Ok(role) => {
                         let mut member = guild_id.member(ctx, user_id).await?;
                         member.add_role(ctx, role).await?;
                         Ok(())
                     },
-                    None => {
-                        log_warn!("Impossible de trouver le rôle \"{}\" dans le serveur {}", ROLE_MUTED, guild_id);
-                        Err(serenity::Error::Other("Impossible de trouver le rôle \"muted\" dans le serveur"))
+                    Err(e) => {
+                        log_warn!("Serveur {}: {}", guild_id, e);
+                        Err(serenity::Error::Other("Le rôle mute n'est pas configuré. Utilisez `/config role_mute`."))
                     }
                 }
             },
+            SanctionType::Mute{mode: MuteMode::Timeout, until, ..} => {
+                let until = Self::timeout_until(until.as_ref()).map_err(serenity::Error::Other)?;
+                let mut member = guild_id.member(ctx, user_id).await?;
+                member.disable_communication_until_datetime(ctx, until).await
+            },
             SanctionType::Kick{reason} => {
                 guild_id.kick_with_reason(ctx, user_id, reason).await
             },
+            SanctionType::Warn{..} | SanctionType::Edit{..} => Ok(()),
             SanctionType::Unban => {
                 guild_id.unban(ctx, user_id).await
             },
             SanctionType::Unmute => {
-                let role = guild_id
-                    .roles(ctx).await?
-                    .into_iter()
-                    .find(|(_, r)| r.name == ROLE_MUTED)
-                    .map(|(id, _)| id);
-                match role {
-                    Some(role) => {
-                        let mut member = guild_id.member(ctx, user_id).await?;
+                // Le membre peut avoir été mute par l'une ou l'autre des méthodes
+                let mut member = guild_id.member(ctx, user_id).await?;
+                let timed_out = member.communication_disabled_until.is_some();
+                if timed_out {
+                    member.enable_communication(ctx).await?;
+                }
+                match settings.mute_role(ctx, guild_id).await {
+                    Ok(role) if member.roles.contains(&role) => {
                         member.remove_role(ctx, role).await?;
                         Ok(())
                     },
-                    None => {
-                        log_warn!("Impossible de trouver le rôle \"muted\" dans le serveur {}", guild_id);
-                        Err(serenity::Error::Other("Impossible de trouver le rôle \"muted\" dans le serveur"))
+                    Ok(_) => Ok(()),
+                    Err(_) if timed_out => Ok(()),
+                    Err(e) => {
+                        log_warn!("Serveur {}: {}", guild_id, e);
+                        Err(serenity::Error::Other("Le rôle mute n'est pas configuré. Utilisez `/config role_mute`."))
                     }
                 } 
             }
         }
     }
+    /// Date de fin d'un timeout Discord, limitée à [`MAX_TIMEOUT_DAYS`] jours.
+    pub fn timeout_until(until: Option<&DateTime<Utc>>) -> Result<Timestamp, &'static str> {
+        let until = match until {
+            Some(until) if *until - Utc::now() <= chrono::Duration::days(MAX_TIMEOUT_DAYS) => until,
+            _ => return Err("Un timeout nécessite une durée de 28 jours maximum")
+        };
+        Timestamp::from_unix_timestamp(until.timestamp())
+            .map_err(|_| "Date de fin du timeout invalide")
+    }
     pub fn user_id(&self) -> UserId {
         self.user_id
     }
@@ -126,6 +192,33 @@ impl Sanction {
     pub fn data(&self) -> &SanctionType {
         &self.data
     }
+    /// Raison de la sanction, si elle en a une.
+    pub fn reason(&self) -> Option<&str> {
+        match &self.data {
+            SanctionType::Ban{reason, ..} 
+            | SanctionType::Mute{reason, ..} 
+            | SanctionType::Kick{reason} 
+            | SanctionType::Warn{reason, ..} => Some(reason.as_str()),
+            _ => None
+        }
+    }
+    /// Date de fin de la sanction, si elle est temporaire.
+    pub fn until(&self) -> Option<DateTime<Utc>> {
+        match &self.data {
+            SanctionType::Ban{until, ..} 
+            | SanctionType::Mute{until, ..} 
+            | SanctionType::Warn{until, ..} 
+            | SanctionType::Edit{until, ..} => *until,
+            _ => None
+        }
+    }
+    /// Modifie la date de fin de la sanction si elle est temporaire.
+    pub fn set_until(&mut self, new_until: DateTime<Utc>) {
+        match &mut self.data {
+            SanctionType::Ban{until, ..} | SanctionType::Mute{until, ..} | SanctionType::Warn{until, ..} => *until = Some(new_until),
+            _ => ()
+        }
+    }
     #[inline]
     pub async fn to_user_message(&self, ctx: &Context) -> message::Message {
         let guild_id = self.guild_id();
@@ -186,6 +279,12 @@ impl Sanction {
                 SanctionType::Kick{reason, ..} => {
                     e.field("Raison", reason, true);
                 },
+                SanctionType::Warn{until, reason} => {
+                    if let Some(until) = until {
+                        e.field("Expire", Self::format_date(until), true);
+                    }
+                    e.field("Raison", reason, true);
+                },
                 _ => ()
             }
             e
@@ -195,57 +294,79 @@ impl Sanction {
     async fn username(ctx: &Context, user_id: UserId) -> String {
         user_id.to_user(ctx).await.map(|user| utils::user_fullname(&user)).unwrap_or_else(|_| user_id.to_string())
     }
-    pub async fn to_log(&self, ctx: &Context, user_by: UserId) -> Result<String, std::fmt::Error> {
-        use std::fmt::Write;
-        let mut log = String::new();
-        let now = chrono::Local::now();
-        let user_by = Self::username(ctx, user_by).await;
-        write!(log, "{:=<10}\n", "")?;
-        write!(log, "When: {}\n", now.format("%d/%m/%Y %H:%M:%S"))?;
-        write!(log, "By: {}\n", user_by)?;
-        let user_id = self.user_id();
-        match &self.data {
-            SanctionType::Ban{ until, reason, ..} => {
-                let user = Self::username(ctx, user_id).await;
-                write!(log, "What: {}\n", "Ban")?;
-                write!(log, "Who: {}\n", user)?;
-                write!(log, "Why: {}\n", reason)?;
-                if let Some(until) = until {
-                    write!(log, "Until: {}\n", until)?;
-                }
-            },
-            SanctionType::Mute{ until, reason, ..} => {
-                let user = Self::username(ctx, user_id).await;
-                write!(log, "What: {}\n", "Mute")?;
-                write!(log, "Who: {}\n", user)?;
-                write!(log, "Why: {}\n", reason)?;
-                if let Some(until) = until {
-                    write!(log, "Until: {}\n", until)?;
-                }
-            },
-            SanctionType::Kick{ reason, ..} => {
-                let user = Self::username(ctx, user_id).await;
-                write!(log, "What: {}\n", "Kick")?;
-                write!(log, "Who: {}\n", user)?;
-                write!(log, "Why: {}\n", reason)?;
-            },
-            SanctionType::Unban => {
-                let user = Self::username(ctx, user_id).await;
-                write!(log, "What: {}\n", "Unban")?;
-                write!(log, "Who: {}\n", user)?;
-            },
-            SanctionType::Unmute => {
-                let user = Self::username(ctx, user_id).await;
-                write!(log, "What: {}\n", "Unmute")?;
-                write!(log, "Who: {}\n", user)?;
+    /// Message du salon des logs de modération.
+    pub async fn to_log_message(&self, ctx: &Context, case_id: CaseId) -> message::Message {
+        let color = match &self.data {
+            SanctionType::Ban{..} | SanctionType::Kick{..} => message::COLOR_ERROR,
+            SanctionType::Mute{..} | SanctionType::Warn{..} => message::COLOR_WARN,
+            SanctionType::Unban | SanctionType::Unmute => message::COLOR_SUCCESS,
+            SanctionType::Edit{..} => message::COLOR_INFO,
+        };
+        let user = Self::username(ctx, self.user_id).await;
+        let mut m = message::Message::new();
+        m.add_embed(|e| {
+            e.title(format!("Case #{} · {}", case_id, self.name()));
+            e.color(color);
+            e.field("Membre", format!("<@{}> ({})", self.user_id, user), true);
+            e.field("Modérateur", format!("<@{}>", self.user_by), true);
+            match &self.data {
+                SanctionType::Ban{until, ..} | SanctionType::Mute{until, ..} => {
+                    let duration = match until {
+                        Some(until) => format!("Jusqu'au {}", Self::format_date(until)),
+                        None => "Définitive".to_string(),
+                    };
+                    e.field("Durée", duration, true);
+                },
+                SanctionType::Warn{until: Some(until), ..} => {
+                    e.field("Expire", Self::format_date(until), true);
+                },
+                SanctionType::Edit{task, sanction, until} => {
+                    let edit = match until {
+                        Some(until) => format!("{} (tâche #{}) replanifié jusqu'au {}", sanction, task, Self::format_date(until)),
+                        None => format!("{} (tâche #{}) annulé", sanction, task),
+                    };
+                    e.field("Modification", edit, false);
+                },
+                _ => ()
+            }
+            if let SanctionType::Mute{mode, ..} = &self.data {
+                e.field("Méthode", mode.name(), true);
+            }
+            if let Some(reason) = self.reason() {
+                e.field("Raison", if reason.is_empty() { "*Aucune raison*" } else { reason }, false);
+            }
+            e.timestamp(Timestamp::now());
+            e
+        });
+        m
+    }
+    /// Enregistre la sanction dans le journal de modération puis
+    /// l'envoie dans le salon des logs du serveur s'il est configuré.
+    pub async fn log(&self, ctx: &Context, logger: &Log, settings: &Settings) -> Option<CaseId> {
+        let case_id = match logger.push(self).await {
+            Ok(case_id) => case_id,
+            Err(e) => {
+                log_warn!("Impossible d'enregistrer la sanction dans les logs: {}", e);
+                return None;
+            }
+        };
+        if let Some(channel) = settings.log_channel(self.guild_id).await {
+            let msg = self.to_log_message(ctx, case_id).await;
+            if let Err(e) = channel.send_message(ctx, |create_msg| {
+                *create_msg = msg.into();
+                create_msg
+            }).await {
+                log_warn!("Impossible d'envoyer la sanction dans le salon des logs {}: {}", channel, e);
             }
         }
-        Ok(log)
+        Some(case_id)
     }
-    async fn undo(&self, ctx: &Context) {
+    pub async fn undo(&self, ctx: &Context, settings: &Settings) {
         let result = match self.data {
-            SanctionType::Ban{..} => Sanction{data: SanctionType::Unban, ..*self}.apply(ctx).await,
-            SanctionType::Mute{..} => Sanction{data: SanctionType::Unmute, ..*self}.apply(ctx).await,
+            SanctionType::Ban{..} => Sanction{data: SanctionType::Unban, ..*self}.apply(ctx, settings).await,
+            SanctionType::Mute{..} => Sanction{data: SanctionType::Unmute, ..*self}.apply(ctx, settings).await,
+            // L'avertissement expire simplement, rien à rétablir.
+            SanctionType::Warn{..} => Ok(()),
             _ => Err(serenity::Error::Other("Sanction impossible à annuler."))
         };
         if let Err(e) = result {
@@ -255,8 +376,16 @@ impl Sanction {
 }
 #[async_trait]
 impl task::DataFunc for Sanction {
-    type Persistent = Context;
-    async fn run(&self, ctx: &Context) -> Result<(), String> {
-        Ok(self.undo(ctx).await)
+    type Persistent = (Context, Arc<Settings>, Arc<Log>);
+    async fn run(&self, (ctx, settings, logger): &(Context, Arc<Settings>, Arc<Log>)) -> Result<(), String> {
+        self.undo(ctx, settings).await;
+        // La levée automatique d'un ban ou d'un mute est journalisée au nom du bot
+        let data = match self.data {
+            SanctionType::Ban{..} => SanctionType::Unban,
+            SanctionType::Mute{..} => SanctionType::Unmute,
+            _ => return Ok(()),
+        };
+        Sanction{data, user_by: ctx.cache.current_user_id(), ..*self}.log(ctx, logger, settings).await;
+        Ok(())
     }
 }
\ No newline at end of file
@@ -0,0 +1,274 @@
+//! Configuration des serveurs
+//!
+//! Les autres composants lisent ici les rôles et salons propres à chaque serveur
+//! plutôt que de les chercher par leur nom.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use crate::{log_error, log_warn};
+use futures_locks::RwLock;
+use cddio_core::{message, ApplicationCommandEmbed};
+use cddio_macros::component;
+use serde::{Serialize, Deserialize};
+use serenity::{
+    client::Context,
+    model::id::*,
+};
+
+use super::utils::{self, data::Data};
+
+/// Nom du rôle staff utilisé avant la configuration par serveur
+const LEGACY_STAFF_ROLE: &str = "staff";
+/// Nom du rôle mute utilisé avant la configuration par serveur
+const LEGACY_MUTE_ROLE: &str = "muted";
+
+/// Le composant de configuration des serveurs
+pub struct Settings {
+    /// Propriétaires du bot, autorisés à modifier la configuration
+    owners: Vec<UserId>,
+    /// Données persistantes
+    data: RwLock<Data<DataSettings>>,
+    /// Rôles par défaut déjà signalés dans les logs, par serveur
+    legacy_warned: Mutex<HashSet<(GuildId, &'static str)>>,
+}
+
+/// Données persistantes du composant
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct DataSettings {
+    /// Configuration de chaque serveur
+    #[serde(default)]
+    guilds: HashMap<GuildId, GuildSettings>,
+}
+
+/// Configuration d'un serveur
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct GuildSettings {
+    /// Rôle du staff, ayant accès aux tickets
+    #[serde(default)]
+    pub staff_role: Option<RoleId>,
+    /// Rôle attribué aux membres mute
+    #[serde(default)]
+    pub mute_role: Option<RoleId>,
+    /// Salon des logs de modération
+    #[serde(default)]
+    pub log_channel: Option<ChannelId>,
+    /// Salon où sont envoyées les archives des tickets
+    #[serde(default)]
+    pub ticket_archive_channel: Option<ChannelId>,
+    /// Envoi de la transcription d'un ticket à son créateur lors de la fermeture
+    #[serde(default)]
+    pub ticket_transcript_dm: bool,
+}
+
+impl GuildSettings {
+    fn to_message(&self) -> message::Message {
+        fn role(role: Option<RoleId>) -> String {
+            role.map(|v| format!("<@&{}>", v)).unwrap_or_else(|| "*Non défini*".to_string())
+        }
+        fn channel(channel: Option<ChannelId>) -> String {
+            channel.map(|v| format!("<#{}>", v)).unwrap_or_else(|| "*Non défini*".to_string())
+        }
+        let mut msg = message::Message::new();
+        msg.add_embed(|embed| {
+            embed
+                .title("Configuration du serveur")
+                .color(message::COLOR_INFO)
+                .field("Rôle staff", role(self.staff_role), true)
+                .field("Rôle mute", role(self.mute_role), true)
+                .field("Salon des logs", channel(self.log_channel), true)
+                .field("Salon des archives de tickets", channel(self.ticket_archive_channel), true)
+                .field("Transcription en message privé", if self.ticket_transcript_dm { "Activée" } else { "Désactivée" }, true)
+        });
+        msg
+    }
+}
+
+impl Settings {
+    pub fn new(owners: Vec<UserId>) -> Self {
+        Self {
+            owners,
+            data: RwLock::new(Data::from_file("settings").unwrap()),
+            legacy_warned: Mutex::new(HashSet::new()),
+        }
+    }
+    /// Configuration d'un serveur. Les valeurs non définies sont à `None`.
+    pub async fn get(&self, guild_id: GuildId) -> GuildSettings {
+        self.data.read().await.read().guilds.get(&guild_id).cloned().unwrap_or_default()
+    }
+    /// Rôle du staff du serveur.
+    ///
+    /// À défaut de configuration, le rôle nommé "staff" est utilisé.
+    /// Retourne une erreur explicite si aucun des deux n'existe.
+    pub async fn staff_role(&self, ctx: &Context, guild_id: GuildId) -> Result<RoleId, String> {
+        self.role_or_legacy(ctx, guild_id, self.get(guild_id).await.staff_role, LEGACY_STAFF_ROLE).await
+            .ok_or_else(|| "Le rôle staff n'est pas configuré. Utilisez `/config role_staff`.".to_string())
+    }
+    /// Rôle attribué aux membres mute.
+    ///
+    /// À défaut de configuration, le rôle nommé "muted" est utilisé.
+    /// Retourne une erreur explicite si aucun des deux n'existe.
+    pub async fn mute_role(&self, ctx: &Context, guild_id: GuildId) -> Result<RoleId, String> {
+        self.role_or_legacy(ctx, guild_id, self.get(guild_id).await.mute_role, LEGACY_MUTE_ROLE).await
+            .ok_or_else(|| "Le rôle mute n'est pas configuré. Utilisez `/config role_mute`.".to_string())
+    }
+    /// Rôle configuré, ou à défaut le rôle nommé `legacy_name` que le bot utilisait
+    /// avant la configuration par serveur. L'utilisation du rôle par défaut est signalée une fois par serveur.
+    async fn role_or_legacy(&self, ctx: &Context, guild_id: GuildId, role: Option<RoleId>, legacy_name: &'static str) -> Option<RoleId> {
+        if role.is_some() {
+            return role;
+        }
+        let roles = match ctx.cache.guild_roles(guild_id) {
+            Some(roles) => roles,
+            None => match guild_id.roles(ctx).await {
+                Ok(roles) => roles,
+                Err(e) => {
+                    log_warn!("Serveur {}: impossible de récupérer les rôles: {}", guild_id, e);
+                    return None;
+                }
+            }
+        };
+        let role = roles.into_iter().find(|(_, role)| role.name == legacy_name).map(|(id, _)| id)?;
+        if self.legacy_warned.lock().unwrap().insert((guild_id, legacy_name)) {
+            log_warn!("Serveur {}: rôle non configuré, utilisation du rôle \"{}\" ({})", guild_id, legacy_name, role);
+        }
+        Some(role)
+    }
+    /// Salon des logs de modération, s'il est configuré.
+    pub async fn log_channel(&self, guild_id: GuildId) -> Option<ChannelId> {
+        self.get(guild_id).await.log_channel
+    }
+    /// Salon des archives de tickets, s'il est configuré.
+    pub async fn ticket_archive_channel(&self, guild_id: GuildId) -> Option<ChannelId> {
+        self.get(guild_id).await.ticket_archive_channel
+    }
+    async fn edit<F: FnOnce(&mut GuildSettings)>(&self, app_cmd: &ApplicationCommandEmbed<'_>, f: F) -> message::Message {
+        let guild_id = match app_cmd.get_guild_id() {
+            Some(guild_id) => guild_id,
+            None => return message::error("Cette commande doit être executé sur un serveur."),
+        };
+        if !utils::is_admin(app_cmd, &self.owners) {
+            return message::error("Seuls les administrateurs du serveur peuvent modifier la configuration.");
+        }
+        let mut data = self.data.write().await;
+        let mut data = data.write();
+        let settings = data.guilds.entry(guild_id).or_default();
+        f(settings);
+        settings.to_message()
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl cddio_core::ComponentLifecycle for Settings {}
+
+#[component]
+#[group(name="config", description="Configuration du bot sur le serveur")]
+impl Settings {
+    #[command(group="config", name="afficher", description="Affiche la configuration du serveur")]
+    async fn com_show(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>) {
+        let msg = match app_cmd.get_guild_id() {
+            Some(guild_id) => self.get(guild_id).await.to_message(),
+            None => message::error("Cette commande doit être executé sur un serveur."),
+        };
+        if let Err(e) = app_cmd.direct_response(ctx, msg.set_ephemeral(true)).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="config", name="role_staff", description="Définit le rôle du staff. Sans argument, retire le rôle.")]
+    async fn com_staff_role(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Rôle du staff")]
+        role: Option<RoleId>
+    ) {
+        let msg = self.edit(&app_cmd, |v| v.staff_role = role).await;
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="config", name="role_mute", description="Définit le rôle attribué aux membres mute. Sans argument, retire le rôle.")]
+    async fn com_mute_role(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Rôle des membres mute")]
+        role: Option<RoleId>
+    ) {
+        let msg = self.edit(&app_cmd, |v| v.mute_role = role).await;
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="config", name="salon_logs", description="Définit le salon des logs de modération. Sans argument, retire le salon.")]
+    async fn com_log_channel(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Salon des logs")]
+        salon: Option<ChannelId>
+    ) {
+        let msg = self.edit(&app_cmd, |v| v.log_channel = salon).await;
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="config", name="salon_archives", description="Définit le salon des archives de tickets. Sans argument, retire le salon.")]
+    async fn com_ticket_archive_channel(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Salon des archives de tickets")]
+        salon: Option<ChannelId>
+    ) {
+        let msg = self.edit(&app_cmd, |v| v.ticket_archive_channel = salon).await;
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+    #[command(group="config", name="transcription_mp", description="Active l'envoi de la transcription d'un ticket à son créateur lors de la fermeture")]
+    async fn com_ticket_transcript_dm(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Envoyer la transcription en message privé ?")]
+        actif: bool
+    ) {
+        let msg = self.edit(&app_cmd, |v| v.ticket_transcript_dm = actif).await;
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            log_error!("{}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cddio_core::testing::{Harness, Reply, fixtures::{self, CommandBuilder}};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn editing_requires_an_administrator() {
+        let harness = Harness::new().await.unwrap();
+        let settings = Settings::default();
+        let role = fixtures::next_id();
+        harness.command(&settings, CommandBuilder::new("config").subcommand("role_staff").role("role", role).permissions(0)).await;
+
+        let responses = harness.mock.find("POST", "/interactions/*/*/callback");
+        assert_eq!(responses[0].body["data"]["embeds"][0]["title"], "Erreur");
+        assert_eq!(settings.get(GuildId(fixtures::GUILD_ID)).await.staff_role, None);
+    }
+
+    #[tokio::test]
+    async fn legacy_roles_are_used_when_not_configured() {
+        let harness = Harness::new().await.unwrap();
+        let settings = Settings::default();
+        let (staff, muted) = (fixtures::next_id(), fixtures::next_id());
+        harness.mock.respond("GET", "/guilds/*/roles", Reply::Json(200, json!([
+            fixtures::role(staff, "staff"),
+            fixtures::role(muted, "muted"),
+        ])));
+        let guild_id = GuildId(fixtures::GUILD_ID);
+
+        assert_eq!(settings.staff_role(harness.context(), guild_id).await, Ok(RoleId(staff)));
+        assert_eq!(settings.mute_role(harness.context(), guild_id).await, Ok(RoleId(muted)));
+    }
+
+    #[tokio::test]
+    async fn missing_roles_are_reported() {
+        let harness = Harness::new().await.unwrap();
+        let settings = Settings::default();
+        harness.mock.respond("GET", "/guilds/*/roles", Reply::Json(200, json!([])));
+
+        assert!(settings.mute_role(harness.context(), GuildId(fixtures::GUILD_ID)).await.unwrap_err().contains("/config role_mute"));
+    }
+}
@@ -1,19 +1,29 @@
 //! Internal slash declaration handler
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use cddio_macros::component;
-use cddio_core::{self as core, message, ApplicationCommandEmbed, declarative::Node};
+use cddio_core::{self as core, message, ApplicationCommandEmbed, declarative::Node, Component};
+use serde::{Deserialize, Serialize};
 use serenity::model::event::ReadyEvent;
 use serenity::model::application::command::CommandPermissionType;
 use serenity::prelude::*;
 use serenity::model::id::{UserId, ApplicationId, CommandId, GuildId};
+use super::utils::data::Data;
+
+/// Données persistantes du composant
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct DataComponents {
+    /// Composants désactivés sur chaque serveur
+    disabled: HashMap<GuildId, HashSet<String>>,
+}
 
 pub struct SlashCommand {
     app_id: ApplicationId,
     container: core::container::RefContainer,
     owners: Vec<UserId>,
-    application_commands: RwLock<HashMap<GuildId,HashMap<String, CommandId>>>
+    application_commands: RwLock<HashMap<GuildId,HashMap<String, CommandId>>>,
+    data: RwLock<Data<DataComponents>>,
 }
 
 impl SlashCommand {
@@ -22,57 +32,58 @@ impl SlashCommand {
             app_id,
             container,
             owners,
-            application_commands: RwLock::new(HashMap::new())
+            application_commands: RwLock::new(HashMap::new()),
+            data: RwLock::new(Data::from_file("components").unwrap()),
+        }
+    }
+    /// Désactive dans le conteneur les composants désactivés enregistrés.
+    pub async fn restore_components(&self, container: &mut core::ComponentContainer) {
+        let data = self.data.read().await;
+        for (guild_id, names) in &data.read().disabled {
+            for name in names {
+                container.set_enabled(*guild_id, name, false);
+            }
         }
     }
 }
 
+impl core::ComponentLifecycle for SlashCommand {}
+
 #[component]
 #[group(name="slash", description="Gestion des commandes slash")]
 #[group(name="permissions", description="Gérer les permissions des commandes", parent="slash")]
+#[group(name="components", description="Gestion des composants du bot")]
 impl SlashCommand {
     #[event(Ready)]
     async fn on_ready(&self, ctx: &Context, ready: &ReadyEvent) {
-        let container = self.container.read().await;
-        let mut list_declarative = Vec::<&'static Node>::new();
-        let mut app_cmds = HashMap::new();
-        #[cfg(feature = "show_markdown")]
         {
-            let mut markdown = "# Commandes Slash\n\n".to_string();
+            let container = self.container.read().await;
+            #[cfg(feature = "show_markdown")]
+            {
+                let mut markdown = "# Commandes Slash\n\n".to_string();
+                for cont in container.as_ref() {
+                    if let Some(node) = cont.declarative() {
+                        markdown.push_str(&format!("{}", node.to_markdown()));
+                    }
+                }
+                println!("{}", markdown);
+            }
+            #[cfg(debug_assertions)]
             for cont in container.as_ref() {
                 if let Some(node) = cont.declarative() {
-                    markdown.push_str(&format!("{}", node.to_markdown()));
+                    node.iter_flat().for_each(|(fullname, item)| println!("|{}| {}", fullname, item));
                 }
             }
-            println!("{}", markdown);
-        }
-        for cont in container.as_ref() {
-            if let Some(node) = cont.declarative() {
-                list_declarative.push(node);
-                #[cfg(debug_assertions)]
-                node.iter_flat().for_each(|(fullname, item)| println!("|{}| {}", fullname, item));
-            }
         }
         for guild in &ready.ready.guilds {
-            let status = guild.id.set_application_commands(ctx, |v| {
-                list_declarative.iter().for_each(|node| node.add_application_command(v));
-                v
-            }).await;
             let guild_name= guild.id.name(ctx).or_else(|| Some(guild.id.0.to_string())).unwrap();
-            match status {
-                Ok(guild_app_cmds) => {
-                    let guild_app_cmds = guild_app_cmds.into_iter().map(|app_cmd| {
-                        (app_cmd.name.to_string(), app_cmd.id)
-                    }).collect();
-                    app_cmds.insert(guild.id, guild_app_cmds);
-                    println!("Application commands added to {}", guild_name)
-                },
+            match self.register_commands(ctx, guild.id).await {
+                Ok(_) => println!("Application commands added to {}", guild_name),
                 Err(why) => {
                     println!("Error while setting application commands to \"{}\": {:?}", guild_name, why);
                 }
             }
         }
-        *self.application_commands.write().await = app_cmds;
     }
     #[command(name="list", description="Liste les permissions des commandes sur le serveur", group="permissions")]
     async fn permissions_list(
@@ -134,7 +145,44 @@ impl SlashCommand {
         };
         delayed.send().await.unwrap();
     }
-
+    #[command(name="list", description="Liste les composants du bot et leur état sur le serveur", group="components")]
+    async fn components_list(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>) {
+        let msg = match app_cmd.get_guild_id() {
+            Some(guild_id) => {
+                let container = self.container.read().await;
+                let list = container.as_ref().iter()
+                    .map(|comp| match container.is_enabled(guild_id, comp.name()) {
+                        true => format!("✅ {}", comp.name()),
+                        false => format!("❌ {}", comp.name()),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let mut msg = message::success(list);
+                if let Some(embed) = msg.last_embed_mut() {
+                    embed.title("Composants du bot");
+                }
+                msg
+            },
+            None => message::error("Cette commande doit être executé sur un serveur."),
+        };
+        if let Err(e) = app_cmd.direct_response(ctx, msg).await {
+            println!("Error while sending the message: {:?}", e);
+        }
+    }
+    #[command(name="enable", description="Active un composant sur le serveur", group="components")]
+    async fn components_enable(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Nom du composant")]
+        composant: String
+    ) {
+        self.set_component(ctx, app_cmd, composant, true).await;
+    }
+    #[command(name="disable", description="Désactive un composant sur le serveur", group="components")]
+    async fn components_disable(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Nom du composant")]
+        composant: String
+    ) {
+        self.set_component(ctx, app_cmd, composant, false).await;
+    }
 }
 
 impl SlashCommand {
@@ -148,4 +196,141 @@ impl SlashCommand {
             result
         }).collect::<Vec<_>>().join("\n")
     }
-}
\ No newline at end of file
+    /// Déclare sur le serveur les commandes des composants qui y sont activés.
+    async fn register_commands(&self, ctx: &Context, guild_id: GuildId) -> serenity::Result<()> {
+        let list_declarative = {
+            let container = self.container.read().await;
+            container.enabled_components(guild_id)
+                .filter_map(|cont| cont.declarative())
+                .collect::<Vec<&'static Node>>()
+        };
+        let guild_app_cmds = guild_id.set_application_commands(ctx, |v| {
+            list_declarative.iter().for_each(|node| node.add_application_command(v));
+            v
+        }).await?;
+        let guild_app_cmds = guild_app_cmds.into_iter().map(|app_cmd| {
+            (app_cmd.name.to_string(), app_cmd.id)
+        }).collect();
+        self.application_commands.write().await.insert(guild_id, guild_app_cmds);
+        Ok(())
+    }
+    /// Active ou désactive un composant sur le serveur de la commande, puis redéclare les commandes du serveur.
+    async fn set_component(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>, name: String, enabled: bool) {
+        let mut delayed = match app_cmd.delayed_response(ctx, false).await {
+            Ok(delayed) => delayed,
+            Err(why) => {
+                println!("Error while sending the message: {:?}", why);
+                return;
+            }
+        };
+        delayed.message = Some(match self.toggle_component(ctx, &app_cmd, name.to_lowercase(), enabled).await {
+            Ok(msg) => message::success(msg),
+            Err(msg) => message::error(msg),
+        });
+        if let Err(why) = delayed.send().await {
+            println!("Error while sending the message: {:?}", why);
+        }
+    }
+    async fn toggle_component(&self, ctx: &Context, app_cmd: &ApplicationCommandEmbed<'_>, name: String, enabled: bool) -> Result<String, String> {
+        let guild_id = app_cmd.get_guild_id().ok_or_else(|| "Cette commande doit être executé sur un serveur.".to_string())?;
+        if !super::utils::is_admin(app_cmd, &self.owners) {
+            return Err("Seuls les administrateurs du serveur peuvent gérer les composants.".to_string());
+        }
+        if !enabled && name == self.name() {
+            return Err(format!("Le composant {} ne peut pas être désactivé.", name));
+        }
+        {
+            let mut container = self.container.write().await;
+            if container.find(&name).is_none() {
+                let names = container.as_ref().iter().map(|comp| comp.name()).collect::<Vec<_>>().join(", ");
+                return Err(format!("Composant \"{}\" inconnu. Composants disponibles : {}", name, names));
+            }
+            if !container.set_enabled(guild_id, &name, enabled) {
+                return Ok(format!("Le composant {} est déjà {}.", name, if enabled { "activé" } else { "désactivé" }));
+            }
+        }
+        {
+            let mut data = self.data.write().await;
+            let mut data = data.write();
+            let disabled = data.disabled.entry(guild_id).or_default();
+            match enabled {
+                true => disabled.remove(&name),
+                false => disabled.insert(name.clone()),
+            };
+            if disabled.is_empty() {
+                data.disabled.remove(&guild_id);
+            }
+        }
+        let state = if enabled { "activé" } else { "désactivé" };
+        match self.register_commands(ctx, guild_id).await {
+            Ok(_) => Ok(format!("Le composant {} est {} sur le serveur.", name, state)),
+            Err(e) => Err(format!("Le composant {} est {}, mais les commandes du serveur n'ont pas pu être mises à jour : {}", name, state, e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cddio_core::testing::{Harness, fixtures::{self, CommandBuilder}};
+    use futures_locks::RwLock;
+
+    async fn slash() -> (core::container::RefContainer, std::sync::Arc<SlashCommand>) {
+        let container = RwLock::new(core::ComponentContainer::new());
+        let slash = {
+            let mut cont = container.write().await;
+            cont.add_component(super::super::DalleMini);
+            cont.add_component(SlashCommand::new(ApplicationId(fixtures::APPLICATION_ID), container.clone(), Vec::new()))
+        };
+        (container, slash)
+    }
+
+    fn toggle(command: &str, component: &str) -> CommandBuilder {
+        CommandBuilder::new("components").subcommand(command).string("composant", component)
+    }
+
+    async fn response(harness: &Harness) -> serde_json::Value {
+        let edits = harness.mock.find("PATCH", "/webhooks/*/*/messages/@original");
+        assert_eq!(edits.len(), 1);
+        edits[0].body["embeds"][0].clone()
+    }
+
+    #[tokio::test]
+    async fn list_shows_the_components_state() {
+        let harness = Harness::new().await.unwrap();
+        let (_, slash) = slash().await;
+        harness.command(slash.as_ref(), CommandBuilder::new("components").subcommand("list")).await;
+
+        let responses = harness.mock.find("POST", "/interactions/*/*/callback");
+        assert_eq!(responses[0].body["data"]["embeds"][0]["description"], "✅ dallemini\n✅ slashcommand");
+    }
+
+    #[tokio::test]
+    async fn toggling_requires_an_administrator() {
+        let harness = Harness::new().await.unwrap();
+        let (container, slash) = slash().await;
+        harness.command(slash.as_ref(), toggle("disable", "dallemini").permissions(0)).await;
+
+        assert_eq!(response(&harness).await["title"], "Erreur");
+        assert!(container.read().await.is_enabled(GuildId(fixtures::GUILD_ID), "dallemini"));
+    }
+
+    #[tokio::test]
+    async fn slash_component_cannot_be_disabled() {
+        let harness = Harness::new().await.unwrap();
+        let (_, slash) = slash().await;
+        harness.command(slash.as_ref(), toggle("disable", "slashcommand")).await;
+
+        assert_eq!(response(&harness).await["description"], "Le composant slashcommand ne peut pas être désactivé.");
+        assert!(harness.mock.find("PUT", "/applications/*/guilds/*/commands").is_empty());
+    }
+
+    #[tokio::test]
+    async fn unknown_components_are_listed() {
+        let harness = Harness::new().await.unwrap();
+        let (_, slash) = slash().await;
+        harness.command(slash.as_ref(), toggle("enable", "Inconnu")).await;
+
+        assert_eq!(response(&harness).await["description"], "Composant \"inconnu\" inconnu. Composants disponibles : dallemini, slashcommand");
+    }
+}
@@ -0,0 +1,106 @@
+//! Accès aux tickets
+//!
+//! En plus du créateur et du staff, des membres ou des rôles peuvent être ajoutés à un ticket,
+//! avec un accès complet ou en lecture seule. Chaque ajout et retrait est journalisé
+//! puis enregistré dans l'archive du ticket.
+
+use serde::{Deserialize, Serialize};
+use serenity::model::{
+    channel::{PermissionOverwrite, PermissionOverwriteType},
+    id::*,
+    permissions::Permissions,
+};
+
+/// Membre ou rôle ayant accès à un ticket
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessTarget {
+    Member(UserId),
+    Role(RoleId),
+}
+
+impl AccessTarget {
+    pub fn overwrite_type(&self) -> PermissionOverwriteType {
+        match self {
+            AccessTarget::Member(user_id) => PermissionOverwriteType::Member(*user_id),
+            AccessTarget::Role(role_id) => PermissionOverwriteType::Role(*role_id),
+        }
+    }
+}
+
+impl std::fmt::Display for AccessTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccessTarget::Member(user_id) => write!(f, "<@{}>", user_id),
+            AccessTarget::Role(role_id) => write!(f, "<@&{}>", role_id),
+        }
+    }
+}
+
+/// Niveau d'accès à un ticket
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessLevel {
+    /// Lecture des messages uniquement
+    ReadOnly,
+    /// Lecture et envoi de messages
+    Participate,
+}
+
+impl AccessLevel {
+    pub fn from_read_only(read_only: bool) -> Self {
+        if read_only { AccessLevel::ReadOnly } else { AccessLevel::Participate }
+    }
+    pub const fn name(&self) -> &'static str {
+        match self {
+            AccessLevel::ReadOnly => "lecture seule",
+            AccessLevel::Participate => "participation",
+        }
+    }
+}
+
+/// Accès accordé à un ticket
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TicketAccess {
+    pub target: AccessTarget,
+    pub level: AccessLevel,
+}
+
+impl TicketAccess {
+    /// Permissions du salon correspondant à l'accès.
+    pub fn overwrite(&self) -> PermissionOverwrite {
+        let (allow, deny) = match self.level {
+            AccessLevel::Participate => (Permissions::VIEW_CHANNEL, Permissions::default()),
+            AccessLevel::ReadOnly => (
+                Permissions::VIEW_CHANNEL | Permissions::READ_MESSAGE_HISTORY,
+                Permissions::SEND_MESSAGES | Permissions::ADD_REACTIONS,
+            ),
+        };
+        PermissionOverwrite {
+            allow,
+            deny,
+            kind: self.target.overwrite_type(),
+        }
+    }
+}
+
+/// Ajout ou retrait d'un accès à un ticket
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccessEvent {
+    pub target: AccessTarget,
+    /// Niveau d'accès accordé, `None` si l'accès a été retiré
+    pub level: Option<AccessLevel>,
+    /// Auteur de la modification
+    pub by: UserId,
+    /// Date de la modification (timestamp unix)
+    pub timestamp: i64,
+}
+
+impl AccessEvent {
+    pub fn new(target: AccessTarget, level: Option<AccessLevel>, by: UserId) -> Self {
+        Self {
+            target,
+            level,
+            by,
+            timestamp: chrono::Utc::now().timestamp(),
+        }
+    }
+}
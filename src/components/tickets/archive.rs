@@ -1,3 +1,6 @@
+use std::path::{Path, PathBuf};
+use super::transcript;
+use super::access::AccessEvent;
 use serenity::{
     client::Context, 
     model::{
@@ -5,13 +8,14 @@ use serenity::{
         channel::Channel,
     }
 };
-mod intern {
+pub mod intern {
     use crate::log_warn;
     use std::collections::HashSet;
 
     use futures::StreamExt;
-    use serde::Serialize;
+    use serde::{Serialize, Deserialize};
     use serenity::client::Context;
+    use super::super::access::AccessEvent;
     mod ser {
         pub use serenity::{
             model::{
@@ -21,7 +25,7 @@ mod intern {
         };
     }
 
-    #[derive(Serialize, PartialEq, Eq, Hash)]
+    #[derive(Serialize, Deserialize, PartialEq, Eq, Hash)]
     pub struct ArchiveUser {
         pub id: u64,
         pub avatar: String,
@@ -36,7 +40,7 @@ mod intern {
             }
         }
     }
-    #[derive(Serialize)]
+    #[derive(Serialize, Deserialize)]
     pub struct ArchiveMessage {
         pub id: u64,
         pub user_id: u64,
@@ -57,12 +61,15 @@ mod intern {
             }
         }
     }
-    #[derive(Serialize)]
+    #[derive(Serialize, Deserialize)]
     pub struct ArchiveChannel {
         pub id: u64,
         pub name: String,
         pub users: Vec<ArchiveUser>,
         pub messages: Vec<ArchiveMessage>,
+        /// Ajouts et retraits de membres et de rôles au ticket
+        #[serde(default)]
+        pub access_log: Vec<AccessEvent>,
     }
     impl ArchiveChannel {
         pub async fn from_channel(ctx: &Context, channel: ser::GuildChannel) -> Self {
@@ -83,23 +90,78 @@ mod intern {
                 name: channel.name.clone(),
                 users: users.into_iter().collect(),
                 messages: messages,
+                access_log: Vec::new(),
             }
         }
     }
 }
 
-pub async fn archive_ticket(ctx: &Context, channel: ChannelId) -> serenity::Result<()> {
-    const ARCHIVE_PATH: &str = "./data/tickets/archives";
+/// Résultat de l'archivage d'un ticket.
+pub struct ArchivedTicket {
+    /// Contenu de l'archive
+    pub archive: intern::ArchiveChannel,
+    /// Transcription HTML lisible
+    pub html: PathBuf,
+}
 
+/// Archive le ticket dans le dossier `folder`.
+/// 
+/// L'archive est enregistrée au format JSON, accompagnée de sa transcription HTML.
+/// L'historique des accès au ticket `access_log` est joint à l'archive.
+pub async fn archive_ticket(ctx: &Context, channel: ChannelId, folder: &Path, access_log: Vec<AccessEvent>) -> serenity::Result<ArchivedTicket> {
     let channel = match channel.to_channel(ctx).await? {
         Channel::Guild(channel) => channel,
         _ => unreachable!()
     };
     let name = channel.name.clone();
     let id = channel.id.0;
-    let archive = serde_json::to_string(&intern::ArchiveChannel::from_channel(ctx, channel).await).unwrap();
-    let path = format!("{}/{}-{}.json", ARCHIVE_PATH, id, name);
-    async_std::fs::write(path, archive).await?;
+    let mut archive = intern::ArchiveChannel::from_channel(ctx, channel).await;
+    archive.access_log = access_log;
+    async_std::fs::create_dir_all(folder).await?;
+    let json = folder.join(format!("{}-{}.json", id, name));
+    let html = folder.join(format!("{}-{}.html", id, name));
+    async_std::fs::write(&json, serde_json::to_string(&archive).unwrap()).await?;
+    async_std::fs::write(&html, transcript::render(&archive)).await?;
+
+    Ok(ArchivedTicket { archive, html })
+}
 
-    Ok(())
-}
\ No newline at end of file
+/// Archives JSON du dossier `folder`, avec l'identifiant de leur ticket.
+pub async fn list_archives(folder: &Path) -> Result<Vec<(ChannelId, PathBuf)>, String> {
+    use futures::StreamExt;
+    let mut entries = async_std::fs::read_dir(folder).await
+        .map_err(|e| format!("Impossible de lire le dossier des archives: {}", e))?;
+    let mut archives = Vec::new();
+    while let Some(entry) = entries.next().await {
+        let path = match entry {
+            Ok(entry) => PathBuf::from(entry.path().into_os_string()),
+            Err(_) => continue,
+        };
+        // Les archives sont nommées `<id>-<nom>.json`
+        let id = path.file_name()
+            .and_then(|name| name.to_str())
+            .filter(|name| name.ends_with(".json"))
+            .and_then(|name| name.split('-').next())
+            .and_then(|id| id.parse::<u64>().ok());
+        if let Some(id) = id {
+            archives.push((ChannelId(id), path));
+        }
+    }
+    Ok(archives)
+}
+
+/// Charge une archive JSON.
+pub async fn read_archive(path: &Path) -> Result<intern::ArchiveChannel, String> {
+    let content = async_std::fs::read_to_string(path).await
+        .map_err(|e| format!("Impossible de lire l'archive {}: {}", path.display(), e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Archive {} invalide: {}", path.display(), e))
+}
+
+/// Charge l'archive JSON du ticket `id` depuis le dossier `folder`.
+pub async fn load_archive(folder: &Path, id: u64) -> Result<intern::ArchiveChannel, String> {
+    match list_archives(folder).await?.into_iter().find(|(archive_id, _)| archive_id.0 == id) {
+        Some((_, path)) => read_archive(&path).await,
+        None => Err(format!("Aucune archive trouvée pour le ticket {}", id)),
+    }
+}
@@ -0,0 +1,97 @@
+//! Fermeture automatique des tickets inactifs.
+//!
+//! Chaque ticket d'une catégorie ayant une politique d'inactivité possède deux tâches :
+//! un rappel au créateur du ticket puis la fermeture du ticket.
+//! Les tâches sont repoussées à chaque nouveau message dans le ticket.
+
+use std::sync::Weak;
+
+use crate::{log_info, log_warn};
+use serde::{Deserialize, Serialize};
+use serenity::{
+    async_trait,
+    client::Context,
+    model::id::*,
+};
+
+use super::Tickets;
+use super::super::utils::{task, time_parser as time};
+
+/// Politique d'inactivité d'une catégorie de ticket
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct InactivityPolicy {
+    /// Délai sans message avant le rappel, en secondes
+    pub reminder_after: u64,
+    /// Délai après le rappel avant la fermeture du ticket, en secondes
+    pub close_after: u64,
+}
+
+impl InactivityPolicy {
+    /// Délai depuis le dernier message avant l'exécution d'une tâche, en secondes.
+    pub fn delay(&self, kind: TimerKind) -> u64 {
+        match kind {
+            TimerKind::Reminder => self.reminder_after,
+            TimerKind::Close => self.reminder_after + self.close_after,
+        }
+    }
+}
+
+impl std::fmt::Display for InactivityPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Rappel après {} d'inactivité, fermeture {} plus tard",
+            time::format_duration(self.reminder_after),
+            time::format_duration(self.close_after)
+        )
+    }
+}
+
+/// Type de tâche d'inactivité
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerKind {
+    /// Mentionne le créateur du ticket
+    Reminder,
+    /// Archive et ferme le ticket
+    Close,
+}
+
+/// Tâche d'inactivité d'un ticket
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TicketTimer {
+    pub channel_id: ChannelId,
+    pub owner: UserId,
+    pub policy: InactivityPolicy,
+    pub kind: TimerKind,
+}
+
+#[async_trait]
+impl task::DataFunc for TicketTimer {
+    type Persistent = (Context, Weak<Tickets>);
+    /// Les erreurs sont seulement journalisées: le salon a pu être supprimé entre temps
+    /// et la tâche ne doit pas être relancée à chaque démarrage.
+    async fn run(&self, (ctx, tickets): &(Context, Weak<Tickets>)) -> Result<(), String> {
+        match self.kind {
+            TimerKind::Reminder => {
+                let content = format!(
+                    "<@{}>, ce ticket est inactif depuis {}. Sans nouveau message, il sera fermé dans {}.",
+                    self.owner,
+                    time::format_duration(self.policy.reminder_after),
+                    time::format_duration(self.policy.close_after)
+                );
+                if let Err(e) = self.channel_id.say(ctx, content).await {
+                    log_warn!("Impossible d'envoyer le rappel d'inactivité du ticket {}: {}", self.channel_id, e);
+                }
+            },
+            TimerKind::Close => {
+                let tickets = match tickets.upgrade() {
+                    Some(tickets) => tickets,
+                    None => return Ok(()),
+                };
+                log_info!("Fermeture du ticket inactif {}", self.channel_id);
+                if let Err(e) = tickets.close_channel(ctx, self.channel_id).await {
+                    log_warn!("Impossible de fermer le ticket inactif {}: {}", self.channel_id, e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,295 @@
+//! Index des archives de tickets
+//!
+//! Chaque ticket archivé est ajouté à l'index avec ses métadonnées et un ensemble borné
+//! des mots de ses messages, pour pouvoir être retrouvé sans relire les fichiers d'archive.
+
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use serenity::model::id::*;
+
+use super::archive::intern::ArchiveChannel;
+
+/// Nombre maximal de mots indexés par ticket
+const MAX_TERMS: usize = 1000;
+/// Longueur minimale d'un mot indexé, en caractères
+const MIN_TERM_LEN: usize = 3;
+/// Longueur maximale d'un mot indexé, en caractères
+const MAX_TERM_LEN: usize = 32;
+
+/// Ticket archivé
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IndexEntry {
+    /// Identifiant du salon du ticket
+    pub id: ChannelId,
+    /// Nom du salon du ticket
+    pub name: String,
+    /// Serveur du ticket
+    pub guild_id: GuildId,
+    /// Créateur du ticket
+    pub owner: UserId,
+    /// Nom de la catégorie du ticket
+    pub category: String,
+    /// Date de création du ticket (timestamp unix)
+    pub opened_at: i64,
+    /// Date de fermeture du ticket (timestamp unix)
+    pub closed_at: i64,
+    /// Auteurs des messages du ticket
+    pub participants: Vec<UserId>,
+    /// Mots les plus fréquents des messages, en minuscules et triés
+    ///
+    /// Les index enregistrés avant cette limite contiennent le texte complet des messages (`text`),
+    /// converti à la lecture.
+    #[serde(default, alias = "text", deserialize_with = "deserialize_terms")]
+    pub terms: Vec<String>,
+    /// Lien vers la transcription envoyée dans le salon des archives
+    #[serde(default)]
+    pub transcript: Option<String>,
+}
+
+impl IndexEntry {
+    pub fn new(archive: &ArchiveChannel, guild_id: GuildId, owner: UserId, category: String, closed_at: i64) -> Self {
+        let id = ChannelId(archive.id);
+        Self {
+            id,
+            name: archive.name.clone(),
+            guild_id,
+            owner,
+            category,
+            opened_at: id.created_at().unix_timestamp(),
+            closed_at,
+            participants: archive.users.iter().map(|user| UserId(user.id)).collect(),
+            terms: terms(archive.messages.iter().map(|message| message.content.as_str())),
+            transcript: None,
+        }
+    }
+}
+
+/// Mots indexés d'un texte : les [`MAX_TERMS`] mots les plus fréquents, en minuscules et triés.
+fn terms<'a>(texts: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut counts = HashMap::<String, usize>::new();
+    for text in texts {
+        let words = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| (MIN_TERM_LEN..=MAX_TERM_LEN).contains(&word.chars().count()));
+        for word in words {
+            *counts.entry(word.to_lowercase()).or_default() += 1;
+        }
+    }
+    let mut terms = counts.into_iter().collect::<Vec<_>>();
+    terms.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+    let mut terms = terms.into_iter().take(MAX_TERMS).map(|(term, _)| term).collect::<Vec<_>>();
+    terms.sort();
+    terms
+}
+
+/// Première personne mentionnée dans le ticket, c'est-à-dire son créateur dans le message de présentation.
+pub fn first_mention(archive: &ArchiveChannel) -> Option<UserId> {
+    let mut messages = archive.messages.iter().collect::<Vec<_>>();
+    messages.sort_by_key(|message| (message.timestamp, message.id));
+    messages.into_iter().find_map(|message| {
+        message.content.split("<@").skip(1).find_map(|mention| {
+            let mention = mention.strip_prefix('!').unwrap_or(mention);
+            mention.split('>').next()?.parse::<u64>().ok().map(UserId)
+        })
+    })
+}
+
+/// Lit les mots indexés, ou le texte complet des index enregistrés avant la limite du nombre de mots.
+fn deserialize_terms<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Terms {
+        Terms(Vec<String>),
+        Text(String),
+    }
+    Ok(match Terms::deserialize(deserializer)? {
+        Terms::Terms(terms) => terms,
+        Terms::Text(text) => terms(std::iter::once(text.as_str())),
+    })
+}
+
+/// Index des tickets archivés
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct ArchiveIndex {
+    #[serde(default)]
+    pub entries: Vec<IndexEntry>,
+}
+
+impl ArchiveIndex {
+    /// Le ticket est dans l'index.
+    pub fn contains(&self, id: ChannelId) -> bool {
+        self.entries.iter().any(|entry| entry.id == id)
+    }
+    /// Ajoute un ticket à l'index, en remplaçant une éventuelle entrée du même ticket.
+    pub fn insert(&mut self, entry: IndexEntry) {
+        self.entries.retain(|e| e.id != entry.id);
+        self.entries.push(entry);
+    }
+    /// Tickets archivés correspondant à la recherche, du plus récent au plus ancien.
+    pub fn search(&self, query: &SearchQuery) -> Vec<&IndexEntry> {
+        let mut results = self.entries.iter()
+            .filter(|entry| query.matches(entry))
+            .collect::<Vec<_>>();
+        results.sort_by_key(|entry| std::cmp::Reverse(entry.closed_at));
+        results
+    }
+}
+
+/// Filtres de recherche dans l'index
+#[derive(Debug, Default)]
+pub struct SearchQuery {
+    pub guild_id: GuildId,
+    /// Créateur ou participant du ticket
+    pub user: Option<UserId>,
+    /// Nom de la catégorie
+    pub category: Option<String>,
+    /// Tickets fermés à partir de cette date (timestamp unix)
+    pub from: Option<i64>,
+    /// Tickets ouverts jusqu'à cette date (timestamp unix)
+    pub to: Option<i64>,
+    /// Mots devant tous apparaître dans les mots indexés des messages, en minuscules
+    pub keywords: Vec<String>,
+}
+
+impl SearchQuery {
+    /// Construit une recherche à partir des arguments de la commande.
+    /// 
+    /// Les dates sont au format `JJ/MM/AAAA` et les mots clés séparés par des espaces.
+    pub fn new(guild_id: GuildId, user: Option<UserId>, category: Option<String>, from: Option<String>, to: Option<String>, keywords: Option<String>) -> Result<Self, String> {
+        Ok(Self {
+            guild_id,
+            user,
+            category,
+            from: from.map(|date| parse_date(&date, false)).transpose()?,
+            to: to.map(|date| parse_date(&date, true)).transpose()?,
+            keywords: keywords
+                .map(|keywords| keywords.to_lowercase().split_whitespace().map(String::from).collect())
+                .unwrap_or_default(),
+        })
+    }
+    fn matches(&self, entry: &IndexEntry) -> bool {
+        entry.guild_id == self.guild_id
+            && self.user.map(|user| entry.owner == user || entry.participants.contains(&user)).unwrap_or(true)
+            && self.category.as_ref().map(|category| entry.category.eq_ignore_ascii_case(category)).unwrap_or(true)
+            && self.from.map(|from| entry.closed_at >= from).unwrap_or(true)
+            && self.to.map(|to| entry.opened_at <= to).unwrap_or(true)
+            && self.keywords.iter().all(|keyword| entry.terms.iter().any(|term| term.contains(keyword.as_str())))
+    }
+}
+
+/// Lit une date au format `JJ/MM/AAAA`.
+///
+/// Retourne le timestamp du début de la journée, ou de sa fin si `end_of_day` est vrai.
+fn parse_date(date: &str, end_of_day: bool) -> Result<i64, String> {
+    let date = NaiveDate::parse_from_str(date.trim(), "%d/%m/%Y")
+        .map_err(|_| format!("Date invalide: {}. Format attendu: JJ/MM/AAAA", date))?;
+    let time = match end_of_day {
+        true => date.and_hms_opt(23, 59, 59),
+        false => date.and_hms_opt(0, 0, 0),
+    }.ok_or_else(|| format!("Date invalide: {}", date))?;
+    Ok(Utc.from_utc_datetime(&time).timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::archive::intern::{ArchiveMessage, ArchiveUser};
+
+    const GUILD: GuildId = GuildId(1);
+
+    fn message(id: u64, user_id: u64, content: &str) -> ArchiveMessage {
+        ArchiveMessage { id, user_id, content: content.to_string(), attachments: Vec::new(), in_reply_to: None, timestamp: id as i64 }
+    }
+
+    fn entry(id: u64, owner: u64, category: &str, opened_at: i64, closed_at: i64, text: &str) -> IndexEntry {
+        let archive = ArchiveChannel {
+            id,
+            name: format!("ticket-{}", id),
+            users: vec![ArchiveUser { id: 50, avatar: String::new(), name: "Staff#0001".to_string() }],
+            messages: vec![message(1, owner, text)],
+            access_log: Vec::new(),
+        };
+        IndexEntry { opened_at, ..IndexEntry::new(&archive, GUILD, UserId(owner), category.to_string(), closed_at) }
+    }
+
+    fn query(user: Option<u64>, category: Option<&str>, from: Option<&str>, to: Option<&str>, keywords: Option<&str>) -> SearchQuery {
+        SearchQuery::new(GUILD, user.map(UserId), category.map(String::from), from.map(String::from), to.map(String::from), keywords.map(String::from)).unwrap()
+    }
+
+    fn index() -> ArchiveIndex {
+        let mut index = ArchiveIndex::default();
+        // 1er janvier 2022, 1er février 2022, 1er mars 2022 à midi
+        index.insert(entry(10, 100, "Support", 1641038400, 1641042000, "Mon compte est bloqué"));
+        index.insert(entry(11, 101, "Signalement", 1643716800, 1643720400, "Signalement d'un membre"));
+        index.insert(entry(12, 100, "Support", 1646136000, 1646139600, "Problème de paiement"));
+        index
+    }
+
+    fn ids(index: &ArchiveIndex, query: &SearchQuery) -> Vec<u64> {
+        index.search(query).iter().map(|entry| entry.id.0).collect()
+    }
+
+    #[test]
+    fn dates_are_parsed_as_utc_days() {
+        assert_eq!(parse_date("01/02/2022", false), Ok(1643673600));
+        assert_eq!(parse_date(" 01/02/2022 ", true), Ok(1643759999));
+        assert!(parse_date("2022-02-01", false).is_err());
+        assert!(parse_date("31/02/2022", false).is_err());
+    }
+
+    #[test]
+    fn search_filters_are_combined() {
+        let index = index();
+        assert_eq!(ids(&index, &query(None, None, None, None, None)), [12, 11, 10]);
+        assert_eq!(ids(&index, &query(Some(100), None, None, None, None)), [12, 10]);
+        // Les participants sont aussi retrouvés
+        assert_eq!(ids(&index, &query(Some(50), Some("signalement"), None, None, None)), [11]);
+        assert_eq!(ids(&index, &query(None, Some("support"), Some("15/01/2022"), None, None)), [12]);
+        assert_eq!(ids(&index, &query(None, None, None, Some("01/02/2022"), None)), [11, 10]);
+        let other_guild = SearchQuery { guild_id: GuildId(2), ..query(None, None, None, None, None) };
+        assert!(ids(&index, &other_guild).is_empty());
+    }
+
+    #[test]
+    fn keywords_must_all_match() {
+        let index = index();
+        assert_eq!(ids(&index, &query(None, None, None, None, Some("COMPTE bloq"))), [10]);
+        assert!(ids(&index, &query(None, None, None, None, Some("compte paiement"))).is_empty());
+        assert!(SearchQuery::new(GUILD, None, None, Some("demain".to_string()), None, None).is_err());
+    }
+
+    #[test]
+    fn terms_are_bounded() {
+        let text = (0..MAX_TERMS + 100).map(|i| format!("mot{}", i)).collect::<Vec<_>>().join(" ");
+        let terms = terms(std::iter::once(format!("{} le a {} mot1", text, "x".repeat(MAX_TERM_LEN + 1)).as_str()));
+        assert_eq!(terms.len(), MAX_TERMS);
+        // Le mot le plus fréquent est conservé, les mots trop courts ou trop longs sont ignorés
+        assert!(terms.contains(&"mot1".to_string()));
+        assert!(terms.iter().all(|term| (MIN_TERM_LEN..=MAX_TERM_LEN).contains(&term.len())));
+        assert!(terms.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn legacy_text_is_converted_to_terms() {
+        let entry: IndexEntry = serde_json::from_value(serde_json::json!({
+            "id": 10, "name": "ticket", "guild_id": 1, "owner": 100, "category": "Support",
+            "opened_at": 0, "closed_at": 0, "participants": [],
+            "text": "bonjour\nmon compte est bloqué, compte 42",
+        })).unwrap();
+        assert_eq!(entry.terms, ["bloqué", "bonjour", "compte", "est", "mon"]);
+    }
+
+    #[test]
+    fn owner_is_the_first_mention() {
+        let archive = ArchiveChannel {
+            id: 10,
+            name: "ticket".to_string(),
+            users: Vec::new(),
+            messages: vec![message(2, 1, "<@!300> répond"), message(1, 1, "<@&7> <@200>, ton ticket a été créé")],
+            access_log: Vec::new(),
+        };
+        assert_eq!(first_mention(&archive), Some(UserId(200)));
+    }
+}
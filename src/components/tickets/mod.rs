@@ -1,22 +1,47 @@
 //! Ticket manager
 
 mod archive;
+mod transcript;
+mod inactivity;
+mod rating;
+mod replay;
+mod index;
+mod access;
 
 use std::path::PathBuf;
-use crate::{log_error, log_warn};
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use crate::{log_error, log_info, log_warn};
 use futures_locks::RwLock;
-use cddio_core::{message, ApplicationCommandEmbed};
+use cddio_core::{message, ApplicationCommandEmbed, ComponentLifecycle, lifecycle::Health};
 use cddio_macros::component;
 use serde::{Serialize, Deserialize};
 use serenity::{
     client::Context,
-    model::{id::*, channel::Message, event::ReadyEvent},
+    model::{id::*, channel::{Message, ReactionType}, event::{ReadyEvent, MessageCreateEvent}},
     model::application::interaction:: {
-        message_component::MessageComponentInteraction
+        message_component::MessageComponentInteraction,
+        modal::ModalSubmitInteraction,
     }, builder::CreateSelectMenuOption
 };
 
-use super::utils::data::Data;
+use super::utils::{
+    data::{Data, DATA_DIR},
+    task::{self, Registry},
+    registry_file::RegistryFile,
+    time_parser as time,
+};
+use super::Settings;
+use self::inactivity::{InactivityPolicy, TicketTimer, TimerKind};
+use self::rating::TicketRating;
+use self::index::{ArchiveIndex, IndexEntry, SearchQuery};
+use self::access::{AccessEvent, AccessLevel, AccessTarget, TicketAccess};
+
+/// Recul minimal de l'échéance d'une tâche d'inactivité pour la replanifier, en secondes
+const TIMER_RESET_THRESHOLD: i64 = 60;
+
+/// Gestionnaire des tâches d'inactivité des tickets
+type TimerManager = task::TaskManager<TicketTimer, RegistryFile<TicketTimer>, (Context, Weak<Tickets>)>;
 
 /// Le composant de gestion des tickets
 pub struct Tickets {
@@ -25,7 +50,17 @@ pub struct Tickets {
     /// Dossier de sauvegarde des tickets
     /// 
     /// Dès que les tickets sont supprimés, ils sont enregistrés dans ce dossier.
-    archives_folder: PathBuf
+    archives_folder: PathBuf,
+    /// Index des tickets archivés
+    index: RwLock<Data<ArchiveIndex>>,
+    /// Configuration des serveurs
+    settings: Arc<Settings>,
+    /// Catégorie choisie par chaque membre en attente de la validation du formulaire
+    pending_forms: RwLock<HashMap<UserId, String>>,
+    /// Tâches de rappel et de fermeture des tickets inactifs
+    timers: RwLock<Option<TimerManager>>,
+    /// Référence vers le composant, transmise aux tâches d'inactivité
+    me: Weak<Tickets>,
 }
 
 /// Données persistantes du composant
@@ -40,6 +75,11 @@ struct DataTickets {
     /// 
     /// [Catégories]: CategoryTicket
     categories: Vec<CategoryTicket>,
+    /// Registre des tickets, par salon
+    /// 
+    /// Les tickets fermés sont conservés pour les statistiques et la réouverture.
+    #[serde(default)]
+    tickets: HashMap<ChannelId, TicketInfo>,
 }
 
 /// Catégorie de tickets
@@ -56,10 +96,138 @@ struct CategoryTicket {
     id: u64,
     /// Description de la catégorie
     desc: Option<String>,
-    /// Tickets créés dans cette catégorie
-    tickets: Vec<String>,
     #[serde(default)]
     hidden: bool,
+    /// Position dans le menu de création de tickets, par ordre croissant
+    #[serde(default)]
+    order: i64,
+    /// Emoji affiché dans le menu de création de tickets
+    #[serde(default)]
+    emoji: Option<ReactionType>,
+    /// Questions posées au membre avant la création du ticket
+    #[serde(default)]
+    questions: Vec<TicketQuestion>,
+    /// Fermeture automatique des tickets inactifs
+    #[serde(default)]
+    inactivity: Option<InactivityPolicy>,
+    /// Nombre maximum de tickets ouverts en même temps par un membre
+    #[serde(default)]
+    max_open: Option<u64>,
+    /// Délai minimum entre deux créations de ticket par un membre, en secondes
+    #[serde(default)]
+    cooldown: Option<u64>,
+    /// Membres ne pouvant pas créer de ticket dans cette catégorie
+    #[serde(default)]
+    blacklist: Vec<UserId>,
+}
+
+/// Question du formulaire d'une catégorie de ticket
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct TicketQuestion {
+    /// Intitulé de la question
+    label: String,
+    /// Réponse sur plusieurs lignes
+    long: bool,
+    /// Réponse obligatoire
+    required: bool,
+}
+
+/// État d'un ticket
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+enum TicketStatus {
+    /// En attente d'un membre du staff
+    Open,
+    /// Pris en charge par un membre du staff
+    Claimed,
+    /// En attente d'une réponse du créateur du ticket
+    WaitingUser,
+    /// Ticket fermé et archivé
+    Closed,
+}
+
+impl TicketStatus {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "ouvert" => Ok(TicketStatus::Open),
+            "pris_en_charge" => Ok(TicketStatus::Claimed),
+            "attente" => Ok(TicketStatus::WaitingUser),
+            _ => Err(format!("Statut \"{}\" inconnu, attendu: ouvert, pris_en_charge, attente", name)),
+        }
+    }
+    const fn name(&self) -> &'static str {
+        match self {
+            TicketStatus::Open => "Ouvert",
+            TicketStatus::Claimed => "Pris en charge",
+            TicketStatus::WaitingUser => "En attente du membre",
+            TicketStatus::Closed => "Fermé",
+        }
+    }
+}
+
+/// Informations d'un ticket
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct TicketInfo {
+    /// Serveur du ticket
+    guild_id: GuildId,
+    /// Créateur du ticket
+    owner: UserId,
+    /// Nom de la catégorie du ticket
+    category: String,
+    /// Date de création du ticket (timestamp unix)
+    #[serde(default)]
+    created_at: i64,
+    /// État du ticket
+    status: TicketStatus,
+    /// Membre du staff en charge du ticket
+    assigned: Option<UserId>,
+    /// Membres et rôles ajoutés au ticket
+    #[serde(default)]
+    access: Vec<TicketAccess>,
+    /// Historique des ajouts et retraits d'accès
+    #[serde(default)]
+    access_log: Vec<AccessEvent>,
+    /// Date de fermeture du ticket (timestamp unix)
+    #[serde(default)]
+    closed_at: Option<i64>,
+    /// Avis du créateur du ticket, donné après la fermeture
+    #[serde(default)]
+    rating: Option<TicketRating>,
+    /// Ticket fermé dont celui-ci est la réouverture
+    #[serde(default)]
+    reopened_from: Option<ChannelId>,
+    /// Salon du ticket rouvert, si ce ticket fermé a été rouvert
+    #[serde(default)]
+    reopened_as: Option<ChannelId>,
+}
+
+impl TicketInfo {
+    /// Nouveau ticket ouvert.
+    fn new(guild_id: GuildId, owner: UserId, category: String, created_at: i64) -> Self {
+        TicketInfo {
+            guild_id,
+            owner,
+            category,
+            created_at,
+            status: TicketStatus::Open,
+            assigned: None,
+            access: Vec::new(),
+            access_log: Vec::new(),
+            closed_at: None,
+            rating: None,
+            reopened_from: None,
+            reopened_as: None,
+        }
+    }
+    /// Sujet du salon du ticket.
+    /// 
+    /// Le statut est affiché dans le sujet plutôt que dans le nom du salon,
+    /// Discord limitant fortement le nombre de renommages de salons.
+    fn topic(&self) -> String {
+        match self.assigned {
+            Some(assigned) => format!("Statut : {} · Pris en charge par <@{}>", self.status.name(), assigned),
+            None => format!("Statut : {}", self.status.name()),
+        }
+    }
 }
 
 impl From<CategoryTicket> for CreateSelectMenuOption {
@@ -67,6 +235,9 @@ impl From<CategoryTicket> for CreateSelectMenuOption {
         let mut menu_option = CreateSelectMenuOption::new(&ticket.name, &ticket.name);
         menu_option
             .description(ticket.desc.unwrap_or_default());
+        if let Some(emoji) = ticket.emoji {
+            menu_option.emoji(emoji);
+        }
         menu_option
     }
 } 
@@ -75,6 +246,9 @@ impl From<&CategoryTicket> for CreateSelectMenuOption {
         let mut menu_option = CreateSelectMenuOption::new(&ticket.name, &ticket.name);
         menu_option
             .description(ticket.desc.clone().unwrap_or_default());
+        if let Some(emoji) = &ticket.emoji {
+            menu_option.emoji(emoji.clone());
+        }
         menu_option
     }
 }
@@ -85,17 +259,91 @@ impl CategoryTicket {
         embed.color(message::COLOR_INFO);
         embed.title(title);
         embed.field(&self.name, self.desc.as_ref().map(|v| v.as_str()).unwrap_or("*Aucune description*"), false);
+        embed.field("Catégorie Discord", format!("<#{}>", self.id), true);
+        embed.field("Préfixe", &self.prefix, true);
+        embed.field("Position", self.order.to_string(), true);
+        if let Some(emoji) = &self.emoji {
+            embed.field("Emoji", emoji.to_string(), true);
+        }
+        if self.hidden {
+            embed.field("Visibilité", "Cachée du menu", true);
+        }
+        if !self.questions.is_empty() {
+            let questions = self.questions.iter().enumerate()
+                .map(|(i, q)| format!("{}. {}{}", i + 1, q.label, if q.required { "" } else { " *(facultative)*" }))
+                .collect::<Vec<_>>()
+                .join("\n");
+            embed.field("Questions", questions, false);
+        }
+        if let Some(policy) = &self.inactivity {
+            embed.field("Inactivité", policy.to_string(), false);
+        }
+        if let Some(max_open) = self.max_open {
+            embed.field("Tickets ouverts par membre", max_open.to_string(), true);
+        }
+        if let Some(cooldown) = self.cooldown {
+            embed.field("Délai entre deux tickets", time::format_duration(cooldown), true);
+        }
+        if !self.blacklist.is_empty() {
+            let blacklist = self.blacklist.iter().map(|user_id| format!("<@{}>", user_id)).collect::<Vec<_>>().join(", ");
+            embed.field("Membres bloqués", blacklist, false);
+        }
         msg.add_embed(|e| {*e=embed; e});
         msg
     }
+    /// Formulaire affiché avant la création d'un ticket.
+    fn to_modal(&self) -> cddio_core::modal::Modal {
+        let mut modal = cddio_core::modal::Modal::new("modal_ticket_create", format!("Ticket : {}", self.name));
+        for (i, question) in self.questions.iter().enumerate() {
+            modal.add_field(format!("question_{}", i), &question.label, question.long, question.required);
+        }
+        modal
+    }
+    /// Associe les réponses du formulaire à leur question.
+    fn answers(&self, values: &[(String, String)]) -> Vec<(String, String)> {
+        self.questions.iter().enumerate()
+            .filter_map(|(i, question)| {
+                let custom_id = format!("question_{}", i);
+                values.iter()
+                    .find(|(id, _)| *id == custom_id)
+                    .map(|(_, value)| (question.label.clone(), value.clone()))
+            })
+            .collect()
+    }
 }
 
 impl Tickets {
     /// Créer un nouveau composant de gestion des tickets
-    pub fn new() -> Self {
+    /// 
+    /// `me` est une référence faible vers le composant lui-même, obtenue avec [`Arc::new_cyclic`].
+    pub fn new(settings: Arc<Settings>, me: Weak<Tickets>) -> Self {
         Self {
             data: RwLock::new(Data::from_file("tickets").unwrap()),
-            archives_folder: PathBuf::from("data/archives/tickets")
+            archives_folder: DATA_DIR.join("tickets/archives"),
+            index: RwLock::new(Data::from_file("tickets_archives").unwrap()),
+            settings,
+            pending_forms: RwLock::new(HashMap::new()),
+            timers: RwLock::new(None),
+            me,
+        }
+    }
+}
+
+#[serenity::async_trait]
+impl ComponentLifecycle for Tickets {
+    async fn init(&self) -> Result<(), String> {
+        async_std::fs::create_dir_all(&self.archives_folder).await
+            .map_err(|e| format!("Impossible de créer le dossier des archives {}: {}", self.archives_folder.display(), e))
+    }
+    async fn on_shutdown(&self) {
+        if let Some(timers) = self.timers.write().await.as_mut() {
+            timers.shutdown().await;
+        }
+    }
+    async fn health(&self) -> Health {
+        match self.timers.read().await.is_some() {
+            true => Health::Healthy,
+            false => Health::Degraded("les tâches d'inactivité ne sont pas chargées".to_string()),
         }
     }
 }
@@ -103,29 +351,39 @@ impl Tickets {
 #[component]
 #[group(name="tickets", description="Gestion des tickets")]
 #[group(parent="tickets", name="categories", description="Gestion des catégories de tickets")]
+#[group(parent="tickets", name="archives", description="Consultation des tickets archivés")]
 #[group(name="ticket", description="Commandes dans un ticket")]
 impl Tickets {
     #[event(Ready)]
-    async fn on_ready(&self, ctx: &Context, _:&ReadyEvent) {
-        let msg_choose = {
-            let data = self.data.read().await;
-            let data = data.read();
-            data.msg_choose.clone()
-        };
-        if let Some((chan_id, msg_id)) = msg_choose {
-            let mut msg = match ChannelId(chan_id).message(ctx, msg_id).await {
-                Ok(msg) => msg,
-                Err(err) => {
-                    log_warn!("Erreur lors de la récupération du message du menu: {:?}", err);
-                    self.reset_message_choose(None).await;
-                    return;
+    async fn on_ready(&self, ctx: &Context, ready: &ReadyEvent) {
+        {
+            let mut timers = self.timers.write().await;
+            let persistent = (ctx.clone(), self.me.clone());
+            match &mut *timers {
+                Some(timers) => timers.reset_persistent(persistent),
+                None => match RegistryFile::from_file(DATA_DIR.join("tickets_timers.json")).await {
+                    Ok(registry) => {
+                        let mut new_timers = task::TaskManager::new(registry, persistent);
+                        new_timers.init().await;
+                        *timers = Some(new_timers);
+                    },
+                    Err(e) => log_error!("Impossible de charger les tâches d'inactivité des tickets: {}", e),
                 }
-            };
-            if let Err(err) = self.update_menu(ctx, &mut msg).await {
-                log_warn!("Erreur lors de la mise à jour du menu: {}", err);
-                self.reset_message_choose(None).await;
             }
         }
+        for guild in &ready.ready.guilds {
+            self.reconcile_tickets(ctx, guild.id).await;
+        }
+        let guilds = ready.ready.guilds.iter().map(|guild| guild.id).collect::<Vec<_>>();
+        self.backfill_index(&guilds).await;
+        self.refresh_menu(ctx).await;
+    }
+    #[event(MessageCreate)]
+    async fn on_message_create(&self, _: &Context, msg_create: &MessageCreateEvent) {
+        if msg_create.message.author.bot || !self.is_a_ticket(msg_create.message.channel_id).await {
+            return;
+        }
+        self.reset_timers(msg_create.message.channel_id).await;
     }
     #[command(group="tickets", description="Assigne le salon de création de tickets")]
     async fn set_channel(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
@@ -216,8 +474,14 @@ impl Tickets {
                 prefix,
                 id: category_id.0,
                 desc,
-                tickets: vec![],
-                hidden
+                hidden,
+                order: 0,
+                emoji: None,
+                questions: vec![],
+                inactivity: None,
+                max_open: None,
+                cooldown: None,
+                blacklist: vec![],
             });
         }
         {
@@ -228,39 +492,248 @@ impl Tickets {
                 log_error!("Erreur lors de l'envoi du message: {}", e);
             });
         }
+        self.refresh_menu(ctx).await;
     }
     #[command(group="categories", name="remove", description="Supprime une catégorie de ticket")]
     async fn remove_categorie(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
         #[argument(name="nom", description="Nom de la catégorie")]
         name: String
     ) {
-        let mut data = self.data.write().await;
-        let mut data = data.write();
-        let pos = match data.categories.iter().position(|category| category.name == name) {
-            Some(pos) => pos,
-            None => {
-                app_cmd.direct_response(ctx, message::error("Cette catégorie n'existe pas")).await.unwrap_or_else(|e| {
-                    log_error!("Erreur lors de l'envoi du message: {}", e);
-                });
-                return;
+        let msg = {
+            let mut data = self.data.write().await;
+            let mut data = data.write();
+            match data.categories.iter().position(|category| category.name == name) {
+                Some(pos) => {
+                    let msg = data.categories[pos].to_message("Catégorie supprimée");
+                    data.categories.remove(pos);
+                    msg
+                },
+                None => message::error("Cette catégorie n'existe pas"),
             }
         };
-        let msg = data.categories[pos].to_message("Catégorie supprimée");
-        data.categories.remove(pos);
-
+        app_cmd.direct_response(ctx, msg).await.unwrap_or_else(|e| {
+            log_error!("Erreur lors de l'envoi du message: {}", e);
+        });
+        self.refresh_menu(ctx).await;
+    }
+    #[command(group="categories", name="edit", description="Modifie une catégorie de ticket. Seuls les champs renseignés sont modifiés.")]
+    async fn edit_categorie(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(name="nom", description="Nom de la catégorie")]
+        name: String,
+        #[argument(description="Nouveau nom de la catégorie")]
+        nouveau_nom: Option<String>,
+        #[argument(description="Catégorie Discord où les tickets seront créés", name="categorie_discord")]
+        category_id: Option<ChannelId>,
+        #[argument(description="Préfixe des tickets", name="prefix")]
+        prefix: Option<String>,
+        #[argument(description="Description de la catégorie", name="description")]
+        desc: Option<String>,
+        #[argument(description="Cacher la catégorie du menu de ticket ?")]
+        hidden: Option<bool>,
+        #[argument(description="Position dans le menu, par ordre croissant")]
+        ordre: Option<i64>,
+        #[argument(description="Emoji affiché dans le menu. \"aucun\" pour le retirer.")]
+        emoji: Option<String>
+    ) {
+        let emoji = match emoji.as_deref() {
+            None => Ok(None),
+            Some("aucun") => Ok(Some(None)),
+            Some(emoji) => ReactionType::try_from(emoji.trim())
+                .map(|emoji| Some(Some(emoji)))
+                .map_err(|_| format!("Emoji invalide: {}", emoji)),
+        };
+        let msg = match emoji {
+            Ok(emoji) => self.edit_category_fields(&name, nouveau_nom, |category| {
+                if let Some(category_id) = category_id {
+                    category.id = category_id.0;
+                }
+                if let Some(prefix) = prefix {
+                    category.prefix = prefix;
+                }
+                if let Some(desc) = desc {
+                    category.desc = Some(desc);
+                }
+                if let Some(hidden) = hidden {
+                    category.hidden = hidden;
+                }
+                if let Some(ordre) = ordre {
+                    category.order = ordre;
+                }
+                if let Some(emoji) = emoji {
+                    category.emoji = emoji;
+                }
+            }).await,
+            Err(e) => Err(e),
+        }.unwrap_or_else(message::error);
+        app_cmd.direct_response(ctx, msg).await.unwrap_or_else(|e| {
+            log_error!("Erreur lors de l'envoi du message: {}", e);
+        });
+        self.refresh_menu(ctx).await;
+    }
+    #[command(group="categories", name="add_question", description="Ajoute une question au formulaire d'une catégorie de ticket")]
+    async fn add_question(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(name="nom", description="Nom de la catégorie")]
+        name: String,
+        #[argument(description="Intitulé de la question (45 caractères maximum)")]
+        question: String,
+        #[argument(description="Réponse sur plusieurs lignes ?")]
+        longue: bool,
+        #[argument(description="Réponse obligatoire ?")]
+        obligatoire: bool
+    ) {
+        let msg = self.edit_category(&name, |category| {
+            if category.questions.len() >= cddio_core::modal::MAX_FIELDS {
+                return Err(format!("Une catégorie ne peut pas avoir plus de {} questions", cddio_core::modal::MAX_FIELDS));
+            }
+            if question.chars().count() > cddio_core::modal::MAX_LABEL_LEN {
+                return Err(format!("L'intitulé ne doit pas dépasser {} caractères", cddio_core::modal::MAX_LABEL_LEN));
+            }
+            category.questions.push(TicketQuestion {
+                label: question,
+                long: longue,
+                required: obligatoire,
+            });
+            Ok(())
+        }).await.unwrap_or_else(message::error);
+        app_cmd.direct_response(ctx, msg).await.unwrap_or_else(|e| {
+            log_error!("Erreur lors de l'envoi du message: {}", e);
+        });
+    }
+    #[command(group="categories", name="remove_question", description="Retire une question du formulaire d'une catégorie de ticket")]
+    async fn remove_question(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(name="nom", description="Nom de la catégorie")]
+        name: String,
+        #[argument(description="Numéro de la question")]
+        numero: u64
+    ) {
+        let msg = self.edit_category(&name, |category| {
+            match (numero as usize).checked_sub(1) {
+                Some(index) if index < category.questions.len() => {
+                    category.questions.remove(index);
+                    Ok(())
+                },
+                _ => Err(format!("La question {} n'existe pas", numero)),
+            }
+        }).await.unwrap_or_else(message::error);
+        app_cmd.direct_response(ctx, msg).await.unwrap_or_else(|e| {
+            log_error!("Erreur lors de l'envoi du message: {}", e);
+        });
+    }
+    #[command(group="categories", name="inactivite", description="Configure la fermeture automatique des tickets inactifs d'une catégorie")]
+    async fn set_inactivity(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(name="nom", description="Nom de la catégorie")]
+        name: String,
+        #[argument(description="Durée sans message avant le rappel. Sans argument, désactive la fermeture automatique.")]
+        rappel: Option<String>,
+        #[argument(description="Durée après le rappel avant la fermeture du ticket. Par défaut, identique au rappel.")]
+        fermeture: Option<String>
+    ) {
+        let policy = match (rappel, fermeture) {
+            (None, _) => Ok(None),
+            (Some(rappel), fermeture) => time::parse(&rappel).and_then(|reminder_after| {
+                let close_after = match fermeture {
+                    Some(fermeture) => time::parse(fermeture)?,
+                    None => reminder_after,
+                };
+                Ok(Some(InactivityPolicy { reminder_after, close_after }))
+            }).map_err(|e| format!("Impossible de parser la durée: {}", e)),
+        };
+        let msg = match policy {
+            Ok(policy) => self.edit_category(&name, |category| {
+                category.inactivity = policy;
+                Ok(())
+            }).await,
+            Err(e) => Err(e),
+        }.unwrap_or_else(message::error);
+        app_cmd.direct_response(ctx, msg).await.unwrap_or_else(|e| {
+            log_error!("Erreur lors de l'envoi du message: {}", e);
+        });
+    }
+    #[command(group="categories", name="limite", description="Limite le nombre de tickets ouverts en même temps par un membre dans une catégorie")]
+    async fn set_max_open(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(name="nom", description="Nom de la catégorie")]
+        name: String,
+        #[argument(description="Nombre maximum de tickets ouverts par membre. Sans argument, retire la limite.")]
+        maximum: Option<u64>
+    ) {
+        let msg = self.edit_category(&name, |category| {
+            if maximum == Some(0) {
+                return Err("La limite doit être d'au moins un ticket".to_string());
+            }
+            category.max_open = maximum;
+            Ok(())
+        }).await.unwrap_or_else(message::error);
+        app_cmd.direct_response(ctx, msg).await.unwrap_or_else(|e| {
+            log_error!("Erreur lors de l'envoi du message: {}", e);
+        });
+    }
+    #[command(group="categories", name="delai", description="Définit le délai minimum entre deux créations de ticket par un membre dans une catégorie")]
+    async fn set_cooldown(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(name="nom", description="Nom de la catégorie")]
+        name: String,
+        #[argument(description="Délai entre deux tickets. Sans argument, retire le délai.")]
+        duree: Option<String>
+    ) {
+        let cooldown = match duree {
+            Some(duree) => time::parse(duree).map(Some).map_err(|e| format!("Impossible de parser la durée: {}", e)),
+            None => Ok(None),
+        };
+        let msg = match cooldown {
+            Ok(cooldown) => self.edit_category(&name, |category| {
+                category.cooldown = cooldown;
+                Ok(())
+            }).await,
+            Err(e) => Err(e),
+        }.unwrap_or_else(message::error);
+        app_cmd.direct_response(ctx, msg).await.unwrap_or_else(|e| {
+            log_error!("Erreur lors de l'envoi du message: {}", e);
+        });
+    }
+    #[command(group="categories", name="blacklist_add", description="Empêche un membre de créer des tickets dans une catégorie")]
+    async fn blacklist_add(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(name="nom", description="Nom de la catégorie")]
+        name: String,
+        #[argument(description="Membre à bloquer")]
+        qui: UserId
+    ) {
+        let msg = self.edit_category(&name, |category| {
+            if category.blacklist.contains(&qui) {
+                return Err(format!("<@{}> fait déjà partie des membres bloqués de cette catégorie", qui));
+            }
+            category.blacklist.push(qui);
+            Ok(())
+        }).await.unwrap_or_else(message::error);
+        app_cmd.direct_response(ctx, msg).await.unwrap_or_else(|e| {
+            log_error!("Erreur lors de l'envoi du message: {}", e);
+        });
+    }
+    #[command(group="categories", name="blacklist_remove", description="Autorise à nouveau un membre à créer des tickets dans une catégorie")]
+    async fn blacklist_remove(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(name="nom", description="Nom de la catégorie")]
+        name: String,
+        #[argument(description="Membre à débloquer")]
+        qui: UserId
+    ) {
+        let msg = self.edit_category(&name, |category| {
+            let len = category.blacklist.len();
+            category.blacklist.retain(|user_id| *user_id != qui);
+            if category.blacklist.len() == len {
+                return Err(format!("<@{}> ne fait pas partie des membres bloqués de cette catégorie", qui));
+            }
+            Ok(())
+        }).await.unwrap_or_else(message::error);
         app_cmd.direct_response(ctx, msg).await.unwrap_or_else(|e| {
             log_error!("Erreur lors de l'envoi du message: {}", e);
         });
     }
     #[command(group="categories", name="list", description="Liste les catégories de ticket")]
     async fn list_categories(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>) {
-        let data = self.data.read().await;
-        let data = data.read();
+        let categories = self.sorted_categories().await;
         let mut msg = message::Message::new();
         let mut embed = message::Embed::default();
         embed.title("Liste des catégories");
         embed.color(message::COLOR_INFO);
-        for category in &data.categories {
+        for category in &categories {
             embed.field(&category.name, category.desc.clone().unwrap_or_else(|| "*Aucune desscription*".into()), false);
         }
         msg.add_embed(|e| {*e=embed; e});
@@ -271,74 +744,130 @@ impl Tickets {
     #[command(group="ticket", description="Ajoute une personne au ticket")]
     async fn add_member(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
         #[argument(name="qui", description="Personne à ajouter au ticket")]
-        personne: UserId
+        personne: UserId,
+        #[argument(description="Accès en lecture seule ? Par défaut, la personne peut participer.")]
+        lecture_seule: Option<bool>
     ) {
-        use serenity::model::{
-            channel::{PermissionOverwrite, PermissionOverwriteType},
-            permissions::Permissions,
+        let access = TicketAccess {
+            target: AccessTarget::Member(personne),
+            level: AccessLevel::from_read_only(lecture_seule.unwrap_or(false)),
         };
-        let channel_id = app_cmd.0.channel_id;
-        let delay_resp = match app_cmd.delayed_response(ctx, false).await {
+        self.edit_access_response(ctx, app_cmd, access.target, Some(access.level)).await;
+    }
+    #[command(group="ticket", name="remove", description="Retire une personne du ticket")]
+    async fn remove_member(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(name="qui", description="Personne à retirer du ticket")]
+        personne: UserId
+    ) {
+        self.edit_access_response(ctx, app_cmd, AccessTarget::Member(personne), None).await;
+    }
+    #[command(group="ticket", name="add_role", description="Ajoute un rôle au ticket")]
+    async fn add_role(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Rôle à ajouter au ticket")]
+        role: RoleId,
+        #[argument(description="Accès en lecture seule ? Par défaut, les membres du rôle peuvent participer.")]
+        lecture_seule: Option<bool>
+    ) {
+        let level = AccessLevel::from_read_only(lecture_seule.unwrap_or(false));
+        self.edit_access_response(ctx, app_cmd, AccessTarget::Role(role), Some(level)).await;
+    }
+    #[command(group="ticket", name="remove_role", description="Retire un rôle du ticket")]
+    async fn remove_role(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Rôle à retirer du ticket")]
+        role: RoleId
+    ) {
+        self.edit_access_response(ctx, app_cmd, AccessTarget::Role(role), None).await;
+    }
+    #[command(group="ticket", name="assign", description="Assigne le ticket à un membre du staff")]
+    async fn ticket_assign(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(name="qui", description="Membre du staff en charge du ticket")]
+        staff: UserId
+    ) {
+        let msg = self.claim_ticket(ctx, app_cmd.0.guild_id, app_cmd.0.channel_id, app_cmd.0.user.id, staff).await
+            .unwrap_or_else(message::error);
+        app_cmd.direct_response(ctx, msg).await.unwrap_or_else(|e| {
+            log_error!("Erreur lors de l'envoi du message: {}", e);
+        });
+    }
+    #[command(group="ticket", name="statut", description="Change le statut du ticket")]
+    async fn ticket_status(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Nouveau statut: ouvert, pris_en_charge ou attente")]
+        statut: String
+    ) {
+        let msg = match TicketStatus::parse(&statut) {
+            Ok(status) => self.set_ticket_status(ctx, app_cmd.0.guild_id, app_cmd.0.channel_id, app_cmd.0.user.id, status).await,
+            Err(e) => Err(e),
+        }.unwrap_or_else(message::error);
+        app_cmd.direct_response(ctx, msg).await.unwrap_or_else(|e| {
+            log_error!("Erreur lors de l'envoi du message: {}", e);
+        });
+    }
+    #[command(group="tickets", name="list", description="Liste les tickets ouverts du serveur")]
+    async fn list_tickets(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>) {
+        let msg = self.open_tickets(ctx, app_cmd.0.guild_id, app_cmd.0.user.id).await
+            .unwrap_or_else(message::error);
+        app_cmd.direct_response(ctx, msg.set_ephemeral(true)).await.unwrap_or_else(|e| {
+            log_error!("Erreur lors de l'envoi du message: {}", e);
+        });
+    }
+    #[command(group="tickets", name="reopen", description="Rouvre un ticket fermé à partir de son archive")]
+    async fn ticket_reopen(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(description="Identifiant du ticket fermé, présent dans le nom de son archive")]
+        id: String
+    ) {
+        let delay_resp = match app_cmd.delayed_response(ctx, true).await {
             Ok(resp) => resp,
             Err(e) => {
                 log_error!("Erreur lors de l'envoi du message: {}", e);
                 return;
             }
         };
-        let msg = loop {
-            let guild_id = match app_cmd.0.guild_id {
-                Some(guild_id) => guild_id,
-                None => break message::error("Cette commande n'est pas disponible dans un DM"),
-            };
-            
-            match self.is_a_ticket(ctx, channel_id).await  {
-                Ok(true) => (),
-                Ok(false) => break message::error("Ce salon n'est pas un ticket"),
-                Err(e) => break message::error(e),
-            }
-            let is_staff = match Self::is_staff(ctx, guild_id, app_cmd.0.user.id).await {
-                Ok(true) => true,
-                Ok(false) => false,
-                Err(e) => break message::error(e),
-            };
-            let is_owner = match Self::is_ticket_owner(ctx, channel_id, app_cmd.0.user.id).await {
-                Ok(true) => true,
-                Ok(false) => false,
-                Err(e) => break message::error(e),
-            };
-            if !is_staff && !is_owner {
-                break message::error("Vous n'avez pas la permission d'ajouter des membres au ticket.");
-            }
-            
-            let username = personne.to_user(ctx).await.map(|u| super::utils::user_fullname(&u)).unwrap_or_else(|_| personne.0.to_string());
-            break match channel_id.create_permission(ctx, &PermissionOverwrite {
-                allow: Permissions::VIEW_CHANNEL,
-                deny: Default::default(),
-                kind: PermissionOverwriteType::Member(personne),
-            }).await {
-                Ok(_) => message::success(format!("{} a bien été ajoutée.", username)),
-                Err(e) => message::error(format!("Impossible d'ajouter {}: {}", personne, e.to_string()))
-            };
+        let msg = match self.reopen_ticket(ctx, app_cmd.0.guild_id, app_cmd.0.user.id, &id).await {
+            Ok(channel_id) => message::success(format!("Ticket rouvert: <#{}>", channel_id)),
+            Err(e) => {
+                log_error!("Erreur lors de la réouverture du ticket {}: {}", id, e);
+                message::error(e)
+            }
         };
         delay_resp.send_message(msg).await.unwrap_or_else(|e| {
             log_error!("Erreur lors de l'envoi du message: {}", e);
         });
     }
+    #[command(group="archives", name="recherche", description="Recherche parmi les tickets archivés")]
+    async fn search_archives(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>,
+        #[argument(name="membre", description="Créateur ou participant du ticket")]
+        user: Option<UserId>,
+        #[argument(description="Nom de la catégorie du ticket")]
+        categorie: Option<String>,
+        #[argument(description="Tickets fermés à partir de cette date (JJ/MM/AAAA)")]
+        depuis: Option<String>,
+        #[argument(description="Tickets ouverts jusqu'à cette date (JJ/MM/AAAA)")]
+        jusqu_a: Option<String>,
+        #[argument(description="Mots présents dans les messages du ticket")]
+        mots_cles: Option<String>
+    ) {
+        let msg = match app_cmd.0.guild_id {
+            Some(guild_id) => match SearchQuery::new(guild_id, user, categorie, depuis, jusqu_a, mots_cles) {
+                Ok(query) => self.search_archive_index(ctx, app_cmd.0.user.id, query).await,
+                Err(e) => Err(e),
+            },
+            None => Err("Cette commande n'est pas disponible dans un DM".to_string()),
+        }.unwrap_or_else(message::error);
+        app_cmd.direct_response(ctx, msg.set_ephemeral(true)).await.unwrap_or_else(|e| {
+            log_error!("Erreur lors de l'envoi du message: {}", e);
+        });
+    }
+    #[command(group="tickets", name="stats", description="Statistiques des tickets du serveur")]
+    async fn ticket_stats(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>) {
+        let msg = self.stats(ctx, app_cmd.0.guild_id, app_cmd.0.user.id).await
+            .unwrap_or_else(message::error);
+        app_cmd.direct_response(ctx, msg.set_ephemeral(true)).await.unwrap_or_else(|e| {
+            log_error!("Erreur lors de l'envoi du message: {}", e);
+        });
+    }
     #[message_component(custom_id="menu_ticket_create")]
     async fn on_menu_ticket_create(&self, ctx: &Context, msg: &MessageComponentInteraction) {
         use serenity::model::application::interaction::InteractionResponseType;
-        let ok = match msg.create_interaction_response(ctx, |resp| {
-            resp.kind(InteractionResponseType::DeferredChannelMessageWithSource)
-                .interaction_response_data(|data| {
-                    data.ephemeral(true)
-                })
-        }).await {
-            Ok(_) => true,
-            Err(e) => {
-                log_warn!("Erreur lors de la création de l'interaction: {}", e);
-                false
-            }
-        };
         let guild_id = match msg.guild_id {
             Some(guild_id) => guild_id,
             None => {
@@ -365,13 +894,40 @@ impl Tickets {
                 }
             }
         };
-        let result = match self.ticket_create(ctx, guild_id, user_id, category).await {
-            Ok(result) => message::success(format!("Ticket créé: <#{}>", result)),
+        if let Err(e) = self.check_limits(&category, user_id).await {
+            if let Err(e) = msg.create_interaction_response(ctx, |resp| {
+                *resp = message::error(e).set_ephemeral(true).into();
+                resp
+            }).await {
+                log_error!("Erreur lors de l'envoi d'une réponse d'interaction: {}", e);
+            }
+            return;
+        }
+        // La catégorie possède un formulaire: le ticket sera créé à sa validation
+        if !category.questions.is_empty() {
+            let modal = category.to_modal();
+            self.pending_forms.write().await.insert(user_id, category.name);
+            if let Err(e) = msg.create_interaction_response(ctx, |resp| {
+                *resp = modal.into();
+                resp
+            }).await {
+                log_error!("Erreur lors de l'affichage du formulaire: {}", e);
+            }
+            return;
+        }
+        let ok = match msg.create_interaction_response(ctx, |resp| {
+            resp.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                .interaction_response_data(|data| {
+                    data.ephemeral(true)
+                })
+        }).await {
+            Ok(_) => true,
             Err(e) => {
-                log_error!("Erreur lors de la création du ticket: {}", e);
-                message::error(e)
+                log_warn!("Erreur lors de la création de l'interaction: {}", e);
+                false
             }
         };
+        let result = self.ticket_create_message(ctx, guild_id, user_id, category, Vec::new()).await;
         if ok {
             match msg.edit_original_interaction_response(ctx, |resp| {
                 *resp = result.into();
@@ -385,26 +941,128 @@ impl Tickets {
         }
         
     }
-    #[message_component(custom_id="button_ticket_close")]
-    async fn on_button_ticket_close(&self, ctx: &Context, msg: &MessageComponentInteraction) {
-        if let Err(e) = self.ticket_close_channel(ctx, msg.channel_id).await {
-            log_error!("{}", e);
-            msg.create_interaction_response(ctx, |resp|{
-                resp.interaction_response_data(|inter| inter.content(e))
-            }).await.unwrap_or_else(|e| {
-                log_error!("Erreur lors de l'envoi d'une réponse d'interaction: {}", e);
-            });
-        }
-    }
-}
-
-impl Tickets {
-    async fn update_menu(&self, ctx: &Context, msg: &mut Message) -> serenity::Result<()>{
-        let options = self.data.read().await.read().categories.iter().filter(|cat| !cat.hidden).map(|cat| cat.into()).collect::<Vec<_>>();
-        msg.edit(ctx, |msg|{
-            msg.components(|comp| {
-                comp.create_action_row(|action| {
-                    action.create_select_menu(|menu| {
+    #[modal_submit(custom_id="modal_ticket_create")]
+    async fn on_modal_ticket_create(&self, ctx: &Context, modal: &ModalSubmitInteraction) {
+        use serenity::model::application::interaction::InteractionResponseType;
+        let ok = match modal.create_interaction_response(ctx, |resp| {
+            resp.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                .interaction_response_data(|data| {
+                    data.ephemeral(true)
+                })
+        }).await {
+            Ok(_) => true,
+            Err(e) => {
+                log_warn!("Erreur lors de la création de l'interaction: {}", e);
+                false
+            }
+        };
+        let result = match (modal.guild_id, self.pending_forms.write().await.remove(&modal.user.id)) {
+            (Some(guild_id), Some(category_name)) => {
+                let category = self.data.read().await.read().categories.iter().find(|category| category.name == category_name).cloned();
+                match category {
+                    Some(category) => {
+                        let answers = category.answers(&cddio_core::modal::values(&modal.data));
+                        self.ticket_create_message(ctx, guild_id, modal.user.id, category, answers).await
+                    },
+                    None => message::error(format!("La catégorie {} n'existe plus", category_name)),
+                }
+            },
+            _ => message::error("Le formulaire a expiré, veuillez sélectionner à nouveau une catégorie."),
+        };
+        if ok {
+            if let Err(e) = modal.edit_original_interaction_response(ctx, |resp| {
+                *resp = result.into();
+                resp
+            }).await {
+                log_error!("Erreur lors de la modification de l'interaction: {}", e);
+            }
+        }
+    }
+    #[message_component(custom_id="button_ticket_claim")]
+    async fn on_button_ticket_claim(&self, ctx: &Context, msg: &MessageComponentInteraction) {
+        let result = match self.claim_ticket(ctx, msg.guild_id, msg.channel_id, msg.user.id, msg.user.id).await {
+            Ok(result) => result,
+            Err(e) => message::error(e).set_ephemeral(true),
+        };
+        msg.create_interaction_response(ctx, |resp| {
+            *resp = result.into();
+            resp
+        }).await.unwrap_or_else(|e| {
+            log_error!("Erreur lors de l'envoi d'une réponse d'interaction: {}", e);
+        });
+    }
+    #[message_component(custom_id_prefix="ticket_rating:")]
+    async fn on_button_ticket_rating(&self, ctx: &Context, msg: &MessageComponentInteraction) {
+        let result = match rating::parse_rating(&msg.data.custom_id) {
+            Some((channel_id, stars)) => self.rate_ticket(channel_id, msg.user.id, stars).await.map(|_| (channel_id, stars)),
+            None => Err("Bouton de notation invalide".to_string()),
+        };
+        let (channel_id, stars) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                msg.create_interaction_response(ctx, |resp| {
+                    *resp = message::error(e).into();
+                    resp
+                }).await.unwrap_or_else(|e| {
+                    log_error!("Erreur lors de l'envoi d'une réponse d'interaction: {}", e);
+                });
+                return;
+            }
+        };
+        if let Err(e) = msg.create_interaction_response(ctx, |resp| {
+            *resp = rating::comment_modal(channel_id).into();
+            resp
+        }).await {
+            log_error!("Erreur lors de l'affichage du formulaire: {}", e);
+        }
+        // Les boutons sont retirés pour ne pas noter deux fois le ticket
+        let mut dm_message = msg.message.clone();
+        if let Err(e) = dm_message.edit(ctx, |edit| {
+            edit
+                .content(format!("{}\nMerci ! Ta note : {}", msg.message.content, rating::stars(stars)))
+                .components(|cmps| cmps)
+        }).await {
+            log_warn!("Impossible de retirer les boutons de notation: {}", e);
+        }
+    }
+    #[modal_submit(custom_id_prefix="ticket_comment:")]
+    async fn on_modal_ticket_comment(&self, ctx: &Context, modal: &ModalSubmitInteraction) {
+        let comment = cddio_core::modal::values(&modal.data).into_iter()
+            .map(|(_, value)| value)
+            .find(|value| !value.is_empty());
+        let result = match (rating::parse_comment(&modal.data.custom_id), comment) {
+            (Some(channel_id), Some(comment)) => self.comment_ticket(channel_id, modal.user.id, comment).await
+                .map(|_| message::success("Merci pour ton retour !")),
+            (Some(_), None) => Ok(message::success("Merci pour ton retour !")),
+            (None, _) => Err("Formulaire de commentaire invalide".to_string()),
+        }.unwrap_or_else(message::error);
+        modal.create_interaction_response(ctx, |resp| {
+            *resp = result.into();
+            resp
+        }).await.unwrap_or_else(|e| {
+            log_error!("Erreur lors de l'envoi d'une réponse d'interaction: {}", e);
+        });
+    }
+    #[message_component(custom_id="button_ticket_close")]
+    async fn on_button_ticket_close(&self, ctx: &Context, msg: &MessageComponentInteraction) {
+        if let Err(e) = self.ticket_close_channel(ctx, msg.channel_id).await {
+            log_error!("{}", e);
+            msg.create_interaction_response(ctx, |resp|{
+                resp.interaction_response_data(|inter| inter.content(e))
+            }).await.unwrap_or_else(|e| {
+                log_error!("Erreur lors de l'envoi d'une réponse d'interaction: {}", e);
+            });
+        }
+    }
+}
+
+impl Tickets {
+    async fn update_menu(&self, ctx: &Context, msg: &mut Message) -> serenity::Result<()>{
+        let options = self.sorted_categories().await.iter().filter(|cat| !cat.hidden).map(|cat| cat.into()).collect::<Vec<_>>();
+        msg.edit(ctx, |msg|{
+            msg.components(|comp| {
+                comp.create_action_row(|action| {
+                    action.create_select_menu(|menu| {
                         menu.options(|opts|{
                             opts.set_options(options)
                         }).custom_id("menu_ticket_create")
@@ -413,6 +1071,70 @@ impl Tickets {
             })
         }).await
     }
+    /// Catégories de tickets dans l'ordre du menu.
+    async fn sorted_categories(&self) -> Vec<CategoryTicket> {
+        let mut categories = self.data.read().await.read().categories.clone();
+        categories.sort_by_key(|category| category.order);
+        categories
+    }
+    /// Met à jour le menu de création de tickets après une modification des catégories.
+    async fn refresh_menu(&self, ctx: &Context) {
+        let msg_choose = self.data.read().await.read().msg_choose;
+        if let Some((chan_id, msg_id)) = msg_choose {
+            let mut msg = match ChannelId(chan_id).message(ctx, msg_id).await {
+                Ok(msg) => msg,
+                Err(err) => {
+                    log_warn!("Erreur lors de la récupération du message du menu: {:?}", err);
+                    self.reset_message_choose(None).await;
+                    return;
+                }
+            };
+            if let Err(err) = self.update_menu(ctx, &mut msg).await {
+                log_warn!("Erreur lors de la mise à jour du menu: {}", err);
+                self.reset_message_choose(None).await;
+            }
+        }
+    }
+    /// Modifie une catégorie de ticket, en la renommant si `new_name` est défini, et retourne sa description.
+    /// 
+    /// Les tickets de la catégorie sont mis à jour avec le nouveau nom.
+    async fn edit_category_fields<F>(&self, name: &str, new_name: Option<String>, f: F) -> Result<message::Message, String>
+        where F: FnOnce(&mut CategoryTicket)
+    {
+        let mut data = self.data.write().await;
+        let mut data = data.write();
+        if let Some(new_name) = &new_name {
+            if new_name != name && data.categories.iter().any(|category| &category.name == new_name) {
+                return Err(format!("La catégorie {} existe déjà", new_name));
+            }
+        }
+        let category = data.categories.iter_mut()
+            .find(|category| category.name == name)
+            .ok_or_else(|| "Cette catégorie n'existe pas".to_string())?;
+        f(category);
+        if let Some(new_name) = &new_name {
+            category.name = new_name.clone();
+        }
+        let msg = category.to_message("Catégorie modifiée");
+        if let Some(new_name) = new_name {
+            for info in data.tickets.values_mut().filter(|info| info.category == name) {
+                info.category = new_name.clone();
+            }
+        }
+        Ok(msg)
+    }
+    /// Modifie une catégorie de ticket et retourne sa description.
+    async fn edit_category<F>(&self, name: &str, f: F) -> Result<message::Message, String>
+        where F: FnOnce(&mut CategoryTicket) -> Result<(), String>
+    {
+        let mut data = self.data.write().await;
+        let mut data = data.write();
+        let category = data.categories.iter_mut()
+            .find(|category| category.name == name)
+            .ok_or_else(|| "Cette catégorie n'existe pas".to_string())?;
+        f(category)?;
+        Ok(category.to_message("Catégorie modifiée"))
+    }
     async fn send_error<D: std::fmt::Display>(ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>, error: D) {
         log_error!("{}", error);
         let mut msg = message::Message::new();
@@ -426,111 +1148,501 @@ impl Tickets {
         });
     }
     async fn ticket_close_channel(&self, ctx: &Context, channel_id: ChannelId) -> Result<(), String> {
-        match self.is_a_ticket(ctx, channel_id).await {
-            Ok(true) => (),
-            Ok(false) => return Err("Ce n'est pas un ticket".to_string()),
-            Err(e) => return Err(e),
-        }
-        if let Err(err) = archive::archive_ticket(ctx, channel_id).await {
-            return Err(format!("Erreur lors de l'archivage du ticket: {}", err));
-        }
+        self.close_channel(ctx, channel_id).await?;
+        self.clear_timers(channel_id).await;
+        Ok(())
+    }
+    /// Archive puis supprime un ticket.
+    /// 
+    /// Les tâches d'inactivité du ticket ne sont pas retirées, cette fonction étant
+    /// appelée par la tâche de fermeture elle-même.
+    async fn close_channel(&self, ctx: &Context, channel_id: ChannelId) -> Result<(), String> {
+        let mut info = self.ticket_info(channel_id).await?;
+        let archived = match archive::archive_ticket(ctx, channel_id, &self.archives_folder, info.access_log.clone()).await {
+            Ok(archived) => archived,
+            Err(err) => return Err(format!("Erreur lors de l'archivage du ticket: {}", err)),
+        };
+        let transcript = self.send_archive(ctx, channel_id, info.owner, &archived.html).await;
         if let Err(err) = channel_id.delete(ctx).await {
             return Err(format!("Erreur lors de la suppression du ticket: {}", err));
         }
+        let closed_at = chrono::Utc::now().timestamp();
+        info.status = TicketStatus::Closed;
+        info.closed_at = Some(closed_at);
+        let owner = info.owner;
+        let mut entry = IndexEntry::new(&archived.archive, info.guild_id, owner, info.category.clone(), closed_at);
+        entry.transcript = transcript;
+        self.index.write().await.write().insert(entry);
+        self.data.write().await.write().tickets.insert(channel_id, info);
+        rating::send_request(ctx, owner, channel_id, &archived.archive.name).await;
         Ok(())
     }
-    async fn is_a_ticket(&self, ctx: &Context, channel_id: ChannelId) -> Result<bool, String> {
-        use serenity::model::channel::Channel;
-        let current_channel = match channel_id.to_channel(ctx).await {
-            Ok(Channel::Guild(chan)) => chan,
-            Ok(_) => return Ok(false),
-            Err(e) => return Err(format!("Une erreur s'est produite lors de la récupération du channel: {}", e)),
+    /// Ajoute à l'index les archives qui n'y sont pas, notamment celles enregistrées avant l'index.
+    ///
+    /// Les informations du ticket sont lues dans le registre. Un ticket absent du registre est attribué au
+    /// seul serveur du bot et à la première personne mentionnée dans le ticket : s'il y a plusieurs serveurs,
+    /// son archive est ignorée.
+    async fn backfill_index(&self, guilds: &[GuildId]) {
+        let archives = match archive::list_archives(&self.archives_folder).await {
+            Ok(archives) => archives,
+            Err(e) => {
+                log_warn!("Indexation des archives impossible: {}", e);
+                return;
+            }
         };
-        let parent_channel = match current_channel.parent_id {
-            Some(id) => id,
-            None => return Ok(false),
+        let mut entries = Vec::new();
+        let mut skipped = 0;
+        for (id, path) in archives {
+            if self.index.read().await.read().contains(id) {
+                continue;
+            }
+            let archive = match archive::read_archive(&path).await {
+                Ok(archive) => archive,
+                Err(e) => {
+                    log_warn!("{}", e);
+                    continue;
+                }
+            };
+            let last_message = archive.messages.iter().map(|message| message.timestamp).max();
+            let info = self.data.read().await.read().tickets.get(&id).cloned();
+            let entry = match (info, guilds) {
+                (Some(info), _) => {
+                    let closed_at = info.closed_at.or(last_message).unwrap_or_default();
+                    IndexEntry::new(&archive, info.guild_id, info.owner, info.category, closed_at)
+                },
+                (None, [guild_id]) => {
+                    let owner = index::first_mention(&archive).unwrap_or_default();
+                    IndexEntry::new(&archive, *guild_id, owner, String::new(), last_message.unwrap_or_default())
+                },
+                (None, _) => {
+                    skipped += 1;
+                    continue;
+                }
+            };
+            entries.push(entry);
+        }
+        if skipped > 0 {
+            log_warn!("{} archive(s) de tickets sans serveur connu n'ont pas été indexées", skipped);
+        }
+        if entries.is_empty() {
+            return;
+        }
+        log_info!("Indexation de {} archive(s) de tickets", entries.len());
+        let mut index = self.index.write().await;
+        let mut index = index.write();
+        for entry in entries {
+            index.insert(entry);
+        }
+    }
+    /// Le salon est un ticket ouvert du registre.
+    async fn is_a_ticket(&self, channel_id: ChannelId) -> bool {
+        self.data.read().await.read().tickets.get(&channel_id)
+            .map(|info| info.status != TicketStatus::Closed)
+            .unwrap_or(false)
+    }
+    async fn is_ticket_owner(&self, channel_id: ChannelId, user_by: UserId) -> bool {
+        self.data.read().await.read().tickets.get(&channel_id)
+            .map(|info| info.status != TicketStatus::Closed && info.owner == user_by)
+            .unwrap_or(false)
+    }
+    /// Met à jour le registre des tickets d'un serveur au démarrage du bot.
+    /// 
+    /// Les tickets dont le salon a été supprimé pendant que le bot était hors ligne sont marqués fermés,
+    /// et les tickets créés avant le registre y sont ajoutés.
+    async fn reconcile_tickets(&self, ctx: &Context, guild_id: GuildId) {
+        let channels = match guild_id.channels(ctx).await {
+            Ok(channels) => channels,
+            Err(e) => {
+                log_warn!("Impossible de récupérer les salons du serveur {}: {}", guild_id, e);
+                return;
+            }
         };
-        {
+        let deleted = {
+            let mut data = self.data.write().await;
+            let mut data = data.write();
+            let now = chrono::Utc::now().timestamp();
+            let mut deleted = Vec::new();
+            for (channel_id, info) in data.tickets.iter_mut().filter(|(_, info)| info.guild_id == guild_id) {
+                if info.created_at == 0 {
+                    info.created_at = channel_id.created_at().unix_timestamp();
+                }
+                if info.status != TicketStatus::Closed && !channels.contains_key(channel_id) {
+                    info.status = TicketStatus::Closed;
+                    info.closed_at = Some(now);
+                    deleted.push(*channel_id);
+                }
+            }
+            deleted
+        };
+        for channel_id in deleted {
+            log_info!("Le ticket {} a été supprimé pendant que le bot était hors ligne", channel_id);
+            self.clear_timers(channel_id).await;
+        }
+        let legacy = {
             let data = self.data.read().await;
             let data = data.read();
-            if let None = data.categories.iter().find(|cat| cat.id == parent_channel.0) {
-                return Ok(false);
-            }
+            channels.values()
+                .filter(|channel| !data.tickets.contains_key(&channel.id))
+                .filter_map(|channel| {
+                    let parent_id = channel.parent_id?;
+                    data.categories.iter()
+                        .find(|category| category.id == parent_id.0)
+                        .map(|category| (channel.id, category.name.clone()))
+                })
+                .collect::<Vec<_>>()
+        };
+        for (channel_id, category) in legacy {
+            let owner = match Self::legacy_ticket_owner(ctx, channel_id).await {
+                Ok(Some(owner)) => owner,
+                Ok(None) => {
+                    log_warn!("Message de présentation du ticket {} introuvable, le salon n'est pas ajouté au registre", channel_id);
+                    continue;
+                },
+                Err(e) => {
+                    log_warn!("Impossible de lire les messages épinglés du ticket {}: {}", channel_id, e);
+                    continue;
+                }
+            };
+            let info = TicketInfo::new(guild_id, owner, category, channel_id.created_at().unix_timestamp());
+            log_info!("Ajout du ticket {} au registre", channel_id);
+            self.data.write().await.write().tickets.insert(channel_id, info);
         }
-        Ok(true)
     }
-    async fn is_ticket_owner(ctx: &Context, channel: ChannelId, user_by: UserId) -> Result<bool, String> {
+    /// Créateur d'un ticket créé avant le registre, mentionné dans le message de présentation épinglé.
+    ///
+    /// Seul le message de présentation envoyé par le bot, avec son bouton de fermeture, est pris en compte :
+    /// un salon de la catégorie n'ayant pas ce message n'est pas un ticket.
+    async fn legacy_ticket_owner(ctx: &Context, channel: ChannelId) -> Result<Option<UserId>, String> {
+        use serenity::model::application::component::ActionRowComponent;
         let pins = match channel.pins(ctx).await {
             Ok(pins) => pins,
             Err(e) => return Err(format!("{}", e))
         };
-        let first_message = match pins.last() {
-            Some(pin) => pin,
-            None => return Ok(false)
-        };
-        Ok(first_message.mentions.iter().find(|m| m.id == user_by).is_some())
+        let bot_id = ctx.cache.current_user_id();
+        let presentation = pins.iter().rev().find(|pin| {
+            pin.author.id == bot_id && pin.components.iter()
+                .flat_map(|row| row.components.iter())
+                .any(|component| matches!(component, ActionRowComponent::Button(button) if button.custom_id.as_deref() == Some("button_ticket_close")))
+        });
+        Ok(presentation.and_then(|pin| pin.mentions.first()).map(|m| m.id))
     }
-    async fn is_staff(ctx: &Context, guild_id: GuildId, user_by: UserId) -> Result<bool, String> {
-        let roles = match guild_id.roles(ctx).await {
-            Ok(roles) => roles,
-            Err(e) => return Err(format!("{}", e))
-        };
-        let staff_role = match roles.into_iter().find(|role| role.1.name == "staff") {
-            Some(role) => role,
-            None => return Err("Le rôle 'staff' n'existe pas.".to_string())
-        };
+    async fn is_staff(&self, ctx: &Context, guild_id: GuildId, user_by: UserId) -> Result<bool, String> {
+        let staff_role = self.settings.staff_role(ctx, guild_id).await?;
         let member = match guild_id.member(ctx, user_by).await {
             Ok(member) => member,
             Err(e) => return Err(format!("{}", e))
         };
-        Ok(member.roles.into_iter().find(|role| role == &staff_role.0).is_some())
+        Ok(member.roles.into_iter().find(|role| role == &staff_role).is_some())
+    }
+    /// Envoie la transcription d'un ticket dans le salon des archives s'il est configuré, 
+    /// et au créateur du ticket si l'envoi en message privé est activé.
+    /// 
+    /// Retourne le lien du message contenant la transcription dans le salon des archives.
+    async fn send_archive(&self, ctx: &Context, channel_id: ChannelId, owner: UserId, transcript: &std::path::Path) -> Option<String> {
+        use serenity::model::channel::Channel;
+        let channel = match channel_id.to_channel(ctx).await {
+            Ok(Channel::Guild(chan)) => chan,
+            _ => return None,
+        };
+        let settings = self.settings.get(channel.guild_id).await;
+        let mut link = None;
+        if let Some(archive_channel) = settings.ticket_archive_channel {
+            match archive_channel.send_files(ctx, [transcript], |msg| msg.content(format!("Transcription du ticket **{}** (`{}`)", channel.name, channel_id))).await {
+                Ok(msg) => link = Some(msg.link()),
+                Err(e) => log_warn!("Impossible d'envoyer l'archive du ticket dans le salon {}: {}", archive_channel, e),
+            }
+        }
+        if !settings.ticket_transcript_dm {
+            return link;
+        }
+        let dm = match owner.create_dm_channel(ctx).await {
+            Ok(dm) => dm,
+            Err(e) => {
+                log_warn!("Impossible d'ouvrir un message privé avec {}: {}", owner, e);
+                return link;
+            }
+        };
+        if let Err(e) = dm.send_files(ctx, [transcript], |msg| msg.content(format!("Voici la transcription de ton ticket **{}**.", channel.name))).await {
+            log_warn!("Impossible d'envoyer la transcription du ticket à {}: {}", owner, e);
+        }
+        link
+    }
+    /// Informations d'un ticket ouvert.
+    async fn ticket_info(&self, channel_id: ChannelId) -> Result<TicketInfo, String> {
+        self.data.read().await.read().tickets.get(&channel_id)
+            .filter(|info| info.status != TicketStatus::Closed)
+            .cloned()
+            .ok_or_else(|| "Ce salon n'est pas un ticket".to_string())
+    }
+    /// Modifie les informations d'un ticket puis met à jour le sujet du salon.
+    async fn update_ticket<F: FnOnce(&mut TicketInfo)>(&self, ctx: &Context, channel_id: ChannelId, f: F) -> Result<TicketInfo, String> {
+        let mut info = self.ticket_info(channel_id).await?;
+        f(&mut info);
+        self.data.write().await.write().tickets.insert(channel_id, info.clone());
+        if let Err(e) = channel_id.edit(ctx, |chan| chan.topic(info.topic())).await {
+            log_warn!("Impossible de mettre à jour le sujet du ticket {}: {}", channel_id, e);
+        }
+        Ok(info)
+    }
+    /// Assigne un ticket à un membre du staff.
+    async fn claim_ticket(&self, ctx: &Context, guild_id: Option<GuildId>, channel_id: ChannelId, user_by: UserId, staff: UserId) -> Result<message::Message, String> {
+        let guild_id = guild_id.ok_or_else(|| "Cette commande n'est pas disponible dans un DM".to_string())?;
+        if !self.is_staff(ctx, guild_id, user_by).await? {
+            return Err("Seul le staff peut prendre en charge un ticket.".to_string());
+        }
+        if staff != user_by && !self.is_staff(ctx, guild_id, staff).await? {
+            return Err(format!("<@{}> ne fait pas partie du staff.", staff));
+        }
+        self.update_ticket(ctx, channel_id, |info| {
+            info.status = TicketStatus::Claimed;
+            info.assigned = Some(staff);
+        }).await?;
+        Ok(message::success(format!("Ticket pris en charge par <@{}>", staff)))
+    }
+    /// Change le statut d'un ticket.
+    async fn set_ticket_status(&self, ctx: &Context, guild_id: Option<GuildId>, channel_id: ChannelId, user_by: UserId, status: TicketStatus) -> Result<message::Message, String> {
+        let guild_id = guild_id.ok_or_else(|| "Cette commande n'est pas disponible dans un DM".to_string())?;
+        if !self.is_staff(ctx, guild_id, user_by).await? {
+            return Err("Seul le staff peut changer le statut d'un ticket.".to_string());
+        }
+        let info = self.update_ticket(ctx, channel_id, |info| {
+            info.status = status;
+            match status {
+                TicketStatus::Open => info.assigned = None,
+                TicketStatus::Claimed if info.assigned.is_none() => info.assigned = Some(user_by),
+                _ => ()
+            }
+        }).await?;
+        Ok(message::success(info.topic()))
+    }
+    /// Liste les tickets ouverts d'un serveur.
+    async fn open_tickets(&self, ctx: &Context, guild_id: Option<GuildId>, user_by: UserId) -> Result<message::Message, String> {
+        let guild_id = guild_id.ok_or_else(|| "Cette commande n'est pas disponible dans un DM".to_string())?;
+        if !self.is_staff(ctx, guild_id, user_by).await? {
+            return Err("Seul le staff peut lister les tickets.".to_string());
+        }
+        let tickets = self.data.read().await.read().tickets.iter()
+            .filter(|(_, info)| info.guild_id == guild_id && info.status != TicketStatus::Closed)
+            .map(|(id, info)| (*id, info.clone()))
+            .collect::<Vec<_>>();
+        if tickets.is_empty() {
+            return Ok(message::info("Aucun ticket ouvert"));
+        }
+        let mut msg = message::Message::new();
+        msg.add_embed(|embed| {
+            embed
+                .title("Tickets ouverts")
+                .color(message::COLOR_INFO);
+            if tickets.len() > 25 {
+                embed.footer(|f| f.text(format!("{} tickets affichés sur {}", 25, tickets.len())));
+            }
+            for (channel_id, info) in tickets.iter().take(25) {
+                let assigned = info.assigned.map(|v| format!("<@{}>", v)).unwrap_or_else(|| "*Personne*".to_string());
+                embed.field(
+                    info.status.name(),
+                    format!("<#{}>\nCréé par <@{}>\nEn charge : {}", channel_id, info.owner, assigned),
+                    true
+                );
+            }
+            embed
+        });
+        Ok(msg)
+    }
+    /// Statistiques des tickets d'un serveur.
+    async fn stats(&self, ctx: &Context, guild_id: Option<GuildId>, user_by: UserId) -> Result<message::Message, String> {
+        let guild_id = guild_id.ok_or_else(|| "Cette commande n'est pas disponible dans un DM".to_string())?;
+        if !self.is_staff(ctx, guild_id, user_by).await? {
+            return Err("Seul le staff peut consulter les statistiques des tickets.".to_string());
+        }
+        let data = self.data.read().await;
+        let data = data.read();
+        Ok(rating::stats_message(data.tickets.iter().filter(|(_, info)| info.guild_id == guild_id)))
+    }
+    /// Recherche parmi les tickets archivés d'un serveur.
+    async fn search_archive_index(&self, ctx: &Context, user_by: UserId, query: SearchQuery) -> Result<message::Message, String> {
+        if !self.is_staff(ctx, query.guild_id, user_by).await? {
+            return Err("Seul le staff peut consulter les archives des tickets.".to_string());
+        }
+        let index = self.index.read().await;
+        let index = index.read();
+        let results = index.search(&query);
+        if results.is_empty() {
+            return Ok(message::info("Aucun ticket archivé ne correspond à la recherche"));
+        }
+        let mut msg = message::Message::new();
+        msg.add_embed(|embed| {
+            embed
+                .title("Tickets archivés")
+                .color(message::COLOR_INFO);
+            if results.len() > 10 {
+                embed.footer(|f| f.text(format!("{} tickets affichés sur {}", 10, results.len())));
+            }
+            for entry in results.iter().take(10) {
+                let category = if entry.category.is_empty() { "*Inconnue*" } else { entry.category.as_str() };
+                let transcript = match &entry.transcript {
+                    Some(link) => format!("[Transcription]({})", link),
+                    None => "*Transcription non envoyée*".to_string(),
+                };
+                embed.field(
+                    format!("{} ({})", entry.name, entry.id),
+                    format!("Créé par <@{}> · {}\nDu <t:{}:d> au <t:{}:d>\n{}", entry.owner, category, entry.opened_at, entry.closed_at, transcript),
+                    false
+                );
+            }
+            embed
+        });
+        Ok(msg)
+    }
+    /// Enregistre la note donnée par le créateur d'un ticket fermé.
+    async fn rate_ticket(&self, channel_id: ChannelId, user_by: UserId, stars: u8) -> Result<(), String> {
+        let mut data = self.data.write().await;
+        let mut data = data.write();
+        let info = data.tickets.get_mut(&channel_id)
+            .ok_or_else(|| "Ce ticket n'existe plus".to_string())?;
+        if info.owner != user_by {
+            return Err("Seul le créateur du ticket peut le noter.".to_string());
+        }
+        let comment = info.rating.take().and_then(|rating| rating.comment);
+        info.rating = Some(TicketRating { stars, comment });
+        Ok(())
+    }
+    /// Enregistre le commentaire du créateur d'un ticket noté.
+    async fn comment_ticket(&self, channel_id: ChannelId, user_by: UserId, comment: String) -> Result<(), String> {
+        let mut data = self.data.write().await;
+        let mut data = data.write();
+        let rating = data.tickets.get_mut(&channel_id)
+            .filter(|info| info.owner == user_by)
+            .and_then(|info| info.rating.as_mut())
+            .ok_or_else(|| "Ce ticket n'a pas été noté".to_string())?;
+        rating.comment = Some(comment);
+        Ok(())
+    }
+    /// Lance les tâches d'inactivité d'un nouveau ticket.
+    async fn start_timers(&self, channel_id: ChannelId, owner: UserId, policy: InactivityPolicy) {
+        let mut timers = self.timers.write().await;
+        let timers = match timers.as_mut() {
+            Some(timers) => timers,
+            None => return,
+        };
+        for kind in [TimerKind::Reminder, TimerKind::Close] {
+            let until = chrono::Utc::now() + chrono::Duration::seconds(policy.delay(kind) as _);
+            if let Err(e) = timers.add(TicketTimer { channel_id, owner, policy, kind }, until).await {
+                log_warn!("Impossible de lancer la tâche d'inactivité du ticket {}: {}", channel_id, e);
+            }
+        }
+    }
+    /// Repousse les tâches d'inactivité d'un ticket après un nouveau message.
+    ///
+    /// Les tâches ne sont replanifiées que si leur échéance recule d'au moins [`TIMER_RESET_THRESHOLD`] secondes,
+    /// pour ne pas réécrire le registre à chaque message. Les tâches en cours d'exécution ne sont pas modifiées.
+    async fn reset_timers(&self, channel_id: ChannelId) {
+        let now = chrono::Utc::now();
+        let outdated = |task: &task::Task<TicketTimer>| {
+            task.until + TIMER_RESET_THRESHOLD < now.timestamp() + task.data.policy.delay(task.data.kind) as i64
+        };
+        let pending = match self.timers.read().await.as_ref() {
+            Some(timers) => timers.registry().lock().await.find_all(|t| t.data.channel_id == channel_id).await,
+            None => return,
+        };
+        let (owner, policy) = match pending.first() {
+            Some((_, task)) => (task.data.owner, task.data.policy),
+            None => return,
+        };
+        // Le rappel a déjà été envoyé: il est relancé pour la prochaine période d'inactivité
+        let reminder_sent = !pending.iter().any(|(_, task)| task.data.kind == TimerKind::Reminder);
+        if !reminder_sent && !pending.iter().any(|(_, task)| outdated(task)) {
+            return;
+        }
+        let mut timers = self.timers.write().await;
+        let timers = match timers.as_mut() {
+            Some(timers) => timers,
+            None => return,
+        };
+        for (id, task) in pending.iter().filter(|(_, task)| outdated(task)) {
+            if timers.is_running(*id) {
+                continue;
+            }
+            let until = now + chrono::Duration::seconds(policy.delay(task.data.kind) as _);
+            if let Err(e) = timers.reschedule(*id, task.data.clone(), until).await {
+                log_warn!("Impossible de repousser la tâche d'inactivité du ticket {}: {}", channel_id, e);
+            }
+        }
+        if reminder_sent {
+            let until = now + chrono::Duration::seconds(policy.delay(TimerKind::Reminder) as _);
+            if let Err(e) = timers.add(TicketTimer { channel_id, owner, policy, kind: TimerKind::Reminder }, until).await {
+                log_warn!("Impossible de relancer le rappel d'inactivité du ticket {}: {}", channel_id, e);
+            }
+        }
+    }
+    /// Retire les tâches d'inactivité d'un ticket.
+    async fn clear_timers(&self, channel_id: ChannelId) {
+        let mut timers = self.timers.write().await;
+        let timers = match timers.as_mut() {
+            Some(timers) => timers,
+            None => return,
+        };
+        let pending = timers.registry().lock().await.find_all(|t| t.data.channel_id == channel_id).await;
+        for (id, _) in pending {
+            if let Err(e) = timers.remove(id).await {
+                log_warn!("Impossible de retirer la tâche d'inactivité du ticket {}: {}", channel_id, e);
+            }
+        }
+    }
+    /// Vérifie qu'un membre peut créer un ticket dans une catégorie :
+    /// il ne doit pas être bloqué, ni avoir atteint la limite de tickets ouverts ou le délai entre deux tickets.
+    async fn check_limits(&self, category: &CategoryTicket, user_id: UserId) -> Result<(), String> {
+        if category.blacklist.contains(&user_id) {
+            return Err(format!("Tu ne peux pas créer de ticket dans la catégorie {}. Contacte le staff si tu penses qu'il s'agit d'une erreur.", category.name));
+        }
+        let data = self.data.read().await;
+        let data = data.read();
+        let tickets = data.tickets.iter()
+            .filter(|(_, info)| info.owner == user_id && info.category == category.name)
+            .collect::<Vec<_>>();
+        if let Some(max_open) = category.max_open {
+            let open = tickets.iter().filter(|(_, info)| info.status != TicketStatus::Closed).count() as u64;
+            if open >= max_open {
+                return Err(format!("Tu as déjà {} ticket(s) ouvert(s) dans la catégorie {}, le maximum est de {}. Ferme un ticket avant d'en ouvrir un nouveau.", open, category.name, max_open));
+            }
+        }
+        if let Some(cooldown) = category.cooldown {
+            let last = tickets.iter().map(|(_, info)| info.created_at).max();
+            if let Some(last) = last {
+                let elapsed = (chrono::Utc::now().timestamp() - last).max(0) as u64;
+                if elapsed < cooldown {
+                    return Err(format!("Tu dois attendre encore {} avant d'ouvrir un nouveau ticket dans la catégorie {}.", time::format_duration(cooldown - elapsed), category.name));
+                }
+            }
+        }
+        Ok(())
     }
     async fn reset_message_choose(&self, new_ids: Option<(u64, u64)>) {
         self.data.write().await.write().msg_choose = new_ids;
     }
-    async fn ticket_create(&self, ctx: &Context, guild_id: GuildId, user_id: UserId, category: CategoryTicket) -> Result<ChannelId, String> {
-        use serenity::model::channel::{PermissionOverwrite, PermissionOverwriteType, ChannelType};
-        use serenity::model::permissions::Permissions;
-        use serenity::model::application::component::ButtonStyle;
-        let role_staff = match guild_id.roles(ctx).await {
-            Ok(roles) => {
-                let role = roles.iter().find(|(_, role)| role.name == "staff");
-                match role {
-                    Some((role_id, _)) => *role_id,
-                    None => {
-                        log_error!("Une erreur s'est produite lors de la création du ticket: Le role 'staff' n'existe pas.");
-                        return Err("Une erreur s'est produite lors de la création du ticket.".to_string());
-                    }
-                }
-            },
-            Err(e) => return Err(format!("Erreur lors de la récupération des roles: {}", e))
-        };
-        let everyone = RoleId(guild_id.0);
-        
-        let permissions = vec![
-            PermissionOverwrite {
-                allow: Permissions::VIEW_CHANNEL,
-                deny: Permissions::default(),
-                kind: PermissionOverwriteType::Member(user_id),
-            },
-            PermissionOverwrite {
-                allow: Permissions::VIEW_CHANNEL,
-                deny: Permissions::default(),
-                kind: PermissionOverwriteType::Role(role_staff),
-            },
-            PermissionOverwrite {
-                allow: Permissions::default(),
-                deny: Permissions::VIEW_CHANNEL,
-                kind: PermissionOverwriteType::Role(everyone),
-            },
-        ];
+    /// Crée un ticket et retourne le message de réponse à l'interaction.
+    async fn ticket_create_message(&self, ctx: &Context, guild_id: GuildId, user_id: UserId, category: CategoryTicket, answers: Vec<(String, String)>) -> message::Message {
+        match self.ticket_create(ctx, guild_id, user_id, category, answers).await {
+            Ok(result) => message::success(format!("Ticket créé: <#{}>", result)),
+            Err(e) => {
+                log_error!("Erreur lors de la création du ticket: {}", e);
+                message::error(e)
+            }
+        }
+    }
+    async fn ticket_create(&self, ctx: &Context, guild_id: GuildId, user_id: UserId, category: CategoryTicket, answers: Vec<(String, String)>) -> Result<ChannelId, String> {
+        use serenity::model::channel::ChannelType;
+        self.check_limits(&category, user_id).await?;
+        let role_staff = self.settings.staff_role(ctx, guild_id).await?;
+        let permissions = Self::ticket_permissions(guild_id, role_staff, user_id, &[]);
         let username = match user_id.to_user(ctx).await {
             Ok(user) => user.name,
             Err(_) => user_id.to_string()
         };
+        let info = TicketInfo::new(guild_id, user_id, category.name.clone(), chrono::Utc::now().timestamp());
         let new_channel = match guild_id.create_channel(ctx, |chan| {
             chan
                 .name(format!("{}-{}", category.prefix, username))
+                .topic(info.topic())
                 .kind(ChannelType::Text)
                 .category(category.id)
                 .permissions(permissions)
@@ -538,11 +1650,191 @@ impl Tickets {
             Ok(chan) => chan,
             Err(e) => return Err(format!("Erreur lors de la création du ticket: {}", e))
         };
-        let mut msg_prez = match new_channel.say(ctx, format!("Hey <@{}>, par ici !\nDès que tu as fini avec le ticket, appuie sur le bouton \"Fermer le ticket\".", user_id.0)).await {
+        self.data.write().await.write().tickets.insert(new_channel.id, info);
+        if let Some(policy) = category.inactivity {
+            self.start_timers(new_channel.id, user_id, policy).await;
+        }
+        let content = format!("Hey <@{}>, par ici !\nDès que tu as fini avec le ticket, appuie sur le bouton \"Fermer le ticket\".", user_id.0);
+        if let Err(e) = Self::send_presentation(ctx, new_channel.id, content, &answers).await {
+            return Err(format!("{}\nLe salon a tout de même été créé: <#{}>", e, new_channel.id.0));
+        }
+
+        Ok(new_channel.id)
+    }
+    /// Répond à une commande d'ajout ou de retrait d'accès au ticket.
+    async fn edit_access_response(&self, ctx: &Context, app_cmd: ApplicationCommandEmbed<'_>, target: AccessTarget, level: Option<AccessLevel>) {
+        let delay_resp = match app_cmd.delayed_response(ctx, false).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                log_error!("Erreur lors de l'envoi du message: {}", e);
+                return;
+            }
+        };
+        let msg = self.edit_access(ctx, app_cmd.0.guild_id, app_cmd.0.channel_id, app_cmd.0.user.id, target, level).await
+            .unwrap_or_else(message::error);
+        delay_resp.send_message(msg).await.unwrap_or_else(|e| {
+            log_error!("Erreur lors de l'envoi du message: {}", e);
+        });
+    }
+    /// Accorde (`level` défini) ou retire (`level` à `None`) l'accès d'un membre ou d'un rôle au ticket.
+    /// 
+    /// Réservé au staff et au créateur du ticket, les rôles ne pouvant être modifiés que par le staff.
+    /// La modification est ajoutée à l'historique du ticket.
+    async fn edit_access(&self, ctx: &Context, guild_id: Option<GuildId>, channel_id: ChannelId, user_by: UserId, target: AccessTarget, level: Option<AccessLevel>) -> Result<message::Message, String> {
+        let guild_id = guild_id.ok_or_else(|| "Cette commande n'est pas disponible dans un DM".to_string())?;
+        let info = self.ticket_info(channel_id).await?;
+        let is_owner = self.is_ticket_owner(channel_id, user_by).await;
+        let is_staff = match is_owner {
+            true => self.is_staff(ctx, guild_id, user_by).await.unwrap_or(false),
+            false => self.is_staff(ctx, guild_id, user_by).await?,
+        };
+        if !is_owner && !is_staff {
+            return Err("Vous n'avez pas la permission de modifier les accès au ticket.".to_string());
+        }
+        match target {
+            AccessTarget::Member(user_id) if user_id == info.owner => return Err("Le créateur du ticket y a toujours accès.".to_string()),
+            // Le rôle @everyone a l'identifiant du serveur : le modifier rendrait le ticket public
+            AccessTarget::Role(role_id) if role_id.0 == guild_id.0 => return Err("L'accès du rôle @everyone aux tickets ne peut pas être modifié.".to_string()),
+            AccessTarget::Role(_) if !is_staff => return Err("Seul le staff peut modifier l'accès des rôles au ticket.".to_string()),
+            AccessTarget::Role(role_id) if self.settings.staff_role(ctx, guild_id).await.ok() == Some(role_id) => return Err("Le staff a toujours accès aux tickets.".to_string()),
+            _ => (),
+        }
+        let result = match level {
+            Some(level) => channel_id.create_permission(ctx, &TicketAccess { target, level }.overwrite()).await,
+            None => channel_id.delete_permission(ctx, target.overwrite_type()).await,
+        };
+        if let Err(e) = result {
+            return Err(format!("Impossible de modifier l'accès de {}: {}", target, e));
+        }
+        self.update_ticket(ctx, channel_id, |info| {
+            info.access.retain(|access| access.target != target);
+            if let Some(level) = level {
+                info.access.push(TicketAccess { target, level });
+            }
+            info.access_log.push(AccessEvent::new(target, level, user_by));
+        }).await?;
+        Ok(match level {
+            Some(level) => message::success(format!("{} a bien été ajouté au ticket ({}).", target, level.name())),
+            None => message::success(format!("{} a bien été retiré du ticket.", target)),
+        })
+    }
+    /// Rouvre un ticket fermé dans sa catégorie d'origine.
+    /// 
+    /// Le créateur et les membres ajoutés retrouvent l'accès au salon, 
+    /// et l'historique de l'archive y est republié.
+    async fn reopen_ticket(&self, ctx: &Context, guild_id: Option<GuildId>, user_by: UserId, id: &str) -> Result<ChannelId, String> {
+        use serenity::model::channel::ChannelType;
+        let guild_id = guild_id.ok_or_else(|| "Cette commande n'est pas disponible dans un DM".to_string())?;
+        if !self.is_staff(ctx, guild_id, user_by).await? {
+            return Err("Seul le staff peut rouvrir un ticket.".to_string());
+        }
+        let old_id = id.trim().parse::<u64>()
+            .map(ChannelId)
+            .map_err(|_| format!("Identifiant de ticket invalide: {}", id))?;
+        let info = self.data.read().await.read().tickets.get(&old_id).cloned()
+            .ok_or_else(|| "Aucune information n'est enregistrée pour ce ticket".to_string())?;
+        if info.guild_id != guild_id {
+            return Err("Ce ticket n'appartient pas à ce serveur".to_string());
+        }
+        if info.status != TicketStatus::Closed {
+            return Err("Ce ticket n'est pas fermé".to_string());
+        }
+        if let Some(reopened_as) = info.reopened_as {
+            return Err(format!("Ce ticket a déjà été rouvert: <#{}>", reopened_as));
+        }
+        let category = self.data.read().await.read().categories.iter()
+            .find(|category| category.name == info.category)
+            .cloned()
+            .ok_or_else(|| format!("La catégorie \"{}\" n'existe plus", info.category))?;
+        let archive = archive::load_archive(&self.archives_folder, old_id.0).await?;
+        let role_staff = self.settings.staff_role(ctx, guild_id).await?;
+        let permissions = Self::ticket_permissions(guild_id, role_staff, info.owner, &info.access);
+        let info = TicketInfo {
+            created_at: chrono::Utc::now().timestamp(),
+            status: TicketStatus::Open,
+            closed_at: None,
+            rating: None,
+            // Le ticket rouvert retourne dans la file du staff, sans prise en charge
+            assigned: None,
+            reopened_from: Some(old_id),
+            reopened_as: None,
+            ..info
+        };
+        let new_channel = match guild_id.create_channel(ctx, |chan| {
+            chan
+                .name(&archive.name)
+                .topic(info.topic())
+                .kind(ChannelType::Text)
+                .category(category.id)
+                .permissions(permissions)
+        }).await {
+            Ok(chan) => chan,
+            Err(e) => return Err(format!("Erreur lors de la création du salon: {}", e))
+        };
+        {
+            let mut data = self.data.write().await;
+            let mut data = data.write();
+            // Le ticket fermé est conservé pour son avis et les statistiques
+            if let Some(closed) = data.tickets.get_mut(&old_id) {
+                closed.reopened_as = Some(new_channel.id);
+            }
+            data.tickets.insert(new_channel.id, info.clone());
+        }
+        if let Some(policy) = category.inactivity {
+            self.start_timers(new_channel.id, info.owner, policy).await;
+        }
+        let content = format!("<@{}>, ton ticket **{}** a été rouvert par le staff. La conversation reprend ci-dessous.", info.owner, archive.name);
+        if let Err(e) = Self::send_presentation(ctx, new_channel.id, content, &[]).await {
+            return Err(format!("{}\nLe salon a tout de même été créé: <#{}>", e, new_channel.id.0));
+        }
+        if let Err(e) = replay::replay(ctx, new_channel.id, &archive).await {
+            return Err(format!("{}\nLe salon a tout de même été créé: <#{}>", e, new_channel.id.0));
+        }
+        Ok(new_channel.id)
+    }
+    /// Permissions d'un salon de ticket : visible uniquement par le staff, le créateur
+    /// et les membres ou rôles ajoutés.
+    fn ticket_permissions(guild_id: GuildId, role_staff: RoleId, owner: UserId, access: &[TicketAccess]) -> Vec<serenity::model::channel::PermissionOverwrite> {
+        use serenity::model::channel::{PermissionOverwrite, PermissionOverwriteType};
+        use serenity::model::permissions::Permissions;
+        let mut permissions = vec![PermissionOverwrite {
+            allow: Permissions::VIEW_CHANNEL,
+            deny: Permissions::default(),
+            kind: PermissionOverwriteType::Member(owner),
+        }];
+        permissions.extend(access.iter().map(|access| access.overwrite()));
+        permissions.push(PermissionOverwrite {
+            allow: Permissions::VIEW_CHANNEL,
+            deny: Permissions::default(),
+            kind: PermissionOverwriteType::Role(role_staff),
+        });
+        permissions.push(PermissionOverwrite {
+            allow: Permissions::default(),
+            deny: Permissions::VIEW_CHANNEL,
+            kind: PermissionOverwriteType::Role(RoleId(guild_id.0)),
+        });
+        permissions
+    }
+    /// Envoie et épingle le message de présentation d'un ticket.
+    async fn send_presentation(ctx: &Context, channel_id: ChannelId, content: String, answers: &[(String, String)]) -> Result<(), String> {
+        use serenity::model::application::component::ButtonStyle;
+        let mut msg_prez = match channel_id.say(ctx, content).await {
             Ok(msg) => msg,
-            Err(e) => return Err(format!("Erreur pendent l'envoi du message de presentation: {}\nLe salon a tout de même été créé: <#{}>", e, new_channel.id.0))
+            Err(e) => return Err(format!("Erreur pendent l'envoi du message de presentation: {}", e))
         };
         msg_prez.edit(ctx, |msg| {
+            if !answers.is_empty() {
+                msg.embed(|embed| {
+                    embed
+                        .title("Formulaire")
+                        .color(message::COLOR_INFO);
+                    for (question, answer) in answers {
+                        let answer = if answer.is_empty() { "*Pas de réponse*" } else { answer.as_str() };
+                        embed.field(question, answer, false);
+                    }
+                    embed
+                });
+            }
             msg.components(|cmps| {
                 cmps.create_action_row(|action|{
                     action.create_button(|button|{
@@ -550,6 +1842,12 @@ impl Tickets {
                             .label("Fermer le ticket")
                             .style(ButtonStyle::Danger)
                             .custom_id("button_ticket_close")
+                    });
+                    action.create_button(|button|{
+                        button
+                            .label("Prendre en charge")
+                            .style(ButtonStyle::Primary)
+                            .custom_id("button_ticket_claim")
                     })
                 })
             })
@@ -560,6 +1858,100 @@ impl Tickets {
             log_warn!("Erreur lors du pin du message de présentation: {}", e);
         });
 
-        Ok(new_channel.id)
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cddio_core::testing::{Harness, Reply, fixtures::{self, CommandBuilder}};
+    use serde_json::json;
+
+    /// Composant de tickets avec ses propres fichiers de données, les tests s'exécutant en parallèle.
+    fn tickets() -> Arc<Tickets> {
+        let id = fixtures::next_id();
+        Arc::new_cyclic(|me| Tickets {
+            data: RwLock::new(Data::new(&format!("tickets-{}", id), Default::default())),
+            index: RwLock::new(Data::new(&format!("tickets_archives-{}", id), Default::default())),
+            ..Tickets::new(Arc::new(Settings::new(Vec::new())), me.clone())
+        })
+    }
+
+    fn category(id: u64) -> CategoryTicket {
+        CategoryTicket { name: "Support".to_string(), prefix: "support".to_string(), id, ..Default::default() }
     }
-}
\ No newline at end of file
+
+    /// Prépare les réponses de l'API pour un ticket créé dans le salon `channel_id`.
+    fn mock_ticket(harness: &Harness, staff: u64, owner: u64, channel_id: u64, name: &str) {
+        let channel = fixtures::channel(channel_id, fixtures::GUILD_ID, name);
+        harness.mock.respond("GET", "/guilds/*/roles", Reply::Json(200, json!([fixtures::role(staff, "staff")])));
+        harness.mock.respond("GET", &format!("/users/{}", owner), Reply::Json(200, fixtures::user(owner)));
+        harness.mock.respond("POST", "/guilds/*/channels", Reply::Json(200, channel.clone()));
+        harness.mock.respond("GET", &format!("/channels/{}", channel_id), Reply::Json(200, channel.clone()));
+        harness.mock.respond("GET", &format!("/channels/{}/messages", channel_id), Reply::Json(200, json!([])));
+        harness.mock.respond("DELETE", &format!("/channels/{}", channel_id), Reply::Json(200, channel));
+    }
+
+    #[tokio::test]
+    async fn ticket_is_opened_then_archived_on_close() {
+        let harness = Harness::new().await.unwrap();
+        let tickets = tickets();
+        let (staff, owner, channel_id, category_id) = (fixtures::next_id(), fixtures::next_id(), fixtures::next_id(), fixtures::next_id());
+        let name = format!("support-user{}", owner);
+        mock_ticket(&harness, staff, owner, channel_id, &name);
+
+        let created = tickets.ticket_create(harness.context(), GuildId(fixtures::GUILD_ID), UserId(owner), category(category_id), Vec::new()).await;
+        assert_eq!(created, Ok(ChannelId(channel_id)));
+
+        let creations = harness.mock.find("POST", "/guilds/*/channels");
+        assert_eq!(creations.len(), 1);
+        assert_eq!(creations[0].body["name"], name);
+        assert_eq!(creations[0].body["parent_id"], category_id);
+        let presentations = harness.mock.find("POST", &format!("/channels/{}/messages", channel_id));
+        assert_eq!(presentations.len(), 1);
+        assert!(presentations[0].body["content"].as_str().unwrap().contains(&format!("<@{}>", owner)));
+        assert_eq!(harness.mock.find("PUT", &format!("/channels/{}/pins/*", channel_id)).len(), 1);
+        assert!(tickets.is_ticket_owner(ChannelId(channel_id), UserId(owner)).await);
+
+        harness.command(tickets.as_ref(), CommandBuilder::new("ticket").subcommand("close").channel_id(channel_id).author(owner, &[])).await;
+
+        // Le ticket est supprimé sans réponse d'erreur
+        assert!(harness.mock.find("POST", "/interactions/*/*/callback").is_empty());
+        assert_eq!(harness.mock.find("DELETE", &format!("/channels/{}", channel_id)).len(), 1);
+        assert!(!tickets.is_a_ticket(ChannelId(channel_id)).await);
+        let archive = archive::load_archive(&tickets.archives_folder, channel_id).await.unwrap();
+        assert_eq!(archive.name, name);
+        assert!(tickets.archives_folder.starts_with(DATA_DIR.as_path()));
+        assert!(tickets.archives_folder.join(format!("{}-{}.html", channel_id, name)).exists());
+    }
+
+    #[tokio::test]
+    async fn reopened_ticket_is_unassigned() {
+        let harness = Harness::new().await.unwrap();
+        let tickets = tickets();
+        let (staff, owner, channel_id, category_id) = (fixtures::next_id(), fixtures::next_id(), fixtures::next_id(), fixtures::next_id());
+        let name = format!("support-user{}", owner);
+        mock_ticket(&harness, staff, owner, channel_id, &name);
+        harness.mock.respond("GET", &format!("/guilds/*/members/{}", staff), Reply::Json(200, fixtures::member(staff, &[staff])));
+        let guild_id = GuildId(fixtures::GUILD_ID);
+        tickets.data.write().await.write().categories.push(category(category_id));
+
+        tickets.ticket_create(harness.context(), guild_id, UserId(owner), category(category_id), Vec::new()).await.unwrap();
+        tickets.set_ticket_status(harness.context(), Some(guild_id), ChannelId(channel_id), UserId(staff), TicketStatus::Claimed).await.unwrap();
+        assert_eq!(tickets.ticket_info(ChannelId(channel_id)).await.unwrap().assigned, Some(UserId(staff)));
+        tickets.ticket_close_channel(harness.context(), ChannelId(channel_id)).await.unwrap();
+
+        // Le salon rouvert est créé avec un nouvel identifiant
+        let new_id = fixtures::next_id();
+        harness.mock.respond("POST", "/guilds/*/channels", Reply::Json(200, fixtures::channel(new_id, fixtures::GUILD_ID, &name)));
+        let reopened = tickets.reopen_ticket(harness.context(), Some(guild_id), UserId(staff), &channel_id.to_string()).await;
+        assert_eq!(reopened, Ok(ChannelId(new_id)));
+
+        let info = tickets.ticket_info(ChannelId(new_id)).await.unwrap();
+        assert_eq!(info.status, TicketStatus::Open);
+        assert_eq!(info.assigned, None);
+        assert_eq!(info.reopened_from, Some(ChannelId(channel_id)));
+        assert_eq!(info.owner, UserId(owner));
+    }
+}
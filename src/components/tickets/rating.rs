@@ -0,0 +1,169 @@
+//! Enquête de satisfaction des tickets
+//!
+//! À la fermeture d'un ticket, son créateur reçoit en message privé une rangée de boutons
+//! pour noter le ticket de 1 à 5 étoiles, suivie d'une fenêtre pour laisser un commentaire.
+//! Le salon du ticket est transmis dans le custom_id des boutons et de la fenêtre.
+
+use std::collections::{BTreeMap, HashMap};
+use cddio_core::{message, modal::Modal};
+use serde::{Deserialize, Serialize};
+use serenity::{
+    client::Context,
+    model::id::*,
+};
+use crate::log_warn;
+
+use super::{TicketInfo, TicketStatus};
+use super::super::utils::time_parser as time;
+
+/// Préfixe du custom_id des boutons de notation: `ticket_rating:<salon>:<note>`
+pub const RATING_PREFIX: &str = "ticket_rating:";
+/// Préfixe du custom_id de la fenêtre de commentaire: `ticket_comment:<salon>`
+pub const COMMENT_PREFIX: &str = "ticket_comment:";
+/// Note maximale d'un ticket
+pub const MAX_STARS: u8 = 5;
+
+/// Avis laissé par le créateur d'un ticket après sa fermeture
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TicketRating {
+    /// Note de 1 à [`MAX_STARS`]
+    pub stars: u8,
+    /// Commentaire facultatif
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+/// Représentation de la note en étoiles.
+pub fn stars(stars: u8) -> String {
+    "⭐".repeat(stars as usize)
+}
+
+/// Retrouve le salon et la note d'un bouton de notation.
+pub fn parse_rating(custom_id: &str) -> Option<(ChannelId, u8)> {
+    let mut args = custom_id.strip_prefix(RATING_PREFIX)?.split(':');
+    let channel_id = args.next()?.parse().ok()?;
+    let stars = args.next()?.parse().ok()?;
+    match stars {
+        1..=MAX_STARS => Some((ChannelId(channel_id), stars)),
+        _ => None
+    }
+}
+
+/// Retrouve le salon d'une fenêtre de commentaire.
+pub fn parse_comment(custom_id: &str) -> Option<ChannelId> {
+    custom_id.strip_prefix(COMMENT_PREFIX)?.parse().ok().map(ChannelId)
+}
+
+/// Fenêtre de commentaire affichée après la notation.
+pub fn comment_modal(channel_id: ChannelId) -> Modal {
+    let mut modal = Modal::new(format!("{}{}", COMMENT_PREFIX, channel_id), "Ton avis sur le ticket");
+    modal.add_field("comment", "Un commentaire ? (facultatif)", true, false);
+    modal
+}
+
+/// Envoie la demande de notation au créateur d'un ticket fermé.
+pub async fn send_request(ctx: &Context, owner: UserId, channel_id: ChannelId, name: &str) {
+    let dm = match owner.create_dm_channel(ctx).await {
+        Ok(dm) => dm,
+        Err(e) => {
+            log_warn!("Impossible d'ouvrir un message privé avec {}: {}", owner, e);
+            return;
+        }
+    };
+    let result = dm.send_message(ctx, |msg| {
+        msg
+            .content(format!("Ton ticket **{}** a été fermé. Comment évaluerais-tu l'aide reçue ?", name))
+            .components(|cmps| {
+                cmps.create_action_row(|row| {
+                    for n in 1..=MAX_STARS {
+                        row.create_button(|button| {
+                            button
+                                .label(format!("{} ⭐", n))
+                                .custom_id(format!("{}{}:{}", RATING_PREFIX, channel_id, n))
+                        });
+                    }
+                    row
+                })
+            })
+    }).await;
+    if let Err(e) = result {
+        log_warn!("Impossible d'envoyer la demande de notation du ticket {} à {}: {}", channel_id, owner, e);
+    }
+}
+
+/// Médiane d'une liste de durées.
+fn median(mut values: Vec<u64>) -> Option<u64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        Some((values[mid - 1] + values[mid]) / 2)
+    } else {
+        Some(values[mid])
+    }
+}
+
+/// Statistiques des tickets d'un serveur :
+/// note moyenne, nombre de tickets par catégorie et
+/// temps médian de fermeture par membre du staff en charge.
+pub fn stats_message<'a, I>(tickets: I) -> message::Message
+    where I: IntoIterator<Item = (&'a ChannelId, &'a TicketInfo)>
+{
+    let mut ratings = Vec::new();
+    let mut categories: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    let mut durations: HashMap<UserId, Vec<u64>> = HashMap::new();
+    for (_, info) in tickets {
+        let category = categories.entry(info.category.as_str()).or_default();
+        category.0 += 1;
+        if info.status != TicketStatus::Closed {
+            category.1 += 1;
+            continue;
+        }
+        if let Some(rating) = &info.rating {
+            ratings.push(rating.stars as u64);
+        }
+        if let (Some(staff), Some(closed_at)) = (info.assigned, info.closed_at) {
+            durations.entry(staff).or_default().push((closed_at - info.created_at).max(0) as u64);
+        }
+    }
+    let average = match ratings.len() {
+        0 => "*Aucun avis*".to_string(),
+        n => format!("{:.2}/{} ({} avis)", ratings.iter().sum::<u64>() as f64 / n as f64, MAX_STARS, n),
+    };
+    let volume = match categories.is_empty() {
+        true => "*Aucun ticket*".to_string(),
+        false => categories.iter()
+            .map(|(name, (total, open))| {
+                let name = if name.is_empty() { "*Inconnue*" } else { name };
+                format!("**{}** : {} ticket(s), dont {} ouvert(s)", name, total, open)
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+    let mut durations = durations.into_iter()
+        .filter_map(|(staff, values)| {
+            let count = values.len();
+            median(values).map(|median| (staff, median, count))
+        })
+        .collect::<Vec<_>>();
+    durations.sort_by_key(|(_, median, _)| *median);
+    let durations = match durations.is_empty() {
+        true => "*Aucun ticket fermé pris en charge*".to_string(),
+        false => durations.iter()
+            .map(|(staff, median, count)| format!("<@{}> : {} ({} ticket(s))", staff, time::format_duration(*median), count))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+    let mut msg = message::Message::new();
+    msg.add_embed(|embed| {
+        embed
+            .title("Statistiques des tickets")
+            .color(message::COLOR_INFO)
+            .field("Note moyenne", average, false)
+            .field("Tickets par catégorie", volume, false)
+            .field("Temps médian de fermeture", durations, false)
+    });
+    msg
+}
@@ -0,0 +1,96 @@
+//! Restitution de l'historique d'un ticket rouvert
+//!
+//! Les messages archivés sont republiés avec un webhook, sous le nom et l'avatar
+//! de leur auteur. Les messages consécutifs d'un même auteur sont regroupés et
+//! seuls les derniers groupes sont republiés.
+
+use std::collections::HashMap;
+use serenity::{client::Context, model::id::ChannelId};
+use crate::log_warn;
+
+use super::archive::intern::{ArchiveChannel, ArchiveUser};
+
+/// Nombre maximum de messages republiés.
+const MAX_REPLAYED: usize = 50;
+/// Taille maximale du contenu d'un message Discord.
+const MAX_CONTENT_LEN: usize = 2000;
+/// Taille maximale du nom d'un webhook.
+const MAX_USERNAME_LEN: usize = 80;
+
+/// Messages consécutifs d'un même auteur
+struct Group<'a> {
+    author: Option<&'a ArchiveUser>,
+    content: String,
+}
+
+fn truncate(text: &str, len: usize) -> String {
+    if text.chars().count() <= len {
+        text.to_string()
+    } else {
+        format!("{}…", text.chars().take(len - 1).collect::<String>())
+    }
+}
+
+/// Regroupe les messages consécutifs de chaque auteur, du plus ancien au plus récent.
+fn condense(archive: &ArchiveChannel) -> Vec<Group<'_>> {
+    let users = archive.users.iter().map(|u| (u.id, u)).collect::<HashMap<_, _>>();
+    let mut ordered = archive.messages.iter().collect::<Vec<_>>();
+    ordered.sort_by_key(|m| (m.timestamp, m.id));
+    let mut groups: Vec<(u64, Group)> = Vec::new();
+    for message in ordered {
+        let mut lines = Vec::new();
+        if !message.content.is_empty() {
+            lines.push(message.content.clone());
+        }
+        lines.extend(message.attachments.iter().map(|url| format!("📎 {}", url)));
+        if lines.is_empty() {
+            continue;
+        }
+        let lines = lines.join("\n");
+        match groups.last_mut() {
+            Some((user_id, group)) if *user_id == message.user_id => {
+                group.content.push('\n');
+                group.content.push_str(&lines);
+            },
+            _ => groups.push((message.user_id, Group {
+                author: users.get(&message.user_id).copied(),
+                content: lines,
+            })),
+        }
+    }
+    let skip = groups.len().saturating_sub(MAX_REPLAYED);
+    groups.into_iter().skip(skip).map(|(_, group)| group).collect()
+}
+
+/// Republie l'historique de `archive` dans le salon `channel_id`.
+pub async fn replay(ctx: &Context, channel_id: ChannelId, archive: &ArchiveChannel) -> Result<(), String> {
+    let groups = condense(archive);
+    if groups.is_empty() {
+        return Ok(());
+    }
+    let webhook = channel_id.create_webhook(ctx, "Historique du ticket").await
+        .map_err(|e| format!("Impossible de créer le webhook de restitution: {}", e))?;
+    for group in &groups {
+        let (name, avatar) = match group.author {
+            Some(user) => (user.name.as_str(), user.avatar.as_str()),
+            None => ("Utilisateur inconnu", ""),
+        };
+        let result = webhook.execute(ctx, true, |msg| {
+            msg
+                .username(truncate(name, MAX_USERNAME_LEN))
+                .content(truncate(&group.content, MAX_CONTENT_LEN))
+                .allowed_mentions(|mentions| mentions.empty_parse());
+            if !avatar.is_empty() {
+                msg.avatar_url(avatar);
+            }
+            msg
+        }).await;
+        if let Err(e) = result {
+            log_warn!("Erreur lors de la restitution d'un message du ticket {}: {}", channel_id, e);
+        }
+    }
+    if let Err(e) = webhook.delete(ctx).await {
+        log_warn!("Impossible de supprimer le webhook de restitution du ticket {}: {}", channel_id, e);
+    }
+    Ok(())
+}
@@ -0,0 +1,216 @@
+//! Rendu HTML des archives de tickets
+//!
+//! Le fichier produit est autonome : le style est intégré au document et
+//! seuls les avatars et les pièces jointes pointent vers Discord.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use chrono::{TimeZone, Utc};
+
+use super::archive::intern::{ArchiveChannel, ArchiveMessage, ArchiveUser};
+use super::access::{AccessEvent, AccessTarget};
+
+/// Nombre de caractères du message cité lors d'une réponse.
+const REPLY_EXCERPT_LEN: usize = 80;
+
+const STYLE: &str = r#"
+body { background: #36393f; color: #dcddde; font-family: "Helvetica Neue", Helvetica, Arial, sans-serif; margin: 0; padding: 16px; }
+header { border-bottom: 1px solid #4f545c; margin-bottom: 16px; padding-bottom: 8px; }
+header h1 { color: #fff; font-size: 20px; margin: 0; }
+header p { color: #a3a6aa; font-size: 13px; margin: 4px 0 0; }
+.message { display: flex; padding: 6px 0; }
+.message:target { background: #4f545c; }
+.avatar { border-radius: 50%; height: 40px; margin-right: 12px; width: 40px; flex-shrink: 0; background: #5865f2; }
+.author { color: #fff; font-weight: 600; }
+.time { color: #a3a6aa; font-size: 12px; margin-left: 6px; }
+.content { white-space: pre-wrap; word-wrap: break-word; margin-top: 2px; }
+.reply { border-left: 3px solid #4f545c; color: #a3a6aa; font-size: 13px; margin-bottom: 2px; padding-left: 6px; }
+.reply a { color: #a3a6aa; text-decoration: none; }
+.attachment a { color: #00aff4; }
+.access { border-bottom: 1px solid #4f545c; color: #a3a6aa; font-size: 13px; margin-bottom: 16px; padding-bottom: 8px; }
+.access ul { margin: 4px 0 0; padding-left: 20px; }
+"#;
+
+/// Échappe les caractères spéciaux HTML.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn format_timestamp(timestamp: i64) -> String {
+    match Utc.timestamp_opt(timestamp, 0).single() {
+        Some(date) => date.format("%d/%m/%Y %H:%M").to_string(),
+        None => timestamp.to_string(),
+    }
+}
+
+fn excerpt(text: &str) -> String {
+    if text.chars().count() <= REPLY_EXCERPT_LEN {
+        text.to_string()
+    } else {
+        format!("{}…", text.chars().take(REPLY_EXCERPT_LEN).collect::<String>())
+    }
+}
+
+fn render_message(html: &mut String, message: &ArchiveMessage, users: &HashMap<u64, &ArchiveUser>, messages: &HashMap<u64, &ArchiveMessage>) -> std::fmt::Result {
+    let author = users.get(&message.user_id);
+    let name = author.map(|u| u.name.as_str()).unwrap_or("Utilisateur inconnu");
+    writeln!(html, r#"<div class="message" id="msg-{}">"#, message.id)?;
+    match author {
+        Some(user) if !user.avatar.is_empty() => writeln!(html, r#"<img class="avatar" src="{}" alt="">"#, escape(&user.avatar))?,
+        _ => writeln!(html, r#"<div class="avatar"></div>"#)?,
+    }
+    writeln!(html, "<div>")?;
+    if let Some(reply_id) = message.in_reply_to {
+        let reply = match messages.get(&reply_id) {
+            Some(replied) => {
+                let replied_name = users.get(&replied.user_id).map(|u| u.name.as_str()).unwrap_or("Utilisateur inconnu");
+                format!("{} : {}", escape(replied_name), escape(&excerpt(&replied.content)))
+            },
+            None => "Message supprimé".to_string(),
+        };
+        writeln!(html, r##"<div class="reply"><a href="#msg-{}">↪ {}</a></div>"##, reply_id, reply)?;
+    }
+    writeln!(html, r#"<span class="author">{}</span><span class="time">{}</span>"#, escape(name), format_timestamp(message.timestamp))?;
+    if !message.content.is_empty() {
+        writeln!(html, r#"<div class="content">{}</div>"#, escape(&message.content))?;
+    }
+    for attachment in &message.attachments {
+        let filename = attachment.rsplit('/').next().unwrap_or(attachment);
+        writeln!(html, r#"<div class="attachment"><a href="{}">📎 {}</a></div>"#, escape(attachment), escape(filename))?;
+    }
+    writeln!(html, "</div>\n</div>")
+}
+
+fn render_access(html: &mut String, events: &[AccessEvent], users: &HashMap<u64, &ArchiveUser>) -> std::fmt::Result {
+    let user_name = |id: u64| users.get(&id).map(|u| escape(&u.name)).unwrap_or_else(|| format!("Utilisateur {}", id));
+    writeln!(html, r#"<div class="access">Accès au ticket<ul>"#)?;
+    for event in events {
+        let target = match event.target {
+            AccessTarget::Member(user_id) => user_name(user_id.0),
+            AccessTarget::Role(role_id) => format!("Rôle {}", role_id),
+        };
+        let action = match event.level {
+            Some(level) => format!("a ajouté {} ({})", target, level.name()),
+            None => format!("a retiré {}", target),
+        };
+        writeln!(html, "<li>{} · {} {}</li>", format_timestamp(event.timestamp), user_name(event.by.0), action)?;
+    }
+    writeln!(html, "</ul></div>")
+}
+
+/// Génère la transcription HTML d'un ticket archivé.
+pub fn render(archive: &ArchiveChannel) -> String {
+    let users = archive.users.iter().map(|u| (u.id, u)).collect::<HashMap<_, _>>();
+    let messages = archive.messages.iter().map(|m| (m.id, m)).collect::<HashMap<_, _>>();
+    // Les messages sont archivés du plus récent au plus ancien
+    let mut ordered = archive.messages.iter().collect::<Vec<_>>();
+    ordered.sort_by_key(|m| (m.timestamp, m.id));
+
+    let mut html = String::new();
+    let _ = writeln!(html, r#"<!DOCTYPE html>
+<html lang="fr">
+<head>
+<meta charset="utf-8">
+<title>Ticket {name}</title>
+<style>{style}</style>
+</head>
+<body>
+<header>
+<h1>#{name}</h1>
+<p>{count} message(s) · {participants} participant(s)</p>
+</header>"#,
+        name = escape(&archive.name),
+        style = STYLE,
+        count = archive.messages.len(),
+        participants = archive.users.len(),
+    );
+    if !archive.access_log.is_empty() {
+        let _ = render_access(&mut html, &archive.access_log, &users);
+    }
+    for message in ordered {
+        let _ = render_message(&mut html, message, &users, &messages);
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: u64, name: &str) -> ArchiveUser {
+        ArchiveUser { id, avatar: String::new(), name: name.to_string() }
+    }
+
+    fn message(id: u64, user_id: u64, content: &str, in_reply_to: Option<u64>) -> ArchiveMessage {
+        ArchiveMessage { id, user_id, content: content.to_string(), attachments: Vec::new(), in_reply_to, timestamp: id as i64 }
+    }
+
+    fn archive(messages: Vec<ArchiveMessage>) -> ArchiveChannel {
+        ArchiveChannel {
+            id: 1,
+            name: "ticket-<test>".to_string(),
+            users: vec![user(10, "Alice#0001"), user(20, "<b>Bob</b>#0002")],
+            messages,
+            access_log: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn html_is_escaped() {
+        let html = render(&archive(vec![message(1, 20, "<script>alert('x')</script> & \"co\"", None)]));
+        assert!(html.contains("<title>Ticket ticket-&lt;test&gt;</title>"));
+        assert!(html.contains("&lt;b&gt;Bob&lt;/b&gt;#0002"));
+        assert!(html.contains("&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt; &amp; &quot;co&quot;"));
+        assert!(!html.contains("<script>"));
+    }
+
+    #[test]
+    fn messages_are_rendered_oldest_first() {
+        // Les archives sont enregistrées du plus récent au plus ancien
+        let html = render(&archive(vec![message(2, 20, "deuxième", None), message(1, 10, "premier", None)]));
+        assert!(html.find("premier").unwrap() < html.find("deuxième").unwrap());
+    }
+
+    #[test]
+    fn replies_link_to_the_replied_message() {
+        let long = "a".repeat(REPLY_EXCERPT_LEN + 10);
+        let html = render(&archive(vec![
+            message(1, 10, &long, None),
+            message(2, 20, "réponse", Some(1)),
+            message(3, 20, "réponse à un message supprimé", Some(42)),
+        ]));
+        let excerpt = format!("Alice#0001 : {}…", "a".repeat(REPLY_EXCERPT_LEN));
+        assert!(html.contains(&format!(r##"<a href="#msg-1">↪ {}</a>"##, excerpt)));
+        assert!(html.contains(r##"<a href="#msg-42">↪ Message supprimé</a>"##));
+        assert!(html.contains(r#"<div class="message" id="msg-1">"#));
+    }
+
+    #[test]
+    fn attachments_are_linked_by_filename() {
+        let mut msg = message(1, 10, "", None);
+        msg.attachments.push("https://cdn.discordapp.com/attachments/1/2/capture&1.png".to_string());
+        let html = render(&archive(vec![msg]));
+        assert!(html.contains(r#"<a href="https://cdn.discordapp.com/attachments/1/2/capture&amp;1.png">📎 capture&amp;1.png</a>"#));
+        // Un message sans texte n'a pas de bloc de contenu
+        assert!(!html.contains(r#"<div class="content">"#));
+    }
+
+    #[test]
+    fn unknown_authors_are_rendered() {
+        let html = render(&archive(vec![message(1, 99, "bonjour", None)]));
+        assert!(html.contains(r#"<span class="author">Utilisateur inconnu</span>"#));
+        assert!(html.contains("1 message(s) · 2 participant(s)"));
+    }
+}
@@ -11,7 +11,21 @@ use serde::{de::DeserializeOwned, Serialize};
 lazy_static! {
     /// Chemin du dossier contenant les données.
     /// = "$CWD/data"
-    pub static ref DATA_DIR: PathBuf = env::current_dir().unwrap().join("data");
+    /// 
+    /// Pendant les tests, un dossier temporaire propre à l'exécution est utilisé
+    /// pour ne pas lire ni modifier les données locales.
+    pub static ref DATA_DIR: PathBuf = data_dir();
+}
+
+#[cfg(not(test))]
+fn data_dir() -> PathBuf {
+    env::current_dir().unwrap().join("data")
+}
+#[cfg(test)]
+fn data_dir() -> PathBuf {
+    let path = env::temp_dir().join(format!("cddio-tests-{}", std::process::id()));
+    fs::create_dir_all(&path).unwrap();
+    path
 }
 #[derive(Debug)]
 pub enum DataError {
@@ -94,7 +108,22 @@ impl<T> Data<T>
     pub fn write<'a>(&'a mut self) -> DataGuard<'a, T> {
         DataGuard(self)
     }
+    /// Sérialise les données sans les enregistrer.
+    /// 
+    /// Avec [`write_file`], permet d'écrire le fichier après avoir relâché le verrou protégeant les données.
+    pub fn to_json(&self) -> DataResult<String> {
+        serde_json::ser::to_string_pretty(&self.value).map_err(SerdeError)
+    }
 }
+
+/// Enregistre le contenu sérialisé de la donnée `name` dans le fichier correspondant.
+pub fn write_file(name: &str, content: &str) -> DataResult<()> {
+    if !DATA_DIR.exists() {
+        fs::create_dir_all(DATA_DIR.as_path()).map_err(FileError)?;
+    }
+    fs::write(DATA_DIR.join(format!("{}.json", name)), content).map_err(FileError)
+}
+
 impl<T> Data<T> 
     where T: DeserializeOwned + Serialize + Default
 {
@@ -135,23 +164,15 @@ impl<T> Drop for DataGuard<'_, T>
 where T: DeserializeOwned + Serialize + Default
 {
     fn drop(&mut self) {
-        let ron_content = match serde_json::ser::to_string_pretty(&self.0.value) {
+        let ron_content = match self.0.to_json() {
             Ok(content) => content,
             Err(err) => {
-                eprintln!("Saving {} - Unable to serialize the data: {}", self.0.name, err);
+                eprintln!("Saving {} - Unable to serialize the data: {:?}", self.0.name, err);
                 return;
             }
         };
-        if !DATA_DIR.exists() {
-            if let Err(e) = fs::create_dir_all(DATA_DIR.as_path()) {
-                eprintln!("Saving {} - Unable to create the data directory: {}", self.0.name, e);
-                return;
-            }
-        }
-        let path_file = DATA_DIR.join(format!("{}.json", self.0.name));
-
-        fs::write(path_file, &ron_content).unwrap_or_else(|err| {
-            eprintln!("Saving {} - Unable to write the file: {}", self.0.name, err);
+        write_file(&self.0.name, &ron_content).unwrap_or_else(|err| {
+            eprintln!("Saving {} - Unable to write the file: {:?}", self.0.name, err);
         });
     }
-}
\ No newline at end of file
+}
@@ -1,10 +1,18 @@
 //! Utilities for the components.
 
 pub mod task;
+pub mod registry_file;
 pub mod time_parser;
 pub mod data;
 
 #[inline]
 pub fn user_fullname(user: &serenity::model::user::User) -> String {
     format!("{}#{:0>4}", user.name, user.discriminator)
-}
\ No newline at end of file
+}
+/// Check if the author of the command is an administrator of the guild or an owner of the bot.
+pub fn is_admin(app_cmd: &cddio_core::ApplicationCommandEmbed, owners: &[serenity::model::id::UserId]) -> bool {
+    let is_admin = app_cmd.0.member.as_ref()
+        .and_then(|member| member.permissions)
+        .is_some_and(|permissions| permissions.administrator());
+    is_admin || owners.contains(&app_cmd.0.user.id)
+}
@@ -1,23 +1,30 @@
+//! Registre de tâches enregistré dans un fichier JSON.
+
 use std::collections::HashMap;
 use crate::log_error;
 
 use async_std::io::WriteExt;
 use futures_locks::RwLock;
 
+use serde::{Serialize, de::DeserializeOwned};
 use serenity::{
     async_trait
 };
 
-use super::sanction::Sanction;
 use super::task;
 
-pub struct RegistryFile {
+/// Registre de tâches persistant.
+/// 
+/// Les tâches sont enregistrées dans un fichier JSON à chaque modification.
+pub struct RegistryFile<D: task::DataFunc + Clone> {
     path_file: std::path::PathBuf,
-    tasks: RwLock<HashMap<task::TaskID, task::Task<Sanction>>>,
+    tasks: RwLock<HashMap<task::TaskID, task::Task<D>>>,
     task_counter: RwLock<task::TaskID>
 }
 
-impl RegistryFile {
+impl<D> RegistryFile<D> where 
+    D: task::DataFunc + Clone + Serialize + DeserializeOwned
+{
     pub async fn from_file(path_file: impl AsRef<std::path::Path>) -> Result<Self, String> {
         let res = Self {
             path_file: path_file.as_ref().to_path_buf(),
@@ -29,7 +36,7 @@ impl RegistryFile {
     }
     async fn save(&self) -> Result<(), String> {
         let log_error = |msg, e| {
-            let e = format!("modo::RegistryFile::save: {}: {}", msg, e);
+            let e = format!("RegistryFile::save: {}: {}", msg, e);
             log_error!("{}", e);
             e
         };
@@ -45,7 +52,7 @@ impl RegistryFile {
     }
     async fn load(&self) -> Result<(), String> {
         let log_error = |msg, e| {
-            let e = format!("modo::RegistryFile::load: {}: {}", msg, e);
+            let e = format!("RegistryFile::load: {}: {}", msg, e);
             log_error!("{}", e);
             e
         };
@@ -62,8 +69,10 @@ impl RegistryFile {
     }
 }
 #[async_trait]
-impl task::Registry for RegistryFile {
-    type Data = Sanction;
+impl<D> task::Registry for RegistryFile<D> where 
+    D: task::DataFunc + Clone + Serialize + DeserializeOwned
+{
+    type Data = D;
     async fn register(&mut self, task: task::Task<Self::Data>) -> Result<task::TaskID, String> {
         let id = self.task_counter.read().await.clone();
         self.tasks.write().await.insert(id, task);
@@ -80,6 +89,13 @@ impl task::Registry for RegistryFile {
             Err(e) => Err(e)
         }
     }
+    async fn update(&mut self, id: task::TaskID, task: task::Task<Self::Data>) -> Result<(), String> {
+        match self.tasks.write().await.get_mut(&id) {
+            Some(v) => *v = task,
+            None => return Err(format!("Task {} not found", id))
+        }
+        self.save().await
+    }
 
     async fn get(&self, id: task::TaskID) -> Option<task::Task<Self::Data>> {
         self.tasks.read().await.iter().find(|(vid, _)| vid == &&id).map(|(_, task)| task.clone())
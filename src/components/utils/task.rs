@@ -3,7 +3,7 @@
 //! Used to create and feed a tasks manager. 
 //! Every task is executed in background after a bunch of time.
 
-use std::{time::Duration, collections::HashMap, sync::Arc};
+use std::{time::Duration, collections::{HashMap, HashSet}, sync::{Arc, Mutex as StdMutex}};
 
 use chrono::{DateTime, Utc, TimeZone};
 use futures_locks::Mutex;
@@ -32,6 +32,7 @@ pub trait Registry
     type Data: DataFunc + Clone;
     async fn register(&mut self, task: Task<Self::Data>) -> Result<TaskID, String>;
     async fn unregister(&mut self, id: TaskID) -> Result<(), String>;
+    async fn update(&mut self, id: TaskID, task: Task<Self::Data>) -> Result<(), String>;
     async fn get(&self, id: TaskID) -> Option<Task<Self::Data>>;
     async fn get_all(&self) -> Vec<(TaskID, Task<Self::Data>)>;
     async fn find_one<F>(&self, f: F) -> Option<(TaskID, Task<Self::Data>)> where
@@ -42,6 +43,36 @@ pub trait Registry
 
 type Tasks<R> = Arc<Mutex<R>>;
 
+/// État d'exécution des tâches d'un gestionnaire
+#[derive(Default)]
+struct Execution {
+    /// Le gestionnaire s'arrête : les tâches qui se réveillent ne sont pas exécutées
+    stopping: bool,
+    /// Tâches en cours d'exécution
+    running: HashSet<TaskID>,
+    /// Génération de la tâche lancée pour chaque identifiant.
+    /// 
+    /// Une tâche qui se réveille ne s'exécute que si sa génération est toujours celle du registre :
+    /// une tâche retirée ou replanifiée est ainsi ignorée même si elle se réveille pendant son annulation.
+    generations: HashMap<TaskID, u64>,
+    /// Prochaine génération attribuée
+    next_generation: u64,
+}
+
+impl Execution {
+    /// Annule la tâche en attente `id`, sous le même verrou que le réveil des tâches.
+    /// 
+    /// Retourne une erreur si la tâche est déjà en cours d'exécution.
+    fn cancel(&mut self, id: TaskID, handle: &tokio::task::JoinHandle<()>) -> Result<(), String> {
+        if self.running.contains(&id) {
+            return Err("Task is running".to_string());
+        }
+        self.generations.remove(&id);
+        handle.abort();
+        Ok(())
+    }
+}
+
 pub struct TaskManager<D, R, P> where
     D: DataFunc<Persistent = P> + Clone,
     R: Registry<Data = D> + Send + 'static,
@@ -49,7 +80,8 @@ pub struct TaskManager<D, R, P> where
 {
     tasks: Tasks<R>,
     task_handles: HashMap<TaskID, tokio::task::JoinHandle<()>>,
-    persistent: Arc<P>
+    persistent: Arc<P>,
+    execution: Arc<StdMutex<Execution>>,
 }
 
 impl<D, R, P> TaskManager<D, R, P> where
@@ -61,7 +93,8 @@ impl<D, R, P> TaskManager<D, R, P> where
         Self {
             tasks: Arc::new(Mutex::new(registry)),
             task_handles: HashMap::new(),
-            persistent: Arc::new(persistent_data)
+            persistent: Arc::new(persistent_data),
+            execution: Arc::new(StdMutex::new(Execution::default())),
         }
     }
     pub async fn init(&mut self) {
@@ -87,6 +120,14 @@ impl<D, R, P> TaskManager<D, R, P> where
     fn spawn_task(&self, id: TaskID, data: D, until: DateTime<Utc>) -> tokio::task::JoinHandle<()> {
         let tasks = Arc::clone(&self.tasks);
         let persistent = Arc::clone(&self.persistent);
+        let execution = Arc::clone(&self.execution);
+        let generation = {
+            let mut execution = execution.lock().unwrap_or_else(|e| e.into_inner());
+            let generation = execution.next_generation;
+            execution.next_generation += 1;
+            execution.generations.insert(id, generation);
+            generation
+        };
         tokio::spawn(async move {
             log_info!("Task {}: Spawning", id);
             let seconds = until.timestamp() - Utc::now().timestamp();
@@ -95,23 +136,71 @@ impl<D, R, P> TaskManager<D, R, P> where
                 log_info!("Task {}: Sleeping for {} seconds", id, seconds);
                 tokio::time::sleep(duration).await;
             }
-            log_info!("Task {}: Running", id);
-            if let Err(e) = data.run(&*persistent).await {
-                log_error!("Task {} failed: {}", id, e);
-                return;
+            {
+                let mut execution = execution.lock().unwrap_or_else(|e| e.into_inner());
+                if execution.stopping {
+                    log_info!("Task {}: Postponed to the next start", id);
+                    return;
+                }
+                if execution.generations.get(&id) != Some(&generation) {
+                    log_info!("Task {}: Cancelled", id);
+                    return;
+                }
+                execution.running.insert(id);
             }
-            if let Err(e) = Self::remove_from_registry(&tasks, id).await {
-                log_error!("Task {} failed to remove from registry: {}", id, e);
-                return;
+            Self::execute(&tasks, id, &data, &persistent).await;
+            let mut execution = execution.lock().unwrap_or_else(|e| e.into_inner());
+            execution.running.remove(&id);
+            if execution.generations.get(&id) == Some(&generation) {
+                execution.generations.remove(&id);
             }
-            log_info!("Task {}: Finished", id);
         })
     }
+    async fn execute(tasks: &Tasks<R>, id: TaskID, data: &D, persistent: &P) {
+        log_info!("Task {}: Running", id);
+        if let Err(e) = data.run(persistent).await {
+            log_error!("Task {} failed: {}", id, e);
+            return;
+        }
+        if let Err(e) = Self::remove_from_registry(tasks, id).await {
+            log_error!("Task {} failed to remove from registry: {}", id, e);
+            return;
+        }
+        log_info!("Task {}: Finished", id);
+    }
+    /// Arrête le gestionnaire.
+    /// 
+    /// Les tâches en cours d'exécution sont attendues. Les tâches en attente sont annulées :
+    /// elles restent dans le registre et reprendront au prochain démarrage.
+    pub async fn shutdown(&mut self) {
+        let running = {
+            let mut execution = self.execution.lock().unwrap_or_else(|e| e.into_inner());
+            execution.stopping = true;
+            execution.running.clone()
+        };
+        for (id, handle) in self.task_handles.drain() {
+            if !running.contains(&id) {
+                handle.abort();
+                continue;
+            }
+            log_info!("Task {}: Waiting for the end of its execution", id);
+            if let Err(e) = handle.await {
+                log_error!("Task {} failed: {}", id, e);
+            }
+        }
+    }
+    /// Vérifie si la tâche est en cours d'exécution.
+    pub fn is_running(&self, id: TaskID) -> bool {
+        self.execution.lock().unwrap_or_else(|e| e.into_inner()).running.contains(&id)
+    }
+    /// Retire une tâche du gestionnaire.
+    /// 
+    /// Une tâche en cours d'exécution n'est pas interrompue.
     pub async fn remove(&mut self, id: TaskID) -> Result<(), String> {
-        match self.task_handles.get(&id){
+        match self.task_handles.remove(&id) {
             Some(handle) => {
-                handle.abort();
-                self.task_handles.remove(&id);
+                // Une tâche en cours d'exécution n'est pas interrompue
+                let _ = self.execution.lock().unwrap_or_else(|e| e.into_inner()).cancel(id, &handle);
                 if let Err(e) = Self::remove_from_registry(&self.tasks, id).await {
                     log_error!("Task {} failed to remove from registry: {}", id, e);
                 }
@@ -120,6 +209,25 @@ impl<D, R, P> TaskManager<D, R, P> where
             None => Err("Task not found".to_string())
         }
     }
+    /// Replanifie une tâche existante à une nouvelle date.
+    /// 
+    /// La tâche en attente est annulée puis relancée avec les nouvelles données.
+    /// Une tâche en cours d'exécution ne peut pas être replanifiée.
+    pub async fn reschedule(&mut self, id: TaskID, data: D, until: DateTime<Utc>) -> Result<(), String> {
+        let handle = match self.task_handles.get(&id) {
+            Some(handle) => handle,
+            None => return Err("Task not found".to_string())
+        };
+        self.execution.lock().unwrap_or_else(|e| e.into_inner()).cancel(id, handle)?;
+        self.task_handles.remove(&id);
+        self.tasks.lock().await.update(id, Task {
+            until: until.timestamp(),
+            data: data.clone()
+        }).await?;
+        let handle = self.spawn_task(id, data, until);
+        self.task_handles.insert(id, handle);
+        Ok(())
+    }
     async fn remove_from_registry(tasks: &Tasks<R>, id: TaskID) -> Result<(), String> {
         let mut registry = tasks.lock().await;
         registry.unregister(id).await
@@ -146,4 +254,106 @@ impl<D, R, P> Drop for TaskManager<D, R, P> where
             task.abort();
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Tâche qui compte ses exécutions, en ajoutant sa valeur au compteur partagé.
+    #[derive(Clone, Debug)]
+    struct Count(usize);
+
+    #[async_trait]
+    impl DataFunc for Count {
+        type Persistent = AtomicUsize;
+        async fn run(&self, persistent: &AtomicUsize) -> Result<(), String> {
+            persistent.fetch_add(self.0, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct MemoryRegistry {
+        tasks: HashMap<TaskID, Task<Count>>,
+        next_id: TaskID,
+    }
+
+    #[async_trait]
+    impl Registry for MemoryRegistry {
+        type Data = Count;
+        async fn register(&mut self, task: Task<Count>) -> Result<TaskID, String> {
+            self.next_id += 1;
+            self.tasks.insert(self.next_id, task);
+            Ok(self.next_id)
+        }
+        async fn unregister(&mut self, id: TaskID) -> Result<(), String> {
+            self.tasks.remove(&id);
+            Ok(())
+        }
+        async fn update(&mut self, id: TaskID, task: Task<Count>) -> Result<(), String> {
+            self.tasks.insert(id, task);
+            Ok(())
+        }
+        async fn get(&self, id: TaskID) -> Option<Task<Count>> {
+            self.tasks.get(&id).cloned()
+        }
+        async fn get_all(&self) -> Vec<(TaskID, Task<Count>)> {
+            self.tasks.iter().map(|(id, task)| (*id, task.clone())).collect()
+        }
+        async fn find_one<F>(&self, f: F) -> Option<(TaskID, Task<Count>)> where F: Fn(&Task<Count>) -> bool + Send {
+            self.tasks.iter().find(|(_, task)| f(task)).map(|(id, task)| (*id, task.clone()))
+        }
+        async fn find_all<F>(&self, f: F) -> Vec<(TaskID, Task<Count>)> where F: Fn(&Task<Count>) -> bool + Send {
+            self.tasks.iter().filter(|(_, task)| f(task)).map(|(id, task)| (*id, task.clone())).collect()
+        }
+    }
+
+    type Manager = TaskManager<Count, MemoryRegistry, AtomicUsize>;
+
+    async fn settle(manager: &Manager) {
+        for _ in 0..100 {
+            tokio::task::yield_now().await;
+        }
+        assert!(manager.execution.lock().unwrap().running.is_empty());
+    }
+
+    #[tokio::test]
+    async fn removed_tasks_are_never_executed() {
+        let mut manager = Manager::new(MemoryRegistry::default(), AtomicUsize::new(0));
+        // Tâche déjà échue : elle se réveille immédiatement, pendant son retrait
+        let id = manager.add(Count(1), Utc::now()).await.unwrap();
+        manager.remove(id).await.unwrap();
+        settle(&manager).await;
+
+        assert_eq!(manager.persistent.load(Ordering::SeqCst), 0);
+        assert!(manager.registry().lock().await.get(id).await.is_none());
+        assert!(manager.execution.lock().unwrap().generations.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rescheduled_tasks_run_once_with_the_new_data() {
+        let mut manager = Manager::new(MemoryRegistry::default(), AtomicUsize::new(0));
+        let id = manager.add(Count(1), Utc::now() + chrono::Duration::hours(1)).await.unwrap();
+        manager.reschedule(id, Count(10), Utc::now()).await.unwrap();
+        while manager.registry().lock().await.get(id).await.is_some() {
+            tokio::task::yield_now().await;
+        }
+        settle(&manager).await;
+
+        assert_eq!(manager.persistent.load(Ordering::SeqCst), 10);
+    }
+
+    #[tokio::test]
+    async fn running_tasks_are_not_rescheduled() {
+        let mut manager = Manager::new(MemoryRegistry::default(), AtomicUsize::new(0));
+        let id = manager.add(Count(1), Utc::now() + chrono::Duration::hours(1)).await.unwrap();
+        // La tâche est marquée en cours d'exécution, comme si elle venait de se réveiller
+        manager.execution.lock().unwrap().running.insert(id);
+
+        assert_eq!(manager.reschedule(id, Count(10), Utc::now()).await, Err("Task is running".to_string()));
+        assert!(manager.task_handles.contains_key(&id));
+        assert_eq!(manager.registry().lock().await.get(id).await.unwrap().data.0, 1);
+        manager.execution.lock().unwrap().running.remove(&id);
+    }
+}